@@ -0,0 +1,26 @@
+#![no_main]
+
+use json_parser::{parse, parse_documents, parse_lenient, FeedParser, JsonReader};
+use libfuzzer_sys::fuzz_target;
+
+// every entry point must return, never panic, whatever the input
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    let _ = parse(String::from(input));
+    let _ = parse_documents(String::from(input));
+    let (_, errors) = parse_lenient(String::from(input));
+    for err in errors {
+        let _ = err.render(input);
+    }
+    let mut reader = JsonReader::new(data);
+    while let Ok(Some(_)) = reader.next_event() {}
+    let mut parser = FeedParser::new();
+    for chunk in data.chunks(3) {
+        parser.feed(chunk);
+        while parser.poll().is_some() {}
+    }
+    parser.finish();
+    while parser.poll().is_some() {}
+});
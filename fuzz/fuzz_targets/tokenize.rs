@@ -0,0 +1,23 @@
+#![no_main]
+
+use json_parser::tokenize::{tokenize_recovering, tokenize_spanned};
+use json_parser::{ParseOptions, SurrogatePolicy};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(input) = std::str::from_utf8(data) else {
+        return;
+    };
+    for surrogates in [
+        SurrogatePolicy::Error,
+        SurrogatePolicy::Replace,
+        SurrogatePolicy::Preserve,
+    ] {
+        let options = ParseOptions {
+            surrogates,
+            ..ParseOptions::default()
+        };
+        let _ = tokenize_spanned(input, &options);
+        let _ = tokenize_recovering(input, &options);
+    }
+});
@@ -0,0 +1,681 @@
+use crate::error::kind_predicates;
+use crate::path::{Path, PathSegment};
+use crate::{ErrorKind, Map, Value};
+use std::borrow::Cow;
+use std::fmt;
+
+/// Why a value couldn't be set at a dot path
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PathError {
+    /// the path can't be read, something can't go at this byte offset into it
+    Syntax { offset: usize },
+    /// the value here is in the way, it can't hold the key or index that comes next
+    Blocked(Path),
+    /// the index at the end of this path is further past the end of its array than arrays are
+    /// padded with nulls
+    TooFar(Path),
+}
+
+impl fmt::Display for PathError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathError::Syntax { offset } => write!(f, "invalid path at offset {offset}"),
+            PathError::Blocked(path) => write!(f, "the value at {path} is in the way"),
+            PathError::TooFar(path) => {
+                write!(
+                    f,
+                    "the index at {path} is too far past the end of its array"
+                )
+            }
+        }
+    }
+}
+
+impl PathError {
+    /// The category of the error: a path that can't be read is `Syntax`, one the value can't
+    /// take is `Data`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            PathError::Syntax { .. } => ErrorKind::Syntax,
+            PathError::Blocked(_) | PathError::TooFar(_) => ErrorKind::Data,
+        }
+    }
+}
+
+kind_predicates!(PathError);
+
+impl std::error::Error for PathError {}
+
+impl Value {
+    /// The member of an object under `key` compared without regard to case, for payloads from
+    /// case-insensitive systems such as HTTP headers. An exact match is preferred, then the
+    /// smallest of the keys that match. `None` when nothing matches or this isn't an object.
+    pub fn get_ignore_case(&self, key: &str) -> Option<&Value> {
+        let Value::Object(members) = self else {
+            return None;
+        };
+        if let Some(member) = members.get(key) {
+            return Some(member);
+        }
+        let key = key.to_lowercase();
+        members
+            .iter()
+            .filter(|(name, _)| name.to_lowercase() == key)
+            .min_by_key(|&(name, _)| name)
+            .map(|(_, member)| member)
+    }
+}
+
+impl Value {
+    /// The value as a number, also reading one from a string such as `" 42 "` and taking
+    /// `true` as 1 and `false` as 0, for upstream APIs that aren't consistent about types.
+    /// `None` for null, containers and strings that aren't a finite number.
+    pub fn as_f64_lossy(&self) -> Option<f64> {
+        match self {
+            Value::Number(number) => Some(*number),
+            Value::Boolean(boolean) => Some(f64::from(u8::from(*boolean))),
+            Value::String(string) => string
+                .trim()
+                .parse()
+                .ok()
+                .filter(|number: &f64| number.is_finite()),
+            _ => None,
+        }
+    }
+
+    /// The value as a boolean, also taking the numbers 1 and 0, and the strings `"1"`, `"0"`
+    /// and any casing of `"true"` and `"false"`. `None` for anything else.
+    pub fn as_bool_lossy(&self) -> Option<bool> {
+        match self {
+            Value::Boolean(boolean) => Some(*boolean),
+            Value::Number(number) if *number == 1.0 => Some(true),
+            Value::Number(number) if *number == 0.0 => Some(false),
+            Value::String(string) => match string.trim() {
+                "1" => Some(true),
+                "0" => Some(false),
+                string if string.eq_ignore_ascii_case("true") => Some(true),
+                string if string.eq_ignore_ascii_case("false") => Some(false),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// The value as a string, writing numbers and booleans out the way they'd read in JSON,
+    /// e.g. `42` as `"42"`. `None` for null and containers.
+    pub fn as_string_lossy(&self) -> Option<Cow<'_, str>> {
+        match self {
+            Value::String(string) => Some(Cow::Borrowed(string)),
+            Value::Number(number) => Some(Cow::Owned(number.to_string())),
+            Value::Boolean(boolean) => Some(Cow::Borrowed(if *boolean { "true" } else { "false" })),
+            _ => None,
+        }
+    }
+}
+
+/// The largest integer a 64 bit float holds exactly along with every integer below it, 2^53 - 1
+const MAX_SAFE_INTEGER: f64 = 9_007_199_254_740_991.0;
+
+/// Defines getters for a number as an integer of some type, each given the bounds it accepts
+macro_rules! integer_getters {
+    ($($name:ident -> $t:ty, $min:expr, $max:expr;)*) => {$(
+        #[doc = concat!(
+            "The number as an `", stringify!($t), "`, `None` when it has a fractional part, ",
+            "is out of range or isn't a number, rather than truncating or saturating"
+        )]
+        pub fn $name(&self) -> Option<$t> {
+            self.integer_within($min as f64, $max as f64)
+                .map(|number| number as $t)
+        }
+    )*};
+}
+
+impl Value {
+    integer_getters! {
+        as_i32_checked -> i32, i32::MIN, i32::MAX;
+        as_u32_checked -> u32, u32::MIN, u32::MAX;
+        as_i16_checked -> i16, i16::MIN, i16::MAX;
+        as_u16_checked -> u16, u16::MIN, u16::MAX;
+        as_i8_checked -> i8, i8::MIN, i8::MAX;
+        as_u8_checked -> u8, u8::MIN, u8::MAX;
+    }
+
+    /// The number as an `i64`, `None` when it has a fractional part or isn't a number. Numbers
+    /// are held as 64 bit floats, so beyond ±(2^53 - 1) the value parsed may not be the one
+    /// written; those give `None` too, which keeps IDs from being silently mangled.
+    pub fn as_i64_exact(&self) -> Option<i64> {
+        self.integer_within(-MAX_SAFE_INTEGER, MAX_SAFE_INTEGER)
+            .map(|number| number as i64)
+    }
+
+    /// The number as a `u64`, `None` when it is negative, has a fractional part or isn't a
+    /// number, and past 2^53 - 1 for the same reason as `as_i64_exact`
+    pub fn as_u64_exact(&self) -> Option<u64> {
+        self.integer_within(0.0, MAX_SAFE_INTEGER)
+            .map(|number| number as u64)
+    }
+
+    /// The number when it is a whole one from `min` to `max` inclusive, `-0` counting as 0
+    fn integer_within(&self, min: f64, max: f64) -> Option<f64> {
+        match self {
+            Value::Number(number) if number.fract() == 0.0 && (min..=max).contains(number) => {
+                Some(*number)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl Value {
+    /// The value at a dot path such as `server.ports[2]`, `None` when there is nothing there
+    /// or the path can't be read. Keys that aren't plain names are quoted, e.g.
+    /// `headers["content-type"]`, the way `Path` displays them.
+    pub fn get_path(&self, path: &str) -> Option<&Value> {
+        parse_path(path)
+            .ok()?
+            .iter()
+            .try_fold(self, |value, segment| match (segment, value) {
+                (PathSegment::Key(key), Value::Object(members)) => members.get(key),
+                (PathSegment::Index(index), Value::Array(elements)) => elements.get(*index),
+                _ => None,
+            })
+    }
+
+    /// Puts `value` at a dot path, returning the value it replaced. Objects and arrays on the way
+    /// are created where they're missing or null, and arrays too short for an index are padded
+    /// with up to 1024 nulls, but any other value in the way or an index further off is an
+    /// error rather than overwritten. Nothing is changed when it fails.
+    pub fn set_path(&mut self, path: &str, value: Value) -> Result<Option<Value>, PathError> {
+        let segments = parse_path(path)?;
+        check_padding(self, &segments)?;
+        let Some((last, parents)) = segments.split_last() else {
+            return Ok(Some(std::mem::replace(self, value)));
+        };
+        let mut current = self;
+        for (depth, segment) in parents.iter().enumerate() {
+            current = child_or_insert(current, segment)
+                .ok_or_else(|| PathError::Blocked(Path(segments[..depth].to_vec())))?;
+        }
+        match (last, make_container(current, last)) {
+            (PathSegment::Key(key), Value::Object(members)) => {
+                Ok(members.insert(key.clone(), value))
+            }
+            (PathSegment::Index(index), Value::Array(elements)) if *index < elements.len() => {
+                Ok(Some(std::mem::replace(&mut elements[*index], value)))
+            }
+            (PathSegment::Index(index), Value::Array(elements)) => {
+                elements.resize(*index, Value::Null);
+                elements.push(value);
+                Ok(None)
+            }
+            _ => Err(PathError::Blocked(Path(parents.to_vec()))),
+        }
+    }
+
+    /// Takes the value at a dot path out of its object or array, `None` when there is nothing
+    /// there or the path can't be read. Later elements of an array move up to fill the gap.
+    pub fn remove_path(&mut self, path: &str) -> Option<Value> {
+        let segments = parse_path(path).ok()?;
+        let (last, parents) = segments.split_last()?;
+        let mut current = self;
+        for segment in parents {
+            current = match (segment, current) {
+                (PathSegment::Key(key), Value::Object(members)) => members.get_mut(key)?,
+                (PathSegment::Index(index), Value::Array(elements)) => elements.get_mut(*index)?,
+                _ => return None,
+            };
+        }
+        match (last, current) {
+            (PathSegment::Key(key), Value::Object(members)) => members.remove(key),
+            (PathSegment::Index(index), Value::Array(elements)) if *index < elements.len() => {
+                Some(elements.remove(*index))
+            }
+            _ => None,
+        }
+    }
+
+    /// An object mapping the dot path of every scalar, and of every empty object or array, to
+    /// that value, e.g. `{"a": {"b": [{"c": 1}]}}` becomes `{"a.b[0].c": 1}`. A scalar at the
+    /// root has the empty path.
+    pub fn flatten(&self) -> Value {
+        let mut flat = Map::new();
+        flatten_into(self, &mut Path::default(), &mut flat);
+        Value::Object(flat)
+    }
+
+    /// Rebuilds the nested value a `flatten` object came from, with arrays padded with nulls
+    /// where an index is missing. A value that isn't an object is already flat and comes back
+    /// as it is. Fails when a key can't be read as a dot path, when two keys disagree on what a
+    /// value is, like `a` being a number and `a.b` needing it to be an object, or when an index
+    /// is so far past the others that `set_path` wouldn't pad up to it, which keeps untrusted
+    /// keys like `[4000000000]` from allocating without limit.
+    pub fn unflatten(&self) -> Result<Value, PathError> {
+        let Value::Object(flat) = self else {
+            return Ok(self.clone());
+        };
+        let mut paths: Vec<_> = flat.iter().collect();
+        // in a fixed order rather than map order, so a conflict is reported the same every time
+        paths.sort_unstable_by(|a, b| a.0.len().cmp(&b.0.len()).then(a.0.cmp(b.0)));
+        // with nothing in it, the flat object stands for an empty one
+        let mut value = match paths.is_empty() {
+            true => Value::Object(Map::new()),
+            false => Value::Null,
+        };
+        for (path, member) in paths {
+            value.set_path(path, member.clone())?;
+        }
+        Ok(value)
+    }
+}
+
+fn flatten_into(value: &Value, path: &mut Path, flat: &mut Map<Value>) {
+    match value {
+        Value::Object(members) if !members.is_empty() => {
+            for (key, member) in members {
+                path.0.push(PathSegment::Key(key.clone()));
+                flatten_into(member, path, flat);
+                path.0.pop();
+            }
+        }
+        Value::Array(elements) if !elements.is_empty() => {
+            for (index, element) in elements.iter().enumerate() {
+                path.0.push(PathSegment::Index(index));
+                flatten_into(element, path, flat);
+                path.0.pop();
+            }
+        }
+        leaf => {
+            flat.insert(dot_path(path), leaf.clone());
+        }
+    }
+}
+
+/// Writes a path the way `parse_path` reads it, which is how `Path` displays it without the `$`
+fn dot_path(path: &Path) -> String {
+    let path = path.to_string();
+    let path = &path[1..];
+    path.strip_prefix('.').unwrap_or(path).to_string()
+}
+
+/// The most nulls `set_path` pads an array with to reach an index, so an index from untrusted
+/// input such as `[4000000000]` can't make it allocate without limit
+const MAX_PADDING: usize = 1024;
+
+/// Fails with the path to the first index in `segments` that `set_path` would have to pad an
+/// array of `value` with more than `MAX_PADDING` nulls to reach
+fn check_padding(value: &Value, segments: &[PathSegment]) -> Result<(), PathError> {
+    let mut current = Some(value);
+    for (depth, segment) in segments.iter().enumerate() {
+        if let PathSegment::Index(index) = segment {
+            let len = match current {
+                Some(Value::Array(elements)) => elements.len(),
+                Some(Value::Null) | None => 0,
+                // anything else is in the way, which `set_path` reports itself
+                Some(_) => return Ok(()),
+            };
+            if index.saturating_sub(len) > MAX_PADDING {
+                return Err(PathError::TooFar(Path(segments[..=depth].to_vec())));
+            }
+        }
+        current = current.and_then(|value| match (segment, value) {
+            (PathSegment::Key(key), Value::Object(members)) => members.get(key),
+            (PathSegment::Index(index), Value::Array(elements)) => elements.get(*index),
+            _ => None,
+        });
+    }
+    Ok(())
+}
+
+/// The child of `value` at `segment`, null when it's missing and padding arrays that are too
+/// short with nulls. `None` when `value` can't hold `segment`.
+fn child_or_insert<'a>(value: &'a mut Value, segment: &PathSegment) -> Option<&'a mut Value> {
+    match (segment, make_container(value, segment)) {
+        (PathSegment::Key(key), Value::Object(members)) => {
+            Some(members.entry(key.clone()).or_insert(Value::Null))
+        }
+        (PathSegment::Index(index), Value::Array(elements)) => {
+            if *index >= elements.len() {
+                elements.resize(index + 1, Value::Null);
+            }
+            Some(&mut elements[*index])
+        }
+        _ => None,
+    }
+}
+
+/// Turns a null into the empty container `segment` needs, a null holds nothing to lose
+fn make_container<'a>(value: &'a mut Value, segment: &PathSegment) -> &'a mut Value {
+    if *value == Value::Null {
+        *value = match segment {
+            PathSegment::Key(_) => Value::Object(Map::new()),
+            PathSegment::Index(_) => Value::Array(Vec::new()),
+        };
+    }
+    value
+}
+
+/// Reads a dot path such as `server.ports[2]` or `headers["content-type"]` into its segments,
+/// the empty path being the whole document
+fn parse_path(path: &str) -> Result<Vec<PathSegment>, PathError> {
+    let mut chars = path.char_indices().peekable();
+    let mut segments = Vec::new();
+    while let Some(&(offset, c)) = chars.peek() {
+        match c {
+            '[' => {
+                chars.next();
+                let segment = match chars.next_if(|&(_, c)| c == '"') {
+                    Some((quote, _)) => PathSegment::Key(parse_quoted(&mut chars, path, quote)?),
+                    None => {
+                        let mut digits = String::new();
+                        while let Some((_, c)) = chars.next_if(|(_, c)| c.is_ascii_digit()) {
+                            digits.push(c);
+                        }
+                        let index = digits
+                            .parse()
+                            .map_err(|_| PathError::Syntax { offset: offset + 1 })?;
+                        PathSegment::Index(index)
+                    }
+                };
+                match chars.next() {
+                    Some((_, ']')) => segments.push(segment),
+                    Some((offset, _)) => return Err(PathError::Syntax { offset }),
+                    None => return Err(PathError::Syntax { offset: path.len() }),
+                }
+            }
+            // a dot only ever separates a key from what came before it
+            '.' if segments.is_empty() => return Err(PathError::Syntax { offset }),
+            '.' => {
+                chars.next();
+                segments.push(PathSegment::Key(parse_name(&mut chars, offset + 1)?));
+            }
+            _ if segments.is_empty() => {
+                segments.push(PathSegment::Key(parse_name(&mut chars, offset)?));
+            }
+            _ => return Err(PathError::Syntax { offset }),
+        }
+    }
+    Ok(segments)
+}
+
+type Chars<'a> = std::iter::Peekable<std::str::CharIndices<'a>>;
+
+/// Reads an unquoted key up to the next `.` or `[`, which starts at `offset`
+fn parse_name(chars: &mut Chars, offset: usize) -> Result<String, PathError> {
+    let mut name = String::new();
+    while let Some((_, c)) = chars.next_if(|&(_, c)| !matches!(c, '.' | '[' | ']' | '"')) {
+        name.push(c);
+    }
+    match name.is_empty() {
+        true => Err(PathError::Syntax { offset }),
+        false => Ok(name),
+    }
+}
+
+/// Reads a quoted key after its opening quote at `quote`, undoing json string escapes the way
+/// `Path` writes them
+fn parse_quoted(chars: &mut Chars, path: &str, quote: usize) -> Result<String, PathError> {
+    let end = loop {
+        match chars.next() {
+            Some((end, '"')) => break end,
+            Some((_, '\\')) => {
+                chars.next();
+            }
+            Some(_) => {}
+            None => return Err(PathError::Syntax { offset: path.len() }),
+        }
+    };
+    match crate::parse(String::from(&path[quote..=end])) {
+        Ok(Value::String(name)) => Ok(name),
+        _ => Err(PathError::Syntax { offset: quote }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::PathError;
+    use crate::{parse, Path, PathSegment, Value};
+
+    fn config() -> Value {
+        let input = r#"{"server": {"host": "localhost", "ports": [80, 443]}, "a.b": {"c": true}}"#;
+        parse(String::from(input)).unwrap()
+    }
+
+    #[test]
+    fn gets_exact_integers() {
+        let cases = [
+            ("42", Some(42), Some(42), Some(42)),
+            ("-7", Some(-7), None, Some(-7)),
+            ("-0", Some(0), Some(0), Some(0)),
+            ("1.5", None, None, None),
+            ("4294967295", Some(4_294_967_295), Some(u32::MAX), None),
+            ("4294967296", Some(4_294_967_296), None, None),
+            ("9007199254740991", Some(9_007_199_254_740_991), None, None),
+            ("9007199254740993", None, None, None),
+            ("1e300", None, None, None),
+            ("\"42\"", None, None, None),
+        ];
+
+        for (input, expected_i64, expected_u32, expected_i32) in cases {
+            let value = parse(String::from(input)).unwrap();
+
+            let actual_i64 = value.as_i64_exact();
+            let actual_u32 = value.as_u32_checked();
+            let actual_i32 = value.as_i32_checked();
+
+            assert_eq!(actual_i64, expected_i64, "{input}");
+            assert_eq!(actual_u32, expected_u32, "{input}");
+            assert_eq!(actual_i32, expected_i32, "{input}");
+        }
+        assert_eq!(Value::from(-1).as_u64_exact(), None);
+        assert_eq!(Value::from(255).as_u8_checked(), Some(255));
+        assert_eq!(Value::from(256).as_u8_checked(), None);
+        assert_eq!(Value::from(-32768).as_i16_checked(), Some(i16::MIN));
+    }
+
+    #[test]
+    fn gets_paths() {
+        let value = config();
+        let cases = [
+            ("server.host", Some(Value::from("localhost"))),
+            ("server.ports[1]", Some(Value::from(443))),
+            ("[\"a.b\"].c", Some(Value::from(true))),
+            ("server.ports[2]", None),
+            ("server.host.name", None),
+            ("server..host", None),
+        ];
+
+        for (path, expected) in cases {
+            let actual = value.get_path(path);
+
+            assert_eq!(actual, expected.as_ref(), "{path}");
+        }
+        assert_eq!(value.get_path(""), Some(&value));
+    }
+
+    #[test]
+    fn sets_paths() {
+        let mut value = config();
+        let expected = parse(String::from(
+            r#"{
+                "server": {"host": "example.com", "ports": [80, 443, 8080], "tls": {"certs": [null, "b.pem"]}},
+                "a.b": {"c": true},
+                "new": [{"x": 1}]
+            }"#,
+        ))
+        .unwrap();
+
+        let replaced = value.set_path("server.host", "example.com".into());
+        let appended = value.set_path("server.ports[2]", 8080.into());
+        let nested = value.set_path("server.tls.certs[1]", "b.pem".into());
+        let created = value.set_path("new[0].x", 1.into());
+
+        assert_eq!(replaced, Ok(Some(Value::from("localhost"))));
+        assert_eq!(appended, Ok(None));
+        assert_eq!(nested, Ok(None));
+        assert_eq!(created, Ok(None));
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn refuses_to_overwrite() {
+        let mut value = config();
+        let expected_blocked = PathError::Blocked(Path(vec![
+            PathSegment::Key(String::from("server")),
+            PathSegment::Key(String::from("host")),
+        ]));
+
+        let blocked = value.set_path("server.host.name", Value::Null).unwrap_err();
+        let syntax = value.set_path("server.ports[x]", Value::Null).unwrap_err();
+
+        assert_eq!(blocked, expected_blocked);
+        assert_eq!(
+            blocked.to_string(),
+            "the value at $.server.host is in the way"
+        );
+        assert_eq!(syntax, PathError::Syntax { offset: 13 });
+        assert_eq!(value, config());
+    }
+
+    #[test]
+    fn refuses_huge_indexes() {
+        let mut value = config();
+        let expected = PathError::TooFar(Path(vec![
+            PathSegment::Key(String::from("server")),
+            PathSegment::Key(String::from("ports")),
+            PathSegment::Index(2000),
+        ]));
+
+        let actual = value.set_path("server.ports[2000]", Value::Null);
+        let overflow = Value::Null.set_path("[18446744073709551615].a", Value::Null);
+        let padded = value.set_path("server.ports[1026]", Value::Null);
+
+        assert_eq!(actual, Err(expected));
+        assert!(matches!(overflow, Err(PathError::TooFar(_))));
+        assert_eq!(padded, Ok(None));
+        assert_eq!(value.get_path("server.ports[1025]"), Some(&Value::Null));
+    }
+
+    #[test]
+    fn flattens() {
+        let input = r#"{"a": {"b": [{"c": 1}, 2]}, "first name": "x", "e": [], "f": {}}"#;
+        let input = parse(String::from(input)).unwrap();
+        let expected = parse(String::from(
+            r#"{"a.b[0].c": 1, "a.b[1]": 2, "[\"first name\"]": "x", "e": [], "f": {}}"#,
+        ))
+        .unwrap();
+
+        let actual = input.flatten();
+        let unflattened = actual.unflatten().unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(unflattened, input);
+        assert_eq!(Value::from(3).flatten().unflatten(), Ok(Value::from(3)));
+    }
+
+    #[test]
+    fn unflattens_conflicts() {
+        let input = parse(String::from(r#"{"a": 1, "a.b": 2}"#)).unwrap();
+        let expected = PathError::Blocked(Path(vec![PathSegment::Key(String::from("a"))]));
+
+        let actual = input.unflatten();
+
+        assert_eq!(actual, Err(expected));
+    }
+
+    #[test]
+    fn unflattens_huge_indexes() {
+        let cases = ["[18446744073709551614]", "[4000000000]", "a[0].b[5000]"];
+
+        for key in cases {
+            let input = Value::Object([(String::from(key), Value::from(1))].into());
+
+            let actual = input.unflatten();
+
+            assert!(matches!(actual, Err(PathError::TooFar(_))), "{key}");
+        }
+    }
+
+    #[test]
+    fn round_trips_escaped_keys() {
+        let input = parse(String::from(
+            r#"{"a\nb": 1, "anb": 2, "t\tab": {"q\"uote": [true]}, "back\\slash": null}"#,
+        ))
+        .unwrap();
+
+        let flat = input.flatten();
+        let actual = flat.unflatten().unwrap();
+
+        assert_eq!(actual, input);
+        assert_eq!(flat.pointer(r#"/["a\nb"]"#), Some(&Value::from(1)));
+        assert_eq!(
+            input.get_path(r#"["t\tab"]["q\"uote"][0]"#),
+            Some(&Value::from(true))
+        );
+    }
+
+    #[test]
+    fn removes_paths() {
+        let mut value = config();
+        let expected = parse(String::from(
+            r#"{"server": {"ports": [443]}, "a.b": {"c": true}}"#,
+        ))
+        .unwrap();
+
+        let port = value.remove_path("server.ports[0]");
+        let host = value.remove_path("server.host");
+        let missing = value.remove_path("server.ports[5]");
+
+        assert_eq!(port, Some(Value::from(80)));
+        assert_eq!(host, Some(Value::from("localhost")));
+        assert_eq!(missing, None);
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn coerces_loosely() {
+        let cases = [
+            (r#""42""#, Some(42.0), None, Some("42")),
+            (r#"" 1.5 ""#, Some(1.5), None, Some(" 1.5 ")),
+            (r#""NaN""#, None, None, Some("NaN")),
+            (r#""TRUE""#, None, Some(true), Some("TRUE")),
+            (r#""0""#, Some(0.0), Some(false), Some("0")),
+            ("1", Some(1.0), Some(true), Some("1")),
+            ("2.5", Some(2.5), None, Some("2.5")),
+            ("false", Some(0.0), Some(false), Some("false")),
+            ("null", None, None, None),
+            ("[1]", None, None, None),
+        ];
+
+        for (input, expected_f64, expected_bool, expected_string) in cases {
+            let input = parse(String::from(input)).unwrap();
+
+            let actual_f64 = input.as_f64_lossy();
+            let actual_bool = input.as_bool_lossy();
+            let actual_string = input.as_string_lossy();
+
+            assert_eq!(actual_f64, expected_f64, "{input:?}");
+            assert_eq!(actual_bool, expected_bool, "{input:?}");
+            assert_eq!(actual_string.as_deref(), expected_string, "{input:?}");
+        }
+    }
+
+    #[test]
+    fn gets_ignoring_case() {
+        let input = r#"{"Content-Type": "a", "ACCEPT": "b", "accept": "c", "Accept": "d"}"#;
+        let value = parse(String::from(input)).unwrap();
+        let cases = [
+            ("content-type", Some("a")),
+            ("Accept", Some("d")),
+            ("aCCept", Some("b")),
+            ("host", None),
+        ];
+
+        for (key, expected) in cases {
+            let expected = expected.map(Value::from);
+
+            let actual = value.get_ignore_case(key);
+
+            assert_eq!(actual, expected.as_ref(), "{key}");
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use crate::pointer::JsonPointer;
+use crate::{to_string, Map, Value};
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+
+/// How loosely `semantically_equals` compares, the default being exact equality
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct Tolerance {
+    /// how far apart two numbers may be and still be equal
+    pub float_epsilon: f64,
+    /// compare arrays as bags of elements, so `[1, 2]` equals `[2, 1]`
+    pub ignore_array_order: bool,
+    /// let a member that is null equal one that is missing, so `{"a": null}` equals `{}`
+    pub null_equals_missing: bool,
+}
+
+impl Value {
+    /// Whether two values are the same within `tolerance`, for tests where exact float bits
+    /// and array order shouldn't matter. Unordered arrays are matched up element by element,
+    /// each pairing with the first equal one left, so an epsilon wide enough to make one
+    /// element equal several can leave a match missed.
+    pub fn semantically_equals(&self, other: &Value, tolerance: &Tolerance) -> bool {
+        match (self, other) {
+            (Value::Number(a), Value::Number(b)) => {
+                a == b || (a - b).abs() <= tolerance.float_epsilon
+            }
+            (Value::Array(a), Value::Array(b)) if tolerance.ignore_array_order => {
+                unordered_equals(a, b, tolerance)
+            }
+            (Value::Array(a), Value::Array(b)) => {
+                a.len() == b.len()
+                    && a.iter()
+                        .zip(b)
+                        .all(|(a, b)| a.semantically_equals(b, tolerance))
+            }
+            (Value::Object(a), Value::Object(b)) => {
+                members_within(a, b, tolerance) && members_within(b, a, tolerance)
+            }
+            (a, b) => a == b,
+        }
+    }
+}
+
+/// One place where a value isn't what was expected
+#[derive(Debug, Clone, PartialEq)]
+pub struct Difference {
+    /// where in the values they differ
+    pub path: JsonPointer,
+    /// what was expected there, `None` when nothing was
+    pub expected: Option<Value>,
+    /// what is actually there, `None` when it's missing
+    pub actual: Option<Value>,
+}
+
+impl fmt::Display for Difference {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |value: &Option<Value>| match value {
+            Some(value) => to_string(value).unwrap_or_else(|_| format!("{value:?}")),
+            None => String::from("nothing"),
+        };
+        write!(
+            f,
+            "{:?}: expected {}, found {}",
+            self.path.to_string(),
+            describe(&self.expected),
+            describe(&self.actual)
+        )
+    }
+}
+
+/// Every place where `actual` isn't exactly `expected`, in document order with object members
+/// in key order. Used by `assert_json_eq!`.
+pub fn diff(actual: &Value, expected: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    collect_differences(
+        actual,
+        expected,
+        false,
+        &mut JsonPointer::root(),
+        &mut differences,
+    );
+    differences
+}
+
+/// Same as `diff` but `actual` may hold more than `expected`: extra object members, and extra
+/// elements after those expected of an array. Used by `assert_json_include!`.
+pub fn diff_included(actual: &Value, expected: &Value) -> Vec<Difference> {
+    let mut differences = Vec::new();
+    collect_differences(
+        actual,
+        expected,
+        true,
+        &mut JsonPointer::root(),
+        &mut differences,
+    );
+    differences
+}
+
+fn collect_differences(
+    actual: &Value,
+    expected: &Value,
+    include: bool,
+    path: &mut JsonPointer,
+    differences: &mut Vec<Difference>,
+) {
+    match (actual, expected) {
+        (Value::Array(actual), Value::Array(expected)) => {
+            let len = match include {
+                true => expected.len(),
+                false => actual.len().max(expected.len()),
+            };
+            for index in 0..len {
+                path.push(index.to_string());
+                match (actual.get(index), expected.get(index)) {
+                    (Some(actual), Some(expected)) => {
+                        collect_differences(actual, expected, include, path, differences)
+                    }
+                    (actual, expected) => differences.push(difference(path, expected, actual)),
+                }
+                path.pop();
+            }
+        }
+        (Value::Object(actual), Value::Object(expected)) => {
+            let mut keys: Vec<&String> = expected.keys().collect();
+            if !include {
+                keys.extend(actual.keys().filter(|key| !expected.contains_key(*key)));
+            }
+            keys.sort_unstable();
+            for key in keys {
+                path.push(key.clone());
+                match (actual.get(key), expected.get(key)) {
+                    (Some(actual), Some(expected)) => {
+                        collect_differences(actual, expected, include, path, differences)
+                    }
+                    (actual, expected) => differences.push(difference(path, expected, actual)),
+                }
+                path.pop();
+            }
+        }
+        (actual, expected) if actual != expected => {
+            differences.push(difference(path, Some(expected), Some(actual)))
+        }
+        _ => {}
+    }
+}
+
+fn difference(path: &JsonPointer, expected: Option<&Value>, actual: Option<&Value>) -> Difference {
+    Difference {
+        path: path.clone(),
+        expected: expected.cloned(),
+        actual: actual.cloned(),
+    }
+}
+
+/// Panics with every difference between two Values unless they are exactly equal, each
+/// difference on its own line with the JSON Pointer to where it is
+#[macro_export]
+macro_rules! assert_json_eq {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_json_eq!(@differences $crate::diff(&$actual, &$expected))
+    };
+    (@differences $differences:expr) => {{
+        let differences: ::std::vec::Vec<$crate::Difference> = $differences;
+        if !differences.is_empty() {
+            let lines: ::std::vec::Vec<::std::string::String> =
+                differences.iter().map(|difference| difference.to_string()).collect();
+            panic!("JSON values differ:\n  {}", lines.join("\n  "));
+        }
+    }};
+}
+
+/// Same as `assert_json_eq!` but the actual value may hold more than the expected one, extra
+/// object members and extra elements at the end of arrays
+#[macro_export]
+macro_rules! assert_json_include {
+    ($actual:expr, $expected:expr $(,)?) => {
+        $crate::assert_json_eq!(@differences $crate::diff_included(&$actual, &$expected))
+    };
+}
+
+/// Values hash consistently with `==`: object members in any order hash the same, and so do
+/// `0` and `-0`, with every NaN hashing alike. Each member of an object is hashed on its own
+/// and the results summed, so the order they're met in doesn't matter.
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        match self {
+            Value::Null => state.write_u8(0),
+            Value::Boolean(boolean) => {
+                state.write_u8(1);
+                boolean.hash(state);
+            }
+            Value::Number(number) => {
+                state.write_u8(2);
+                state.write_u64(normalized_bits(*number));
+            }
+            Value::String(string) => {
+                state.write_u8(3);
+                string.hash(state);
+            }
+            Value::Array(elements) => {
+                state.write_u8(4);
+                elements.hash(state);
+            }
+            Value::Object(members) => {
+                state.write_u8(5);
+                state.write_usize(members.len());
+                let sum = members.iter().fold(0_u64, |sum, member| {
+                    let mut hasher = DefaultHasher::new();
+                    member.hash(&mut hasher);
+                    sum.wrapping_add(hasher.finish())
+                });
+                state.write_u64(sum);
+            }
+        }
+    }
+}
+
+/// Values are equal when they hold the same data, numbers compared the way they hash: `0`
+/// equals `-0`, and unlike with `f64` every NaN equals every other, so `==` is reflexive.
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Null, Value::Null) => true,
+            (Value::Boolean(a), Value::Boolean(b)) => a == b,
+            (Value::Number(a), Value::Number(b)) => normalized_bits(*a) == normalized_bits(*b),
+            (Value::String(a), Value::String(b)) => a == b,
+            (Value::Array(a), Value::Array(b)) => a == b,
+            (Value::Object(a), Value::Object(b)) => a == b,
+            _ => false,
+        }
+    }
+}
+
+/// Values are `Eq` so they can be set members and map keys
+impl Eq for Value {}
+
+/// Values of different kinds order null < boolean < number < string < array < object, so
+/// mixed arrays sort deterministically. Numbers order by value with `-0` equal to `0` and NaN
+/// above everything else, strings by their bytes, arrays element by element, and objects by
+/// their members sorted by key, compared key then value.
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+            (Value::Number(a), Value::Number(b)) => compare_numbers(*a, *b),
+            (Value::String(a), Value::String(b)) => a.cmp(b),
+            (Value::Array(a), Value::Array(b)) => a.cmp(b),
+            (Value::Object(a), Value::Object(b)) => sorted_members(a).cmp(&sorted_members(b)),
+            (a, b) => rank(a).cmp(&rank(b)),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Orders numbers so that they are `Equal` exactly when `==` says so, NaN going last
+fn compare_numbers(a: f64, b: f64) -> Ordering {
+    if normalized_bits(a) == normalized_bits(b) {
+        return Ordering::Equal;
+    }
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+/// Where a kind of value comes in the cross-kind order
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Boolean(_) => 1,
+        Value::Number(_) => 2,
+        Value::String(_) => 3,
+        Value::Array(_) => 4,
+        Value::Object(_) => 5,
+    }
+}
+
+fn sorted_members(members: &Map<Value>) -> Vec<(&String, &Value)> {
+    let mut sorted: Vec<_> = members.iter().collect();
+    sorted.sort_unstable_by_key(|&(key, _)| key);
+    sorted
+}
+
+/// The bits of a number with `-0` made `0` and every NaN made the same one
+fn normalized_bits(number: f64) -> u64 {
+    match number {
+        0.0 => 0.0_f64.to_bits(),
+        number if number.is_nan() => f64::NAN.to_bits(),
+        number => number.to_bits(),
+    }
+}
+
+fn unordered_equals(a: &[Value], b: &[Value], tolerance: &Tolerance) -> bool {
+    if a.len() != b.len() {
+        return false;
+    }
+    let mut unmatched: Vec<&Value> = b.iter().collect();
+    a.iter().all(|a| {
+        match unmatched
+            .iter()
+            .position(|b| a.semantically_equals(b, tolerance))
+        {
+            Some(position) => {
+                unmatched.swap_remove(position);
+                true
+            }
+            None => false,
+        }
+    })
+}
+
+/// Whether every member of `a` has an equal one in `b`
+fn members_within(a: &Map<Value>, b: &Map<Value>, tolerance: &Tolerance) -> bool {
+    a.iter().all(|(key, a)| match b.get(key) {
+        Some(b) => a.semantically_equals(b, tolerance),
+        None => tolerance.null_equals_missing && *a == Value::Null,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{diff, diff_included, Tolerance};
+    use crate::{parse, Value};
+    use std::cmp::Ordering;
+    use std::collections::{BTreeSet, HashSet};
+
+    fn value(input: &str) -> Value {
+        parse(String::from(input)).unwrap()
+    }
+
+    #[test]
+    fn equals_within_tolerance() {
+        let loose = Tolerance {
+            float_epsilon: 1e-9,
+            ignore_array_order: true,
+            null_equals_missing: true,
+        };
+        let cases = [
+            ("0.3", "0.30000000000000004", Tolerance::default(), false),
+            ("0.3", "0.30000000000000004", loose, true),
+            ("[1, [2, 3]]", "[[3, 2], 1]", Tolerance::default(), false),
+            ("[1, [2, 3]]", "[[3, 2], 1]", loose, true),
+            ("[1, 1, 2]", "[1, 2, 2]", loose, false),
+            (
+                r#"{"a": 1, "b": null}"#,
+                r#"{"a": 1}"#,
+                Tolerance::default(),
+                false,
+            ),
+            (r#"{"a": 1, "b": null}"#, r#"{"a": 1}"#, loose, true),
+            (r#"{"a": 1}"#, r#"{"a": 1, "b": 2}"#, loose, false),
+            (r#"{"a": "1"}"#, r#"{"a": 1}"#, loose, false),
+        ];
+
+        for (a, b, tolerance, expected) in cases {
+            let actual = value(a).semantically_equals(&value(b), &tolerance);
+
+            assert_eq!(actual, expected, "{a} {b} {tolerance:?}");
+        }
+    }
+
+    #[test]
+    fn hashes_structurally() {
+        let input = [
+            r#"{"a": 1, "b": [true, null], "c": {"d": "e", "f": 0}}"#,
+            r#"{"c": {"f": -0, "d": "e"}, "b": [true, null], "a": 1.0}"#,
+            r#"{"a": 1, "b": [null, true], "c": {"d": "e", "f": 0}}"#,
+            r#"{"a": "1"}"#,
+            r#"["1"]"#,
+        ];
+        let nans = [Value::Number(f64::NAN), Value::Number(-f64::NAN)];
+
+        let actual: HashSet<Value> = input.iter().map(|input| value(input)).collect();
+        let actual_nans: HashSet<Value> = nans.iter().cloned().collect();
+
+        assert_eq!(actual.len(), 4);
+        assert_eq!(actual_nans.len(), 1);
+        assert_eq!(nans[0], nans[0]);
+        assert!(actual.contains(&value(
+            r#"{"b": [true, null], "a": 1, "c": {"d": "e", "f": -0.0}}"#
+        )));
+    }
+
+    #[test]
+    fn orders_across_kinds() {
+        let mut input = value(
+            r#"[{"b": 1}, [2], "b", 10, true, null, {"a": 2}, [1, 3], "a", -0, false, {"a": 1, "b": 0}]"#,
+        );
+        let expected = value(
+            r#"[null, false, true, -0, 10, "a", "b", [1, 3], [2], {"a": 1, "b": 0}, {"a": 2}, {"b": 1}]"#,
+        );
+
+        if let Value::Array(elements) = &mut input {
+            elements.sort();
+        }
+        let set: BTreeSet<Value> = [value("0"), value("-0"), value("1")].into_iter().collect();
+
+        assert_eq!(input, expected);
+        assert_eq!(set.len(), 2);
+    }
+
+    #[test]
+    fn orders_consistently_with_equality() {
+        let input = [
+            Value::Number(f64::NAN),
+            Value::Number(-f64::NAN),
+            Value::Number(0.0),
+            Value::Number(-0.0),
+            Value::Number(f64::INFINITY),
+            Value::from(1),
+            value(r#"{"a": [1]}"#),
+            Value::Array(vec![Value::Number(f64::NAN)]),
+        ];
+
+        for a in &input {
+            for b in &input {
+                let actual = a.cmp(b);
+
+                assert_eq!(actual == Ordering::Equal, a == b, "{a:?} {b:?}");
+                assert_eq!(actual, b.cmp(a).reverse(), "{a:?} {b:?}");
+            }
+        }
+        let set: BTreeSet<Value> = input.into_iter().collect();
+        assert_eq!(set.len(), 6);
+    }
+
+    #[test]
+    fn diffs_by_pointer() {
+        let actual = value(r#"{"a": [1, 2, 3], "b": {"c": true}, "extra": null}"#);
+        let expected = value(r#"{"a": [1, 5], "b": {"c": true, "d": "x"}}"#);
+        let expected_lines = vec![
+            r#""/a/1": expected 5, found 2"#,
+            r#""/a/2": expected nothing, found 3"#,
+            r#""/b/d": expected "x", found nothing"#,
+            r#""/extra": expected nothing, found null"#,
+        ];
+        let expected_included = vec![
+            r#""/a/1": expected 5, found 2"#,
+            r#""/b/d": expected "x", found nothing"#,
+        ];
+
+        let lines: Vec<_> = diff(&actual, &expected)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+        let included: Vec<_> = diff_included(&actual, &expected)
+            .iter()
+            .map(|d| d.to_string())
+            .collect();
+
+        assert_eq!(lines, expected_lines);
+        assert_eq!(included, expected_included);
+    }
+
+    #[test]
+    fn asserts_json() {
+        let actual = value(r#"{"id": 7, "tags": ["a", "b"], "created": "today"}"#);
+
+        crate::assert_json_eq!(actual, actual.clone());
+        crate::assert_json_include!(actual, value(r#"{"id": 7, "tags": ["a"]}"#));
+    }
+
+    #[test]
+    #[should_panic(expected = "JSON values differ:\n  \"/id\": expected 8, found 7")]
+    fn panics_with_the_diff() {
+        let actual = value(r#"{"id": 7}"#);
+
+        crate::assert_json_include!(actual, value(r#"{"id": 8}"#));
+    }
+}
@@ -0,0 +1,533 @@
+use crate::parse::{Expected, TokenParseError};
+use crate::path::Path;
+use crate::span::{line_column, Spanned};
+use crate::tokenize::{Token, TokenizeError};
+use crate::{Map, Value};
+use std::fmt;
+use std::io;
+
+/// An error reading json, from tokenizing, parsing or the reader underneath, carrying what went
+/// wrong and where in the input it happened. Writing fails with `WriteError` instead, which has
+/// no place in an input to report, and the path and query helpers have small errors of their
+/// own; all of them are classified by `ErrorKind`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Error {
+    /// exactly what went wrong, see `kind` for the broad category
+    pub code: ErrorCode,
+    /// byte offset into the input
+    pub offset: usize,
+    /// 1-based line of the offset
+    pub line: usize,
+    /// 1-based column of the offset, counted in chars
+    pub column: usize,
+    /// the tokens that would have been valid here, empty when the input couldn't be tokenized
+    pub expected: Vec<Expected>,
+    /// the token found instead, `None` at the end of the input
+    pub found: Option<Token>,
+    /// the value being parsed when the error happened, the root outside of any container
+    pub path: Path,
+}
+
+/// Broad categories of errors, for callers that decide what to do next programmatically
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum ErrorKind {
+    /// the input stopped partway through, more bytes may make it valid, or output was finished
+    /// before the document was complete
+    Eof,
+    /// the input is not valid json and never will be, or a writer was asked to write something
+    /// that isn't
+    Syntax,
+    /// the json is well formed but its contents can't be represented or are not allowed
+    Data,
+    /// the caller cancelled the parse, nothing is known about the rest of the input
+    Cancelled,
+    /// reading the input or writing the output failed, nothing is known about the rest of it
+    Io,
+}
+
+/// Adds the `ErrorKind` predicates that `Error` has to the crate's other error types, each of
+/// which has a `kind` method
+macro_rules! kind_predicates {
+    ($($t:ty),*) => {$(
+        impl $t {
+            /// Whether the text stopped too early, so more of it could still succeed
+            pub fn is_eof(&self) -> bool {
+                self.kind() == $crate::ErrorKind::Eof
+            }
+
+            /// Whether the text is malformed regardless of what might follow
+            pub fn is_syntax(&self) -> bool {
+                self.kind() == $crate::ErrorKind::Syntax
+            }
+
+            /// Whether the text is well formed but what it holds isn't allowed
+            pub fn is_data(&self) -> bool {
+                self.kind() == $crate::ErrorKind::Data
+            }
+        }
+    )*};
+}
+
+pub(crate) use kind_predicates;
+
+/// The specific reason for an error
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum ErrorCode {
+    /// the input couldn't be split into tokens
+    TokenizeError(TokenizeError),
+    /// the tokens don't form a valid value
+    ParseError(TokenParseError),
+    /// the reader the input came from failed
+    Io {
+        kind: io::ErrorKind,
+        message: String,
+    },
+}
+
+impl Error {
+    /// An error at `offset` in `input` with nothing known about what was expected
+    pub fn new(input: &str, offset: usize, code: ErrorCode) -> Self {
+        let (line, column) = line_column(input, offset);
+        Self::located(offset, line, column, code)
+    }
+
+    /// An error whose line and column are already known, for input that isn't held in memory
+    pub(crate) fn located(offset: usize, line: usize, column: usize, code: ErrorCode) -> Self {
+        Self {
+            code,
+            offset,
+            line,
+            column,
+            expected: Vec::new(),
+            found: None,
+            path: Path::default(),
+        }
+    }
+
+    /// The category of the error
+    pub fn kind(&self) -> ErrorKind {
+        match &self.code {
+            ErrorCode::TokenizeError(
+                TokenizeError::UnexpectedEof
+                | TokenizeError::UnclosedQuotes
+                | TokenizeError::UnclosedComment,
+            )
+            | ErrorCode::ParseError(TokenParseError::UnexpectedEof) => ErrorKind::Eof,
+            ErrorCode::TokenizeError(
+                TokenizeError::InvalidCodePointValue | TokenizeError::InputTooLarge,
+            )
+            | ErrorCode::ParseError(
+                TokenParseError::ParseNumberError(_)
+                | TokenParseError::NumberOutOfRange
+                | TokenParseError::DepthLimitExceeded
+                | TokenParseError::ArrayElementLimitExceeded
+                | TokenParseError::ObjectMemberLimitExceeded
+                | TokenParseError::DuplicateKey { .. }
+                | TokenParseError::ExpectedContainer
+                | TokenParseError::ExpectedArray,
+            ) => ErrorKind::Data,
+            ErrorCode::TokenizeError(TokenizeError::Cancelled)
+            | ErrorCode::ParseError(TokenParseError::Cancelled) => ErrorKind::Cancelled,
+            ErrorCode::Io { .. } => ErrorKind::Io,
+            _ => ErrorKind::Syntax,
+        }
+    }
+
+    /// Whether the input ended too early, so appending more of it could still succeed
+    pub fn is_eof(&self) -> bool {
+        self.kind() == ErrorKind::Eof
+    }
+
+    /// Whether the input is malformed regardless of what might follow
+    pub fn is_syntax(&self) -> bool {
+        self.kind() == ErrorKind::Syntax
+    }
+
+    /// Whether the input is well formed but holds something that isn't allowed
+    pub fn is_data(&self) -> bool {
+        self.kind() == ErrorKind::Data
+    }
+
+    /// Whether the parse stopped because `ParseOptions::cancel` was set
+    pub fn is_cancelled(&self) -> bool {
+        self.kind() == ErrorKind::Cancelled
+    }
+
+    /// Draws the line of `input` holding the error with a caret under the error position,
+    /// `input` must be the text that produced the error
+    pub fn render(&self, input: &str) -> String {
+        let message = self.message();
+
+        let source_line = input
+            .lines()
+            .nth(self.line.saturating_sub(1))
+            .unwrap_or_default()
+            .trim_end_matches('\r');
+        // copy tabs from the source line so the caret lines up however tabs are displayed
+        let padding: String = source_line
+            .chars()
+            .take(self.column.saturating_sub(1))
+            .map(|c| if c == '\t' { '\t' } else { ' ' })
+            .collect();
+        let gutter = " ".repeat(self.line.to_string().len());
+        let mut location = format!("line {}, column {}", self.line, self.column);
+        if !self.path.is_root() {
+            location = format!("{location} in {}", self.path);
+        }
+
+        format!(
+            "error: {message}\n{gutter}--> {location}\n{gutter} |\n{} | {source_line}\n{gutter} | {padding}^\n",
+            self.line
+        )
+    }
+
+    /// What went wrong and what was found instead, without the location
+    fn message(&self) -> String {
+        match self.describe_found() {
+            Some(found) => format!("{}, found {found}", self.describe()),
+            None => self.describe(),
+        }
+    }
+
+    /// What went wrong, reads as "expected `,` or `}`" when the valid tokens are known
+    fn describe(&self) -> String {
+        if self.expected.is_empty() {
+            return self.code.to_string();
+        }
+        let mut description = String::from("expected ");
+        for (i, expected) in self.expected.iter().enumerate() {
+            match i {
+                0 => {}
+                i if i == self.expected.len() - 1 => description.push_str(" or "),
+                _ => description.push_str(", "),
+            }
+            description.push_str(&expected.to_string());
+        }
+        description
+    }
+
+    /// What was found instead, only worth saying when something else was expected
+    fn describe_found(&self) -> Option<String> {
+        match &self.found {
+            Some(token) => Some(token.to_string()),
+            None if !self.expected.is_empty() => Some(String::from("end of input")),
+            None => None,
+        }
+    }
+
+    /// The error as a json object, ready to go straight into an API response, e.g.
+    /// `{"code": "expected_comma", "kind": "syntax", "message": "...", "offset": 3,
+    /// "line": 1, "column": 4, "path": "$.a"}`
+    pub fn to_value(&self) -> Value {
+        let message = self.message();
+        let text = |text: &str| Value::String(String::from(text));
+        let number = |number: usize| Value::Number(number as f64);
+
+        Value::Object(Map::from([
+            (String::from("code"), text(self.code.name())),
+            (String::from("kind"), text(self.kind().name())),
+            (String::from("message"), Value::String(message)),
+            (String::from("offset"), number(self.offset)),
+            (String::from("line"), number(self.line)),
+            (String::from("column"), number(self.column)),
+            (String::from("path"), Value::String(self.path.to_string())),
+        ]))
+    }
+
+    /// Locates an error from the tokenizer within its input
+    pub fn from_tokenize(input: &str, err: Spanned<TokenizeError>) -> Self {
+        Self::new(input, err.span.start, ErrorCode::TokenizeError(err.value))
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.describe())?;
+        write!(f, " at line {}, column {}", self.line, self.column)?;
+        if !self.path.is_root() {
+            write!(f, " in {}", self.path)?;
+        }
+        match self.describe_found() {
+            Some(found) => write!(f, ", found {found}"),
+            None => Ok(()),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ErrorCode::TokenizeError(err) => write!(f, "{err}"),
+            ErrorCode::ParseError(err) => write!(f, "{err}"),
+            ErrorCode::Io { message, .. } => write!(f, "failed to read input: {message}"),
+        }
+    }
+}
+
+impl ErrorCode {
+    /// A stable snake_case name for the code, for matching on outside of Rust
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorCode::TokenizeError(err) => match err {
+                TokenizeError::UnfinishedLiteralValue { .. } => "invalid_literal",
+                TokenizeError::InvalidNumber => "invalid_number",
+                TokenizeError::UnclosedQuotes => "unclosed_quotes",
+                TokenizeError::UnexpectedEof => "unexpected_eof",
+                TokenizeError::CharNotRecognized(_) => "unrecognized_character",
+                TokenizeError::UnfinishedEscape => "invalid_escape",
+                TokenizeError::InvalidHexValue => "invalid_hex_value",
+                TokenizeError::InvalidCodePointValue => "invalid_code_point",
+                TokenizeError::InputTooLarge => "input_too_large",
+                TokenizeError::Cancelled => "cancelled",
+                TokenizeError::UnclosedComment => "unclosed_comment",
+                TokenizeError::InvalidUtf8 => "invalid_utf8",
+            },
+            ErrorCode::ParseError(err) => match err {
+                TokenParseError::ParseNumberError(_) => "invalid_number",
+                TokenParseError::NumberOutOfRange => "number_out_of_range",
+                TokenParseError::ExpectedValue => "expected_value",
+                TokenParseError::ExpectedComma => "expected_comma",
+                TokenParseError::ExpectedProperty => "expected_key",
+                TokenParseError::ExpectedColon => "expected_colon",
+                TokenParseError::UnexpectedEof => "unexpected_eof",
+                TokenParseError::TrailingTokens => "trailing_tokens",
+                TokenParseError::DepthLimitExceeded => "depth_limit_exceeded",
+                TokenParseError::ArrayElementLimitExceeded => "array_element_limit_exceeded",
+                TokenParseError::ObjectMemberLimitExceeded => "object_member_limit_exceeded",
+                TokenParseError::DuplicateKey { .. } => "duplicate_key",
+                TokenParseError::Cancelled => "cancelled",
+                TokenParseError::ExpectedContainer => "expected_container",
+                TokenParseError::ExpectedArray => "expected_array",
+            },
+            ErrorCode::Io { .. } => "io",
+        }
+    }
+}
+
+impl ErrorKind {
+    /// A stable lowercase name for the kind, for matching on outside of Rust
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::Eof => "eof",
+            ErrorKind::Syntax => "syntax",
+            ErrorKind::Data => "data",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Io => "io",
+        }
+    }
+}
+
+impl From<TokenizeError> for ErrorCode {
+    fn from(err: TokenizeError) -> Self {
+        Self::TokenizeError(err)
+    }
+}
+
+impl From<io::Error> for ErrorCode {
+    fn from(err: io::Error) -> Self {
+        Self::Io {
+            kind: err.kind(),
+            message: err.to_string(),
+        }
+    }
+}
+
+impl From<TokenParseError> for ErrorCode {
+    fn from(err: TokenParseError) -> Self {
+        Self::ParseError(err)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Error, ErrorCode, ErrorKind};
+    use crate::parse::{Expected, TokenParseError};
+    use crate::path::{Path, PathSegment};
+    use crate::tokenize::{Token, TokenizeError};
+    use crate::{
+        JsonWriter, Map, PathError, PointerError, QueryError, TranscodeError, Value, WriteError,
+    };
+    use std::io;
+
+    #[test]
+    fn displays_tokenize_error() {
+        let input = "[\n  tru\n]";
+        let code = ErrorCode::TokenizeError(TokenizeError::literal("tru", "true"));
+        let expected = "invalid literal `tru`, did you mean `true`? at line 2, column 3";
+
+        let actual = Error::new(input, 4, code).to_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn displays_expected_tokens() {
+        let input = "[1 2]";
+        let code = ErrorCode::ParseError(TokenParseError::ExpectedComma);
+        let expected = "expected a value, `,` or `]` at line 1, column 4, found number `2`";
+
+        let actual = Error {
+            expected: vec![Expected::Value, Expected::Comma, Expected::RightBracket],
+            found: Some(Token::number("2")),
+            ..Error::new(input, 3, code)
+        }
+        .to_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn displays_end_of_input() {
+        let input = "{";
+        let code = ErrorCode::ParseError(TokenParseError::UnexpectedEof);
+        let expected = "expected a string key or `}` at line 1, column 2, found end of input";
+
+        let actual = Error {
+            expected: vec![Expected::Key, Expected::RightBrace],
+            ..Error::new(input, 1, code)
+        }
+        .to_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn renders_snippet() {
+        let input = "{\n  \"a\": 1\n  ]\n}";
+        let code = ErrorCode::ParseError(TokenParseError::ExpectedComma);
+        let expected = "\
+error: expected `,` or `}`, found `]`
+ --> line 3, column 3
+  |
+3 |   ]
+  |   ^
+";
+
+        let actual = Error {
+            expected: vec![Expected::Comma, Expected::RightBrace],
+            found: Some(Token::RightBracket),
+            ..Error::new(input, 13, code)
+        }
+        .render(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn renders_tabs() {
+        let input = "[\t\tx]";
+        let code = ErrorCode::TokenizeError(TokenizeError::CharNotRecognized('x'));
+        let expected = "\
+error: unrecognized character 'x'
+ --> line 1, column 4
+  |
+1 | [\t\tx]
+  |  \t\t^
+";
+
+        let actual = Error::new(input, 3, code).render(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn classifies_errors() {
+        let cases = [
+            (
+                ErrorCode::ParseError(TokenParseError::UnexpectedEof),
+                ErrorKind::Eof,
+            ),
+            (
+                ErrorCode::TokenizeError(TokenizeError::UnclosedQuotes),
+                ErrorKind::Eof,
+            ),
+            (
+                ErrorCode::ParseError(TokenParseError::ExpectedColon),
+                ErrorKind::Syntax,
+            ),
+            (
+                ErrorCode::TokenizeError(TokenizeError::InvalidNumber),
+                ErrorKind::Syntax,
+            ),
+            (
+                ErrorCode::TokenizeError(TokenizeError::InvalidCodePointValue),
+                ErrorKind::Data,
+            ),
+            (
+                ErrorCode::ParseError(TokenParseError::Cancelled),
+                ErrorKind::Cancelled,
+            ),
+        ];
+
+        for (code, expected) in cases {
+            let actual = Error::new("", 0, code);
+
+            assert_eq!(actual.kind(), expected);
+            assert_eq!(actual.is_eof(), expected == ErrorKind::Eof);
+            assert_eq!(actual.is_syntax(), expected == ErrorKind::Syntax);
+            assert_eq!(actual.is_data(), expected == ErrorKind::Data);
+            assert_eq!(actual.is_cancelled(), expected == ErrorKind::Cancelled);
+        }
+    }
+
+    #[test]
+    fn classifies_other_errors() {
+        let writer = JsonWriter::new(Vec::new());
+        let cases = [
+            (writer.finish().unwrap_err().kind(), ErrorKind::Eof),
+            (WriteError::NothingToEnd.kind(), ErrorKind::Syntax),
+            (WriteError::NonFiniteNumber.kind(), ErrorKind::Data),
+            (
+                WriteError::Io(io::Error::other("disk full")).kind(),
+                ErrorKind::Io,
+            ),
+            (
+                TranscodeError::Write(WriteError::Unfinished).kind(),
+                ErrorKind::Eof,
+            ),
+            (PathError::Syntax { offset: 0 }.kind(), ErrorKind::Syntax),
+            (PathError::Blocked(Path::default()).kind(), ErrorKind::Data),
+            (QueryError::UnexpectedEnd.kind(), ErrorKind::Eof),
+            (QueryError::MissingRoot.kind(), ErrorKind::Syntax),
+            (PointerError::MissingSlash.kind(), ErrorKind::Syntax),
+        ];
+
+        for (actual, expected) in cases {
+            assert_eq!(actual, expected);
+        }
+        assert!(WriteError::Unfinished.is_eof());
+        assert!(QueryError::MissingRoot.is_syntax());
+        assert!(PathError::TooFar(Path::default()).is_data());
+    }
+
+    #[test]
+    fn to_value() {
+        let input = "{\"a\": [1 2]}";
+        let code = ErrorCode::ParseError(TokenParseError::ExpectedComma);
+        let text = |text: &str| Value::String(String::from(text));
+        let expected = Value::Object(Map::from([
+            (String::from("code"), text("expected_comma")),
+            (String::from("kind"), text("syntax")),
+            (
+                String::from("message"),
+                text("expected `,` or `]`, found number `2`"),
+            ),
+            (String::from("offset"), Value::Number(9.0)),
+            (String::from("line"), Value::Number(1.0)),
+            (String::from("column"), Value::Number(10.0)),
+            (String::from("path"), text("$.a")),
+        ]));
+
+        let actual = Error {
+            expected: vec![Expected::Comma, Expected::RightBracket],
+            found: Some(Token::number("2")),
+            path: Path(vec![PathSegment::Key(String::from("a"))]),
+            ..Error::new(input, 9, code)
+        }
+        .to_value();
+
+        assert_eq!(actual, expected);
+    }
+}
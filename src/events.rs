@@ -0,0 +1,372 @@
+use crate::reader::Event;
+use crate::writer::WriteError;
+use crate::{Map, Value};
+use std::io::{self, Read, Write};
+
+// the tag byte starting each encoded event
+const START_OBJECT: u8 = 0;
+const END_OBJECT: u8 = 1;
+const START_ARRAY: u8 = 2;
+const END_ARRAY: u8 = 3;
+const KEY: u8 = 4;
+const STRING: u8 = 5;
+const NUMBER: u8 = 6;
+const FALSE: u8 = 7;
+const TRUE: u8 = 8;
+const NULL: u8 = 9;
+
+/// Records events in a compact binary form, so an event stream can be stored and replayed later
+/// by an `EventDecoder`. Each event is a tag byte, followed for keys and strings by their length
+/// as a LEB128 varint and their UTF-8 bytes, and for numbers by the 8 little endian bytes of the
+/// `f64`.
+#[derive(Debug)]
+pub struct EventEncoder<W> {
+    writer: W,
+}
+
+impl<W: Write> EventEncoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self { writer }
+    }
+
+    pub fn write(&mut self, event: &Event) -> io::Result<()> {
+        let (tag, string) = match event {
+            Event::StartObject => (START_OBJECT, None),
+            Event::EndObject => (END_OBJECT, None),
+            Event::StartArray => (START_ARRAY, None),
+            Event::EndArray => (END_ARRAY, None),
+            Event::Key(key) => (KEY, Some(key)),
+            Event::String(string) => (STRING, Some(string)),
+            Event::Number(number) => {
+                self.writer.write_all(&[NUMBER])?;
+                return self.writer.write_all(&number.to_le_bytes());
+            }
+            Event::Boolean(false) => (FALSE, None),
+            Event::Boolean(true) => (TRUE, None),
+            Event::Null => (NULL, None),
+        };
+        self.writer.write_all(&[tag])?;
+        if let Some(string) = string {
+            self.write_len(string.len())?;
+            self.writer.write_all(string.as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Flushes and hands back the underlying writer
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    fn write_len(&mut self, mut len: usize) -> io::Result<()> {
+        loop {
+            let byte = (len & 0x7f) as u8;
+            len >>= 7;
+            if len == 0 {
+                return self.writer.write_all(&[byte]);
+            }
+            self.writer.write_all(&[byte | 0x80])?;
+        }
+    }
+}
+
+/// Replays the events recorded by an `EventEncoder`, e.g. into a `JsonWriter` with `write_event`
+/// or a `ValueBuilder` with `push`. Input that ends part way through an event or isn't an
+/// encoding of events is an `io::Error`, after which nothing more is read.
+#[derive(Debug)]
+pub struct EventDecoder<R> {
+    reader: R,
+    done: bool,
+}
+
+impl<R: Read> EventDecoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            done: false,
+        }
+    }
+
+    /// The next event, `None` when the input ends cleanly between events
+    fn read_event(&mut self) -> io::Result<Option<Event>> {
+        let mut tag = [0];
+        if self.reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let event = match tag[0] {
+            START_OBJECT => Event::StartObject,
+            END_OBJECT => Event::EndObject,
+            START_ARRAY => Event::StartArray,
+            END_ARRAY => Event::EndArray,
+            KEY => Event::Key(self.read_string()?),
+            STRING => Event::String(self.read_string()?),
+            NUMBER => {
+                let mut bytes = [0; 8];
+                self.reader.read_exact(&mut bytes)?;
+                Event::Number(f64::from_le_bytes(bytes))
+            }
+            FALSE => Event::Boolean(false),
+            TRUE => Event::Boolean(true),
+            NULL => Event::Null,
+            tag => return Err(invalid_data(format!("unknown event tag {tag}"))),
+        };
+        Ok(Some(event))
+    }
+
+    fn read_string(&mut self) -> io::Result<String> {
+        let len = self.read_len()?;
+        let mut bytes = Vec::new();
+        // the length isn't trusted for an allocation up front
+        (&mut self.reader)
+            .take(len as u64)
+            .read_to_end(&mut bytes)?;
+        if bytes.len() < len {
+            return Err(io::ErrorKind::UnexpectedEof.into());
+        }
+        String::from_utf8(bytes).map_err(|_| invalid_data(String::from("invalid UTF-8")))
+    }
+
+    fn read_len(&mut self) -> io::Result<usize> {
+        let mut len = 0_usize;
+        for shift in (0..usize::BITS).step_by(7) {
+            let mut byte = [0];
+            self.reader.read_exact(&mut byte)?;
+            len |= usize::from(byte[0] & 0x7f) << shift;
+            if byte[0] & 0x80 == 0 {
+                return Ok(len);
+            }
+        }
+        Err(invalid_data(String::from("length is too long")))
+    }
+}
+
+impl<R: Read> Iterator for EventDecoder<R> {
+    type Item = io::Result<Event>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let event = self.read_event().transpose();
+        self.done = !matches!(event, Some(Ok(_)));
+        event
+    }
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// An array or object being built, with the key of the member whose value comes next
+#[derive(Debug)]
+enum Open {
+    Array(Vec<Value>),
+    Object(Map<Value>, Option<String>),
+}
+
+/// Builds a `Value` from events pushed one at a time, checking they make a valid document the
+/// same way `JsonWriter` does. A repeated key keeps its last value.
+#[derive(Debug, Default)]
+pub struct ValueBuilder {
+    stack: Vec<Open>,
+    /// the finished document
+    value: Option<Value>,
+}
+
+impl ValueBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn push(&mut self, event: Event) -> Result<(), WriteError> {
+        let value = match event {
+            Event::StartObject => {
+                self.begin_value()?;
+                self.stack.push(Open::Object(Map::new(), None));
+                return Ok(());
+            }
+            Event::StartArray => {
+                self.begin_value()?;
+                self.stack.push(Open::Array(Vec::new()));
+                return Ok(());
+            }
+            Event::Key(key) => {
+                return match self.stack.last_mut() {
+                    Some(Open::Object(_, pending @ None)) => {
+                        *pending = Some(key);
+                        Ok(())
+                    }
+                    _ => Err(WriteError::UnexpectedKey),
+                };
+            }
+            Event::EndObject | Event::EndArray => {
+                let object = matches!(event, Event::EndObject);
+                match self.stack.last() {
+                    Some(Open::Object(_, Some(_))) | None => return Err(WriteError::NothingToEnd),
+                    Some(Open::Object(..)) if !object => return Err(WriteError::MismatchedEnd),
+                    Some(Open::Array(_)) if object => return Err(WriteError::MismatchedEnd),
+                    _ => {}
+                }
+                match self.stack.pop() {
+                    Some(Open::Object(map, _)) => Value::Object(map),
+                    Some(Open::Array(array)) => Value::Array(array),
+                    None => unreachable!("checked above"),
+                }
+            }
+            Event::String(string) => {
+                self.begin_value()?;
+                Value::String(string)
+            }
+            Event::Number(number) => {
+                self.begin_value()?;
+                Value::Number(number)
+            }
+            Event::Boolean(boolean) => {
+                self.begin_value()?;
+                Value::Boolean(boolean)
+            }
+            Event::Null => {
+                self.begin_value()?;
+                Value::Null
+            }
+        };
+        self.complete(value);
+        Ok(())
+    }
+
+    /// Checks the document is complete and hands it back
+    pub fn finish(self) -> Result<Value, WriteError> {
+        match self.value {
+            Some(value) if self.stack.is_empty() => Ok(value),
+            _ => Err(WriteError::Unfinished),
+        }
+    }
+
+    /// Checks a value may go here
+    fn begin_value(&self) -> Result<(), WriteError> {
+        match self.stack.last() {
+            None if self.value.is_some() => Err(WriteError::DocumentComplete),
+            Some(Open::Object(_, None)) => Err(WriteError::ExpectedKey),
+            _ => Ok(()),
+        }
+    }
+
+    /// Puts a finished value in the container it belongs to
+    fn complete(&mut self, value: Value) {
+        match self.stack.last_mut() {
+            None => self.value = Some(value),
+            Some(Open::Array(array)) => array.push(value),
+            Some(Open::Object(map, key)) => {
+                if let Some(key) = key.take() {
+                    map.insert(key, value);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{EventDecoder, EventEncoder, ValueBuilder};
+    use crate::{parse, Event, JsonReader, JsonWriter, WriteError};
+    use std::io;
+
+    #[test]
+    fn records_and_replays() {
+        let input = r#"{"a": [1.5, "é", true, null, {}], "b": {"c": false}}"#;
+        let expected = parse(String::from(input)).unwrap();
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let mut encoder = EventEncoder::new(Vec::new());
+        while let Some(event) = reader.next_event().unwrap() {
+            encoder.write(&event).unwrap();
+        }
+        let recorded = encoder.finish().unwrap();
+        let mut builder = ValueBuilder::new();
+        let mut writer = JsonWriter::new(Vec::new());
+        for event in EventDecoder::new(&recorded[..]) {
+            let event = event.unwrap();
+            writer.write_event(&event).unwrap();
+            builder.push(event).unwrap();
+        }
+        let written = String::from_utf8(writer.finish().unwrap()).unwrap();
+        let actual = builder.finish().unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(parse(written).unwrap(), expected);
+    }
+
+    #[test]
+    fn encodes_compactly() {
+        let events = [
+            Event::StartArray,
+            Event::String("x".repeat(200)),
+            Event::Null,
+            Event::EndArray,
+        ];
+        let mut expected = vec![2, 5, 0xc8, 0x01];
+        expected.extend("x".repeat(200).bytes());
+        expected.extend([9, 3]);
+
+        let mut encoder = EventEncoder::new(Vec::new());
+        for event in &events {
+            encoder.write(event).unwrap();
+        }
+        let actual = encoder.finish().unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn rejects_corrupt_input() {
+        let cases: [&[u8]; 3] = [&[2, 5, 3, b'a'], &[2, 42], &[2, 6, 0, 0]];
+
+        for input in cases {
+            let actual: Vec<_> = EventDecoder::new(input)
+                .map(|event| event.map_err(|err| err.kind()))
+                .collect();
+
+            assert_eq!(actual[0], Ok(Event::StartArray));
+            assert!(matches!(
+                actual[1..],
+                [Err(
+                    io::ErrorKind::UnexpectedEof | io::ErrorKind::InvalidData
+                )]
+            ));
+        }
+    }
+
+    #[test]
+    fn builder_checks_structure() {
+        let cases = [
+            (
+                vec![Event::StartObject, Event::Null],
+                WriteError::ExpectedKey,
+            ),
+            (
+                vec![Event::StartArray, Event::Key(String::new())],
+                WriteError::UnexpectedKey,
+            ),
+            (
+                vec![Event::StartArray, Event::EndObject],
+                WriteError::MismatchedEnd,
+            ),
+            (vec![Event::EndArray], WriteError::NothingToEnd),
+            (vec![Event::Null, Event::Null], WriteError::DocumentComplete),
+        ];
+
+        for (events, expected) in cases {
+            let mut builder = ValueBuilder::new();
+            let actual = events
+                .into_iter()
+                .try_for_each(|event| builder.push(event))
+                .unwrap_err();
+
+            assert_eq!(actual.to_string(), expected.to_string());
+        }
+        let mut builder = ValueBuilder::new();
+        builder.push(Event::StartArray).unwrap();
+        assert!(matches!(builder.finish(), Err(WriteError::Unfinished)));
+    }
+}
@@ -0,0 +1,329 @@
+use crate::error::Error;
+use crate::options::ParseOptions;
+use crate::span::Position;
+use crate::tokenize::TokenizeError;
+use crate::{parse_with, Value};
+
+/// What the scanner is in the middle of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Scan {
+    /// tokens and the whitespace between them
+    Code,
+    /// a string, `escaped` straight after a backslash
+    String {
+        escaped: bool,
+    },
+    LineComment,
+    BlockComment,
+}
+
+/// A parser fed input in chunks of any size as it arrives, handing back each value once all of
+/// it has been fed. Suits protocols whose json frames arrive fragmented, e.g. over TCP.
+///
+/// Values are found by scanning for where they end, then parsed on their own, so a bad value is
+/// reported and the values after it are still read. A top level number or literal only ends
+/// once something follows it, or once `finish` says no more input is coming.
+#[derive(Debug)]
+pub struct FeedParser {
+    options: ParseOptions,
+    /// input fed but not yet handed back as part of a value
+    buffer: Vec<u8>,
+    /// where in the whole input `buffer` starts
+    position: Position,
+    /// how far into `buffer` has been scanned
+    scan: usize,
+    state: Scan,
+    /// where in `buffer` the value being scanned starts
+    start: Option<usize>,
+    /// how many arrays and objects the scan is inside of
+    depth: usize,
+    /// whether `finish` has been called
+    finished: bool,
+    /// whether a value outgrew `ParseOptions::max_input_len`, after which nothing more is read
+    failed: bool,
+}
+
+impl Default for FeedParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FeedParser {
+    pub fn new() -> Self {
+        Self::with_options(ParseOptions::default())
+    }
+
+    /// A parser applying `options` to each value, `max_input_len` limits the length of one value
+    pub fn with_options(options: ParseOptions) -> Self {
+        Self {
+            options,
+            buffer: Vec::new(),
+            position: Position::default(),
+            scan: 0,
+            state: Scan::Code,
+            start: None,
+            depth: 0,
+            finished: false,
+            failed: false,
+        }
+    }
+
+    /// Adds the next chunk of input, which may end partway through a value or even a char
+    pub fn feed(&mut self, bytes: &[u8]) {
+        if !self.finished && !self.failed {
+            self.buffer.extend_from_slice(bytes);
+        }
+    }
+
+    /// Says the input is over, so whatever is left is parsed as the last value
+    pub fn finish(&mut self) {
+        self.finished = true;
+    }
+
+    /// The next value fed in full, `None` until more input is fed
+    pub fn poll(&mut self) -> Option<Result<Value, Error>> {
+        if self.failed {
+            return None;
+        }
+        let Some((start, end)) = self.scan_value() else {
+            return self.wait();
+        };
+        let mut position = self.position;
+        position.advance(&self.buffer[..start]);
+        let bytes = &self.buffer[start..end];
+        let result = match std::str::from_utf8(bytes) {
+            Ok(text) => parse_with(String::from(text), &self.options),
+            Err(err) => {
+                let text = String::from_utf8_lossy(&bytes[..err.valid_up_to()]);
+                Err(Error::new(
+                    &text,
+                    text.len(),
+                    TokenizeError::InvalidUtf8.into(),
+                ))
+            }
+        };
+        self.drain(end);
+        Some(result.map_err(|err| position.relocate(err)))
+    }
+
+    /// Checks the value still being fed is within limits, dropping anything before it
+    fn wait(&mut self) -> Option<Result<Value, Error>> {
+        let Some(start) = self.start else {
+            // whitespace and comments between values can go straight away
+            self.drain(self.scan);
+            return None;
+        };
+        let len = self.buffer.len() - start;
+        if self.options.max_input_len.is_some_and(|max| len > max) {
+            self.failed = true;
+            let mut position = self.position;
+            position.advance(&self.buffer[..start]);
+            let err = Error::new("", 0, TokenizeError::InputTooLarge.into());
+            return Some(Err(position.relocate(err)));
+        }
+        None
+    }
+
+    /// Finds the start and end in `buffer` of the next complete value
+    fn scan_value(&mut self) -> Option<(usize, usize)> {
+        while self.scan < self.buffer.len() {
+            let index = self.scan;
+            let byte = self.buffer[index];
+            self.scan += 1;
+            match self.state {
+                Scan::String { escaped } => {
+                    self.state = match byte {
+                        b'"' if !escaped => Scan::Code,
+                        b'\\' if !escaped => Scan::String { escaped: true },
+                        _ => Scan::String { escaped: false },
+                    };
+                    if self.state == Scan::Code && self.depth == 0 {
+                        return self.start.map(|start| (start, self.scan));
+                    }
+                }
+                Scan::LineComment if byte == b'\n' => self.state = Scan::Code,
+                Scan::BlockComment if byte == b'*' => match self.buffer.get(index + 1) {
+                    Some(b'/') => {
+                        self.scan += 1;
+                        self.state = Scan::Code;
+                    }
+                    // the `/` may be in the next chunk
+                    None if !self.finished => {
+                        self.scan = index;
+                        break;
+                    }
+                    _ => {}
+                },
+                Scan::LineComment | Scan::BlockComment => {}
+                Scan::Code => {
+                    // a top level number or literal ends at whatever comes after it
+                    let scalar = self.depth == 0 && self.start.is_some();
+                    match byte {
+                        b' ' | b'\t' | b'\n' | b'\r' if scalar => {
+                            return self.start.map(|start| (start, index))
+                        }
+                        b' ' | b'\t' | b'\n' | b'\r' => {}
+                        b'/' if self.options.allow_comments => {
+                            let state = match self.buffer.get(index + 1) {
+                                Some(b'/') => Scan::LineComment,
+                                Some(b'*') => Scan::BlockComment,
+                                None if !self.finished => {
+                                    self.scan = index;
+                                    break;
+                                }
+                                _ => {
+                                    self.start.get_or_insert(index);
+                                    continue;
+                                }
+                            };
+                            if scalar {
+                                return self.start.map(|start| (start, index));
+                            }
+                            self.scan += 1;
+                            self.state = state;
+                        }
+                        b'"' | b'[' | b'{' | b']' | b'}' if scalar => {
+                            return self.start.map(|start| (start, index));
+                        }
+                        b'"' => {
+                            self.start.get_or_insert(index);
+                            self.state = Scan::String { escaped: false };
+                        }
+                        b'[' | b'{' => {
+                            self.start.get_or_insert(index);
+                            self.depth += 1;
+                        }
+                        // a stray closer at the top level is a value of its own, which won't parse
+                        b']' | b'}' if self.depth <= 1 => {
+                            let start = *self.start.get_or_insert(index);
+                            return Some((start, self.scan));
+                        }
+                        b']' | b'}' => self.depth -= 1,
+                        _ => {
+                            self.start.get_or_insert(index);
+                        }
+                    }
+                }
+            }
+        }
+        // once the input is over, whatever was started is all there is of it
+        match self.finished {
+            true => self.start.map(|start| (start, self.buffer.len())),
+            false => None,
+        }
+    }
+
+    /// Drops `buffer` up to `end`, where the scan starts again from scratch
+    fn drain(&mut self, end: usize) {
+        self.position.advance(&self.buffer[..end]);
+        self.buffer.drain(..end);
+        self.scan = 0;
+        self.start = None;
+        self.depth = 0;
+        self.state = Scan::Code;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FeedParser;
+    use crate::{ErrorCode, Map, ParseOptions, TokenParseError, TokenizeError, Value};
+
+    #[test]
+    fn parses_fragments() {
+        let input = "{\"a\": [1, \"x]\\\"\"]} 2 \"s\"[true]null";
+        let expected = vec![
+            Value::Object(Map::from([(
+                String::from("a"),
+                Value::Array(vec![
+                    Value::Number(1.0),
+                    Value::String(String::from("x]\"")),
+                ]),
+            )])),
+            Value::Number(2.0),
+            Value::String(String::from("s")),
+            Value::Array(vec![Value::Boolean(true)]),
+            Value::Null,
+        ];
+
+        let mut parser = FeedParser::new();
+        let mut actual = Vec::new();
+        for byte in input.as_bytes() {
+            parser.feed(&[*byte]);
+            while let Some(value) = parser.poll() {
+                actual.push(value.unwrap());
+            }
+        }
+        // `null` could still become something longer
+        assert_eq!(actual.len(), expected.len() - 1);
+        parser.finish();
+        actual.extend(parser.poll().map(Result::unwrap));
+
+        assert_eq!(actual, expected);
+        assert!(parser.poll().is_none());
+    }
+
+    #[test]
+    fn reports_bad_values() {
+        let input = "[1,\n 2 3] {\"é\": }\n[4]";
+        let expected = vec![
+            Err((
+                ErrorCode::ParseError(TokenParseError::ExpectedComma),
+                7,
+                2,
+                4,
+            )),
+            Err((
+                ErrorCode::ParseError(TokenParseError::ExpectedValue),
+                17,
+                2,
+                13,
+            )),
+            Ok(Value::Array(vec![Value::Number(4.0)])),
+        ];
+
+        let mut parser = FeedParser::new();
+        parser.feed(input.as_bytes());
+        let actual: Vec<_> = std::iter::from_fn(|| parser.poll())
+            .map(|result| result.map_err(|err| (err.code, err.offset, err.line, err.column)))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn skips_comments() {
+        let input = "/* [ */ [1 // ]\n] 2/**/";
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let expected = vec![Value::Array(vec![Value::Number(1.0)]), Value::Number(2.0)];
+
+        let mut parser = FeedParser::with_options(options);
+        for chunk in input.as_bytes().chunks(3) {
+            parser.feed(chunk);
+        }
+        let actual: Result<Vec<_>, _> = std::iter::from_fn(|| parser.poll()).collect();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn limits_value_length() {
+        let options = ParseOptions {
+            max_input_len: Some(6),
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::TokenizeError(TokenizeError::InputTooLarge);
+
+        let mut parser = FeedParser::with_options(options);
+        parser.feed(b"[1] [1, 2, 3");
+        assert!(matches!(parser.poll(), Some(Ok(_))));
+        let actual = parser.poll().unwrap().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+        assert!(parser.poll().is_none());
+    }
+}
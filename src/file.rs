@@ -0,0 +1,167 @@
+use crate::error::Error;
+use crate::options::ParseOptions;
+use crate::reader::from_reader_with;
+use crate::writer::{write_value, WriteError};
+use crate::Value;
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Makes each temporary file name unique among the writes of this process, whichever thread
+static TEMP_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Parses the file at `path`, reading it a chunk at a time
+pub fn from_file(path: impl AsRef<Path>) -> Result<Value, Error> {
+    from_file_with(path, &ParseOptions::default())
+}
+
+pub fn from_file_with(path: impl AsRef<Path>, options: &ParseOptions) -> Result<Value, Error> {
+    let file = File::open(path).map_err(|err| Error::located(0, 1, 1, err.into()))?;
+    from_reader_with(BufReader::new(file), options)
+}
+
+/// Writes `value` as compact json to the file at `path`, replacing it atomically. The output goes
+/// to a temporary file next to it that is renamed over it once complete, so a failure part way
+/// leaves the old file as it was and readers never see a half written one.
+pub fn to_file(path: impl AsRef<Path>, value: &Value) -> Result<(), WriteError> {
+    let path = path.as_ref();
+    let temp = temp_path(path)?;
+    // never open an existing file, which would belong to another write
+    let file = File::options().write(true).create_new(true).open(&temp)?;
+    let result = write_file(file, value).and_then(|()| Ok(fs::rename(&temp, path)?));
+    if result.is_err() {
+        // only the first error matters
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+fn write_file(file: File, value: &Value) -> Result<(), WriteError> {
+    let mut writer = BufWriter::new(file);
+    write_value(&mut writer, value)?;
+    let file = writer
+        .into_inner()
+        .map_err(io::IntoInnerError::into_error)?;
+    // the data must be on disk before the rename makes it visible
+    file.sync_all()?;
+    Ok(())
+}
+
+/// A hidden file in the same directory as `path`, as a rename can't cross file systems, named
+/// after the process and a count so concurrent writes never share one
+fn temp_path(path: &Path) -> io::Result<PathBuf> {
+    let Some(name) = path.file_name() else {
+        let message = "the path doesn't name a file";
+        return Err(io::Error::new(io::ErrorKind::InvalidInput, message));
+    };
+    let mut temp = std::ffi::OsString::from(".");
+    temp.push(name);
+    let count = TEMP_COUNTER.fetch_add(1, Ordering::Relaxed);
+    temp.push(format!(".{}.{count}.tmp", process::id()));
+    Ok(path.with_file_name(temp))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{from_file, temp_path, to_file};
+    use crate::{ErrorCode, ErrorKind, Map, Value, WriteError};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+
+    /// A path in the temp directory unique to the test
+    fn test_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("json-parser-{}-{name}.json", std::process::id()))
+    }
+
+    /// Whether a temporary file for `path` was left behind
+    fn leftover_temp(path: &Path) -> bool {
+        let name = format!(".{}.", path.file_name().unwrap().to_string_lossy());
+        fs::read_dir(path.parent().unwrap()).unwrap().any(|entry| {
+            entry
+                .unwrap()
+                .file_name()
+                .to_string_lossy()
+                .starts_with(&name)
+        })
+    }
+
+    #[test]
+    fn round_trips() {
+        let path = test_path("round-trips");
+        let expected = Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![Value::Number(1.5), Value::Null]),
+        )]));
+
+        to_file(&path, &expected).unwrap();
+        let actual = from_file(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn failed_write_keeps_file() {
+        let path = test_path("failed-write");
+        fs::write(&path, "[1]").unwrap();
+        let value = Value::Array(vec![Value::Number(f64::NAN)]);
+
+        let err = to_file(&path, &value).unwrap_err();
+        let actual = fs::read_to_string(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(matches!(err, WriteError::NonFiniteNumber));
+        assert_eq!(actual, "[1]");
+        assert!(!leftover_temp(&path));
+    }
+
+    #[test]
+    fn concurrent_writes_to_one_file() {
+        let path = test_path("concurrent");
+        let values: Vec<_> = (0..8).map(|n| Value::from(n as f64)).collect();
+
+        std::thread::scope(|scope| {
+            for value in &values {
+                let path = &path;
+                scope.spawn(move || {
+                    for _ in 0..20 {
+                        to_file(path, value).unwrap();
+                    }
+                });
+            }
+        });
+        let actual = from_file(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+
+        assert!(values.contains(&actual));
+        assert!(!leftover_temp(&path));
+    }
+
+    #[test]
+    fn temp_paths_differ() {
+        let path = test_path("temp-paths");
+
+        let first = temp_path(&path).unwrap();
+        let second = temp_path(&path).unwrap();
+
+        assert_ne!(first, second);
+    }
+
+    #[test]
+    fn missing_file() {
+        let path = test_path("missing");
+
+        let actual = from_file(&path).unwrap_err();
+
+        assert_eq!(actual.kind(), ErrorKind::Io);
+        assert!(matches!(
+            actual.code,
+            ErrorCode::Io {
+                kind: std::io::ErrorKind::NotFound,
+                ..
+            }
+        ));
+    }
+}
@@ -1,6 +1,87 @@
-mod tokenize;
+//! A JSON parser. Parsing never panics: whatever the input, every function returns either
+//! a Value or an Error describing what is wrong with it.
+
+// errors carry their full location and context by design and are only built on the failure path
+#![allow(clippy::result_large_err)]
+
+mod access;
+mod compare;
+mod error;
+mod events;
+mod feed;
+mod file;
+mod merge;
+mod ndjson;
+mod options;
+mod parse;
+mod path;
+mod pattern;
+mod pointer;
+mod query;
+mod reader;
+pub mod schema;
+mod seq;
+mod serialize;
+mod simd;
+mod span;
+mod split;
+mod stats;
+pub mod tokenize;
+mod transcode;
+mod visit;
+mod warning;
+mod wrangle;
+mod writer;
+pub use crate::access::PathError;
+pub use crate::compare::{diff, diff_included, Difference, Tolerance};
+pub use crate::error::{Error, ErrorCode, ErrorKind};
+pub use crate::events::{EventDecoder, EventEncoder, ValueBuilder};
+pub use crate::feed::FeedParser;
+pub use crate::file::{from_file, from_file_with, to_file};
+pub use crate::merge::{merge3, Conflict, Merged};
+pub use crate::ndjson::{par_iter_ndjson, par_iter_ndjson_with};
+pub use crate::options::{DuplicateKeyPolicy, ParseOptions, Progress, RootPolicy, SurrogatePolicy};
+use crate::parse::Parser;
+pub use crate::parse::{Expected, TokenParseError};
+use crate::path::pointer;
+pub use crate::path::{Path, PathSegment};
+pub use crate::pointer::{JsonPointer, PointerError};
+pub use crate::query::QueryError;
+pub use crate::reader::{
+    from_reader, from_reader_with, iter_array, iter_array_with, iter_documents,
+    iter_documents_with, validate_stream, validate_stream_with, ArrayIter, DocumentIter, Event,
+    Handler, JsonReader,
+};
+pub use crate::seq::{JsonSeqReader, JsonSeqWriter, RECORD_SEPARATOR};
+#[cfg(feature = "color")]
+pub use crate::serialize::to_terminal;
+pub use crate::serialize::{
+    to_jsonc, to_jsonc_with, to_string, to_string_preserving, to_string_preserving_with,
+    to_string_tree, to_string_truncated, to_string_with, to_vec, to_vec_pretty, to_vec_with,
+    to_writer, to_writer_tree, to_writer_with, LineEnding, Node, NonFinitePolicy, Notation,
+    PreviewLimits, SerializeOptions, Tree,
+};
+pub use crate::span::{line_column, Span, Spanned};
+pub use crate::split::Splitter;
+pub use crate::stats::Stats;
+use crate::tokenize::{
+    tokenize_commented, tokenize_recovering, tokenize_spanned, Token, TokenizeError, Tokenized,
+};
+pub use crate::transcode::{reformat, transcode, Style, TranscodeError, Transform};
+pub use crate::visit::{walk, walk_mut, Leaves, Visitor, VisitorMut};
+pub use crate::warning::{Warning, WarningKind};
+pub use crate::writer::{to_chunks, Chunks, FlushPolicy, JsonWriter, NdjsonWriter, WriteError};
 use std::collections::HashMap;
+use std::ops::Range;
+
+/// The map behind `Value::Object`: a `HashMap`, or with the `sorted_maps` feature a `BTreeMap`
+/// so members are always in key order and sorted output takes no sorting pass
+#[cfg(not(feature = "sorted_maps"))]
+pub type Map<V> = HashMap<String, V>;
+#[cfg(feature = "sorted_maps")]
+pub type Map<V> = std::collections::BTreeMap<String, V>;
 
+#[derive(Debug, Clone)]
 pub enum Value {
     /// literal characters `null`
     Null,
@@ -13,5 +94,778 @@ pub enum Value {
     /// zero to many json values
     Array(Vec<Value>),
     /// string keys with json values
-    Object(HashMap<String, Value>),
+    Object(Map<Value>),
+}
+
+/// A Value where every node, however deeply nested, carries where it came from in the input
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpannedValue {
+    Null,
+    Boolean(bool),
+    String(String),
+    Number(f64),
+    Array(Vec<Spanned<SpannedValue>>),
+    Object(Map<Spanned<SpannedValue>>),
+}
+
+macro_rules! number_from {
+    ($($t:ty),*) => {$(
+        impl From<$t> for Value {
+            /// Numbers are held as a 64 bit float, integers beyond 2^53 lose precision
+            fn from(number: $t) -> Self {
+                Value::Number(number as f64)
+            }
+        }
+    )*};
+}
+
+number_from!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize, f32, f64);
+
+impl From<bool> for Value {
+    fn from(boolean: bool) -> Self {
+        Value::Boolean(boolean)
+    }
+}
+
+impl From<String> for Value {
+    fn from(string: String) -> Self {
+        Value::String(string)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(string: &str) -> Self {
+        Value::String(String::from(string))
+    }
+}
+
+impl From<Vec<Value>> for Value {
+    fn from(elements: Vec<Value>) -> Self {
+        Value::Array(elements)
+    }
+}
+
+impl From<Map<Value>> for Value {
+    fn from(members: Map<Value>) -> Self {
+        Value::Object(members)
+    }
+}
+
+/// Takes in a JSON document and returns the Value it describes
+pub fn parse(input: String) -> Result<Value, Error> {
+    parse_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse` but with control over how the document is interpreted
+pub fn parse_with(input: String, options: &ParseOptions) -> Result<Value, Error> {
+    let tokenized = tokenize_document(&input, options)?;
+
+    let mut parser = Parser::new(&input, &tokenized.tokens, options);
+    parse_document(&input, &mut parser, &tokenized)
+}
+
+/// Tokenizes the single document in the input
+fn tokenize_document(input: &str, options: &ParseOptions) -> Result<Tokenized, Error> {
+    tokenize_commented(input, options).map_err(|err| Error::from_tokenize(input, err))
+}
+
+/// Parses the one document the tokens hold. Tokenizing stops early when trailing data is
+/// allowed, so running out of tokens means the document reached the input that failed.
+fn parse_document(input: &str, parser: &mut Parser, tokenized: &Tokenized) -> Result<Value, Error> {
+    parser
+        .parse_document()
+        .map_err(|err| match tokenized.errors.first() {
+            Some(cut) if err.offset >= cut.span.start => Error::from_tokenize(input, cut.clone()),
+            _ => err,
+        })
+}
+
+/// Where each value of a document came from, keyed by JSON Pointer with `""` for the root
+pub type SourceMap = HashMap<String, Span>;
+
+/// Same as `parse_with` but also returns a warning for everything the lenient options let
+/// through, such as comments and trailing commas, so strictness can be tightened gradually
+pub fn parse_with_warnings(
+    input: String,
+    options: &ParseOptions,
+) -> Result<(Value, Vec<Warning>), Error> {
+    let tokenized = tokenize_document(&input, options)?;
+
+    let mut parser = Parser::new(&input, &tokenized.tokens, options);
+    let value = parse_document(&input, &mut parser, &tokenized)?;
+
+    let comments = tokenized.comments.iter();
+    let mut warnings: Vec<Warning> = comments
+        .map(|comment| Warning::new(&input, comment.start, WarningKind::Comment))
+        .collect();
+    warnings.append(&mut parser.warnings);
+    // comments and parser warnings arrive separately, report them in document order
+    warnings.sort_by_key(|warning| warning.offset);
+    Ok((value, warnings))
+}
+
+/// Same as `parse` but also returns the byte range of every value in the input,
+/// so tools can point back at the text a value was parsed from
+pub fn parse_source_map(input: String) -> Result<(Value, SourceMap), Error> {
+    parse_source_map_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse_source_map` but with control over how the document is interpreted
+pub fn parse_source_map_with(
+    input: String,
+    options: &ParseOptions,
+) -> Result<(Value, SourceMap), Error> {
+    let tokenized = tokenize_document(&input, options)?;
+
+    let mut parser = Parser::new(&input, &tokenized.tokens, options).with_spans();
+    let value = parse_document(&input, &mut parser, &tokenized)?;
+
+    Ok((value, parser.spans.unwrap_or_default()))
+}
+
+/// Same as `parse` but every node of the result carries its span, for tools that report
+/// problems with a value against where it was written
+pub fn parse_spanned(input: String) -> Result<Spanned<SpannedValue>, Error> {
+    parse_spanned_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse_spanned` but with control over how the document is interpreted
+pub fn parse_spanned_with(
+    input: String,
+    options: &ParseOptions,
+) -> Result<Spanned<SpannedValue>, Error> {
+    let (value, spans) = parse_source_map_with(input, options)?;
+    Ok(attach_spans(value, &spans, &mut Vec::new()))
+}
+
+/// Rebuilds the Value with the span of each node looked up in the source map
+fn attach_spans(
+    value: Value,
+    spans: &SourceMap,
+    path: &mut Vec<PathSegment>,
+) -> Spanned<SpannedValue> {
+    let span = spans.get(&pointer(path)).copied().unwrap_or_default();
+    let value = match value {
+        Value::Null => SpannedValue::Null,
+        Value::Boolean(boolean) => SpannedValue::Boolean(boolean),
+        Value::String(string) => SpannedValue::String(string),
+        Value::Number(number) => SpannedValue::Number(number),
+        Value::Array(array) => {
+            let mut elements = Vec::with_capacity(array.len());
+            for (index, element) in array.into_iter().enumerate() {
+                path.push(PathSegment::Index(index));
+                elements.push(attach_spans(element, spans, path));
+                path.pop();
+            }
+            SpannedValue::Array(elements)
+        }
+        Value::Object(map) => {
+            let mut members = Map::new();
+            for (key, member) in map {
+                path.push(PathSegment::Key(key));
+                let member = attach_spans(member, spans, path);
+                if let Some(PathSegment::Key(key)) = path.pop() {
+                    members.insert(key, member);
+                }
+            }
+            SpannedValue::Object(members)
+        }
+    };
+    Spanned::new(value, span)
+}
+
+/// Checks whether the input is a valid JSON document without building the Value it describes.
+/// The whole document is still tokenized up front, decoded strings included, so memory grows
+/// with the input; `validate_stream` checks it in constant memory but doesn't look for
+/// duplicate keys.
+pub fn validate(input: &str) -> Result<(), Error> {
+    validate_with(input, &ParseOptions::default())
+}
+
+/// Same as `validate` but with control over what counts as valid
+pub fn validate_with(input: &str, options: &ParseOptions) -> Result<(), Error> {
+    let tokenized = tokenize_document(input, options)?;
+
+    let mut parser = Parser::new(input, &tokenized.tokens, options).validating();
+    parse_document(input, &mut parser, &tokenized)?;
+    Ok(())
+}
+
+/// Strips the whitespace from a document, and any comments the options allow, without building
+/// the Value it describes. Every token is copied as it was written, so numbers and string
+/// escapes come through byte for byte.
+pub fn minify(input: &str) -> Result<String, Error> {
+    minify_with(input, &ParseOptions::default())
+}
+
+/// Same as `minify` but with control over what counts as valid, a trailing comma the options
+/// allow is dropped
+pub fn minify_with(input: &str, options: &ParseOptions) -> Result<String, Error> {
+    relayout(input, options, Style::Minified)
+}
+
+/// Lays a document out afresh in `style` without building the Value it describes. Every token
+/// is copied as it was written, so a formatter never changes a number or a string escape.
+pub fn prettify(input: &str, style: Style) -> Result<String, Error> {
+    prettify_with(input, style, &ParseOptions::default())
+}
+
+/// Same as `prettify` but with control over what counts as valid, comments the options allow
+/// are dropped along with a trailing comma
+pub fn prettify_with(input: &str, style: Style, options: &ParseOptions) -> Result<String, Error> {
+    relayout(input, options, style)
+}
+
+/// Checks the document then writes its tokens with the whitespace of `style` between them
+fn relayout(input: &str, options: &ParseOptions, style: Style) -> Result<String, Error> {
+    let tokenized = tokenize_document(input, options)?;
+
+    let mut parser = Parser::new(input, &tokenized.tokens, options).validating();
+    parse_document(input, &mut parser, &tokenized)?;
+
+    let closes =
+        |token: &Spanned<Token>| matches!(token.value, Token::RightBracket | Token::RightBrace);
+    let mut output = String::with_capacity(input.len());
+    let mut depth = 0_usize;
+    let mut tokens = tokenized.tokens.iter().peekable();
+    while let Some(token) = tokens.next() {
+        let raw = &input[Range::from(token.span)];
+        match token.value {
+            Token::Comma if tokens.peek().is_some_and(|next| closes(next)) => continue,
+            Token::Comma => {
+                output.push(',');
+                newline(&mut output, style, depth);
+            }
+            Token::Colon => match style {
+                Style::Minified => output.push(':'),
+                Style::Pretty { .. } => output.push_str(": "),
+            },
+            Token::LeftBracket | Token::LeftBrace => {
+                output.push_str(raw);
+                // an empty container stays on one line
+                match tokens.next_if(|next| closes(next)) {
+                    Some(closer) => output.push_str(&input[Range::from(closer.span)]),
+                    None => {
+                        depth += 1;
+                        newline(&mut output, style, depth);
+                    }
+                }
+            }
+            _ if closes(token) => {
+                depth -= 1;
+                newline(&mut output, style, depth);
+                output.push_str(raw);
+            }
+            _ => output.push_str(raw),
+        }
+        // whatever follows the document is only there when the options allow it
+        if depth == 0 {
+            break;
+        }
+    }
+    Ok(output)
+}
+
+/// Starts a new line indented for `depth` when pretty printing
+fn newline(output: &mut String, style: Style, depth: usize) {
+    if let Style::Pretty {
+        indent,
+        line_ending,
+    } = style
+    {
+        output.push_str(line_ending.as_str());
+        output.extend(std::iter::repeat_n(' ', indent * depth));
+    }
+}
+
+/// Parses back-to-back documents such as `{"a":1}{"b":2}`, returning one Value per document
+pub fn parse_documents(input: String) -> Result<Vec<Value>, Error> {
+    parse_documents_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse_documents` but with control over how the documents are interpreted
+pub fn parse_documents_with(input: String, options: &ParseOptions) -> Result<Vec<Value>, Error> {
+    let tokens =
+        tokenize_spanned(&input, options).map_err(|err| Error::from_tokenize(&input, err))?;
+
+    let mut parser = Parser::new(&input, &tokens, options);
+    let mut documents = Vec::new();
+    // each value ends exactly where the next document begins
+    while !parser.is_finished() {
+        documents.push(parser.parse_root()?);
+    }
+
+    Ok(documents)
+}
+
+/// Parses a document without stopping at the first mistake, returning every error found
+pub fn parse_recovering(input: String) -> Result<Value, Vec<Error>> {
+    parse_recovering_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse_recovering` but with control over how the document is interpreted
+pub fn parse_recovering_with(input: String, options: &ParseOptions) -> Result<Value, Vec<Error>> {
+    match parse_lenient_with(input, options) {
+        (Some(value), errors) if errors.is_empty() => Ok(value),
+        (_, errors) => Err(errors),
+    }
+}
+
+/// Parses as much of a truncated or malformed document as possible, returning the
+/// best-effort Value alongside every error. The Value is `None` only when nothing usable was found.
+pub fn parse_lenient(input: String) -> (Option<Value>, Vec<Error>) {
+    parse_lenient_with(input, &ParseOptions::default())
+}
+
+/// Same as `parse_lenient` but with control over how the document is interpreted
+pub fn parse_lenient_with(input: String, options: &ParseOptions) -> (Option<Value>, Vec<Error>) {
+    let (tokens, tokenize_errors) = tokenize_recovering(&input, options);
+    let mut errors: Vec<Error> = tokenize_errors
+        .into_iter()
+        .map(|err| Error::from_tokenize(&input, err))
+        .collect();
+    // the tokens stop short when tokenizing was cut off, parsing them would only add noise
+    let cut_off = errors.iter().any(|err| {
+        matches!(
+            err.code,
+            ErrorCode::TokenizeError(TokenizeError::InputTooLarge | TokenizeError::Cancelled)
+        )
+    });
+    if cut_off {
+        return (None, errors);
+    }
+
+    let mut parser = Parser::new(&input, &tokens, options).recovering();
+    let value = match parser.parse_root() {
+        Ok(value) => Some(value),
+        Err(err) => {
+            errors.push(err);
+            None
+        }
+    };
+    if value.is_some() && !parser.is_finished() {
+        if options.allow_trailing_data {
+            // whatever follows the document is ignored, including its mistakes
+            let end = tokens[parser.index].span.start;
+            errors.retain(|err| err.offset < end);
+        } else {
+            let kind = TokenParseError::TrailingTokens;
+            errors.push(parser.unexpected(kind, &[Expected::Eof]));
+        }
+    }
+    errors.append(&mut parser.errors);
+
+    // tokenizer and parser errors arrive separately, report them in document order
+    errors.sort_by_key(|err| err.offset);
+    (value, errors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        line_column, minify, minify_with, parse, parse_documents, parse_lenient,
+        parse_lenient_with, parse_recovering, parse_source_map, parse_spanned, parse_with,
+        parse_with_warnings, prettify, prettify_with, validate, validate_with, DuplicateKeyPolicy,
+        ErrorCode, FeedParser, JsonReader, LineEnding, RootPolicy, Span, SpannedValue, Style,
+        TokenParseError, Value, WarningKind,
+    };
+    use crate::tokenize::TokenizeError;
+    use crate::{Map, ParseOptions};
+    use std::ops::Range;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    /// Runs the input through every entry point, all that matters is that none of them panic
+    fn parse_everything(input: &str) {
+        let _ = parse(String::from(input));
+        let _ = parse_documents(String::from(input));
+        let _ = minify(input);
+        let (_, errors) = parse_lenient(String::from(input));
+        for err in errors {
+            let _ = err.render(input);
+        }
+        let mut reader = JsonReader::new(input.as_bytes());
+        while let Ok(Some(_)) = reader.next_event() {}
+        let mut parser = FeedParser::new();
+        for chunk in input.as_bytes().chunks(3) {
+            parser.feed(chunk);
+            while parser.poll().is_some() {}
+        }
+        parser.finish();
+        while parser.poll().is_some() {}
+    }
+
+    #[test]
+    fn parses_document() {
+        let input = String::from("{\"list\": [1, 2.5, null], \"ok\": true}\n");
+        let expected = Value::Object(Map::from([
+            (
+                String::from("list"),
+                Value::Array(vec![Value::Number(1.0), Value::Number(2.5), Value::Null]),
+            ),
+            (String::from("ok"), Value::Boolean(true)),
+        ]));
+
+        let actual = parse(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn trailing_value() {
+        let input = String::from("1 2");
+        let expected = "expected end of input at line 1, column 3, found number `2`";
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(
+            actual.code,
+            ErrorCode::ParseError(TokenParseError::TrailingTokens)
+        );
+        assert_eq!(actual.to_string(), expected);
+    }
+
+    #[test]
+    fn concatenated_documents() {
+        let input = String::from("{\"a\":1}{\"b\":2}\n[]\nnull");
+        let expected = vec![
+            Value::Object(Map::from([(String::from("a"), Value::Number(1.0))])),
+            Value::Object(Map::from([(String::from("b"), Value::Number(2.0))])),
+            Value::Array(vec![]),
+            Value::Null,
+        ];
+
+        let actual = parse_documents(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn truncated_second_document() {
+        let input = String::from("{}[1,");
+        let expected = ErrorCode::ParseError(TokenParseError::UnexpectedEof);
+
+        let actual = parse_documents(input).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, 5);
+    }
+
+    #[test]
+    fn collects_every_error() {
+        let input = String::from("{\n  \"a\": tru,\n  \"b\" 1,\n  \"c\": [1 2]\n}");
+        let expected = [
+            ErrorCode::TokenizeError(TokenizeError::literal("tru", "true")),
+            ErrorCode::ParseError(TokenParseError::ExpectedColon),
+            ErrorCode::ParseError(TokenParseError::ExpectedComma),
+        ];
+
+        let actual = parse_recovering(input).unwrap_err();
+
+        let kinds: Vec<_> = actual.iter().map(|err| err.code.clone()).collect();
+        assert_eq!(kinds, expected);
+        let lines: Vec<_> = actual.iter().map(|err| err.line).collect();
+        assert_eq!(lines, [2, 3, 4]);
+    }
+
+    #[test]
+    fn recovering_valid_document() {
+        let input = String::from("[1, {}]");
+        let expected = Value::Array(vec![Value::Number(1.0), Value::Object(Map::new())]);
+
+        let actual = parse_recovering(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn lenient_cut_off_line() {
+        let input = String::from("{\"level\": \"warn\", \"tags\": [\"db\", \"sl");
+        let expected = Value::Object(Map::from([
+            (String::from("level"), Value::String(String::from("warn"))),
+            (
+                String::from("tags"),
+                Value::Array(vec![Value::String(String::from("db")), Value::Null]),
+            ),
+        ]));
+
+        let (actual, errors) = parse_lenient(input);
+
+        assert_eq!(actual, Some(expected));
+        let kinds: Vec<_> = errors.iter().map(|err| err.code.clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                ErrorCode::TokenizeError(TokenizeError::UnclosedQuotes),
+                ErrorCode::ParseError(TokenParseError::UnexpectedEof),
+            ]
+        );
+    }
+
+    #[test]
+    fn lenient_nothing_usable() {
+        let input = String::from("]");
+
+        let (actual, errors) = parse_lenient(input);
+
+        assert_eq!(actual, None);
+        assert_eq!(errors.len(), 1);
+    }
+
+    #[test]
+    fn never_panics() {
+        let documents = [
+            "{\"a\": [1, -2.5e+3, \"\\u00e9\\uD83D\\uDE00\"], \"b\": {\"c\": null}}",
+            "[true, false, \"é\\n\", {}, []]\n",
+        ];
+        let replacements = [
+            "", "\"", "\\", "[", "]", "{", "}", ",", ":", "-", "e", "u", "é",
+        ];
+
+        for document in documents {
+            let boundaries = (0..=document.len()).filter(|&i| document.is_char_boundary(i));
+            for i in boundaries {
+                // every truncation, and every char swapped for something that confuses the grammar
+                parse_everything(&document[..i]);
+                let Some(c) = document[i..].chars().next() else {
+                    continue;
+                };
+                for replacement in replacements {
+                    let rest = &document[i + c.len_utf8()..];
+                    parse_everything(&format!("{}{replacement}{rest}", &document[..i]));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn lenient_cancelled() {
+        let input = String::from("[1, 2, 3]");
+        let options = ParseOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..ParseOptions::default()
+        };
+
+        let (actual, errors) = parse_lenient_with(input, &options);
+
+        assert_eq!(actual, None);
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].is_cancelled());
+    }
+
+    #[test]
+    fn source_map() {
+        let input = String::from("[true, \"x\"]");
+        let expected = Value::Array(vec![Value::Boolean(true), Value::String(String::from("x"))]);
+
+        let (actual, spans) = parse_source_map(input.clone()).unwrap();
+
+        assert_eq!(actual, expected);
+        let range: Range<usize> = spans["/1"].into();
+        assert_eq!(&input[range], "\"x\"");
+    }
+
+    #[test]
+    fn spanned_tree() {
+        let input = String::from("{\n  \"name\": \"ann\",\n  \"age\": \"12\"\n}");
+
+        let actual = parse_spanned(input.clone()).unwrap();
+
+        let SpannedValue::Object(members) = actual.value else {
+            panic!("expected an object, found {actual:?}");
+        };
+        let age = &members["age"];
+        assert_eq!(age.value, SpannedValue::String(String::from("12")));
+        assert_eq!(line_column(&input, age.span.start), (3, 10));
+        assert_eq!(actual.span, Span::new(0, input.len()));
+    }
+
+    #[test]
+    fn warns_about_deviations() {
+        let input = String::from("{\n  // retries\n  \"a\": 1,\n  \"a\": 2,\n}");
+        let options = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let expected = [
+            WarningKind::Comment,
+            WarningKind::DuplicateKey(String::from("a")),
+            WarningKind::TrailingComma,
+        ];
+
+        let (_, warnings) = parse_with_warnings(input, &options).unwrap();
+
+        let kinds: Vec<_> = warnings.iter().map(|w| w.kind.clone()).collect();
+        assert_eq!(kinds, expected);
+        assert_eq!(warnings[0].to_string(), "comment at line 2, column 3");
+    }
+
+    #[test]
+    fn validates() {
+        let cases = [
+            "{\"a\": [1, \"two\", {\"b\": null}]}",
+            "[1 2]",
+            "{\"a\": 1} 2",
+            "\"\\x\"",
+        ];
+
+        for input in cases {
+            let expected = parse(String::from(input)).map(|_| ());
+
+            let actual = validate(input);
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn minifies() {
+        let input = "{ \"a\" : [ 1.50 , 1E2 , \"\\u0041 b\" ] ,\n\t\"c\" : { } }";
+        let expected = r#"{"a":[1.50,1E2,"\u0041 b"],"c":{}}"#;
+
+        let actual = minify(input);
+
+        assert_eq!(actual, Ok(String::from(expected)));
+    }
+
+    #[test]
+    fn minifies_lenient_input() {
+        let input = "// config\n[1, /* two */ 2,] trailing";
+        let options = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            allow_trailing_data: true,
+            ..ParseOptions::default()
+        };
+        let expected = "[1,2]";
+
+        let actual = minify_with(input, &options);
+
+        assert_eq!(actual, Ok(String::from(expected)));
+        assert!(minify("[1 2]").is_err());
+    }
+
+    #[test]
+    fn prettifies() {
+        let input = r#"{"a":[1.50,1E2,"\u0041",{},[ ]],"b":{"c":-0}}"#;
+        let expected = "{\n  \"a\": [\n    1.50,\n    1E2,\n    \"\\u0041\",\n    {},\n    []\n  ],\n  \"b\": {\n    \"c\": -0\n  }\n}";
+
+        let actual = prettify(
+            input,
+            Style::Pretty {
+                indent: 2,
+                line_ending: LineEnding::Lf,
+            },
+        );
+
+        assert_eq!(actual, Ok(String::from(expected)));
+        assert_eq!(minify(&actual.unwrap()), minify(input));
+    }
+
+    #[test]
+    fn prettifies_lenient_input() {
+        let input = "[1, // one\n 2,]";
+        let options = ParseOptions {
+            allow_comments: true,
+            allow_trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let expected = "[\n 1,\n 2\n]";
+
+        let actual = prettify_with(
+            input,
+            Style::Pretty {
+                indent: 1,
+                line_ending: LineEnding::Lf,
+            },
+            &options,
+        );
+
+        assert_eq!(actual, Ok(String::from(expected)));
+    }
+
+    #[test]
+    fn prettifies_with_crlf() {
+        let input = "[1, 2]";
+        let expected = "[\r\n 1,\r\n 2\r\n]";
+
+        let actual = prettify_with(
+            input,
+            Style::Pretty {
+                indent: 1,
+                line_ending: LineEnding::CrLf,
+            },
+            &ParseOptions::default(),
+        );
+
+        assert_eq!(actual, Ok(String::from(expected)));
+    }
+
+    #[test]
+    fn validates_duplicate_keys() {
+        let input = "{\"a\": 1, \"b\": 2, \"a\": 3}";
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeyPolicy::Error,
+            ..ParseOptions::default()
+        };
+
+        let actual = validate_with(input, &options).unwrap_err();
+
+        assert_eq!(actual.offset, 17);
+    }
+
+    #[test]
+    fn container_root() {
+        let options = ParseOptions {
+            root: RootPolicy::ObjectOrArray,
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedContainer);
+
+        let actual = parse_with(String::from("\"just a string\""), &options).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert!(parse_with(String::from("[\"a string\"]"), &options).is_ok());
+    }
+
+    #[test]
+    fn trailing_data() {
+        let options = ParseOptions {
+            allow_trailing_data: true,
+            ..ParseOptions::default()
+        };
+        let expected = Value::Array(vec![Value::Number(1.0)]);
+
+        let actual = parse_with(String::from("[1] 2, ] %% garbage"), &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn trailing_data_reached_by_document() {
+        let options = ParseOptions {
+            allow_trailing_data: true,
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::TokenizeError(TokenizeError::literal("nope", "null"));
+
+        let actual = parse_with(String::from("[1, nope]"), &options).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, 4);
+    }
+
+    #[test]
+    fn lenient_trailing_data() {
+        let options = ParseOptions {
+            allow_trailing_data: true,
+            ..ParseOptions::default()
+        };
+
+        let (actual, errors) = parse_lenient_with(String::from("{} ]] %%"), &options);
+
+        assert_eq!(actual, Some(Value::Object(Map::new())));
+        assert_eq!(errors, []);
+    }
 }
@@ -0,0 +1,160 @@
+use crate::pointer::JsonPointer;
+use crate::{to_string, Map, Value};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// The result of `merge3`: both sides' changes applied, with ours kept wherever they clash
+#[derive(Debug, Clone, PartialEq)]
+pub struct Merged {
+    /// the merged value
+    pub value: Value,
+    /// every place both sides changed differently, in document order with object members in
+    /// key order
+    pub conflicts: Vec<Conflict>,
+}
+
+/// One place where both sides of a three-way merge changed the same value differently
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    /// where in the values they clash
+    pub path: JsonPointer,
+    /// what was there before either change, `None` when nothing was
+    pub base: Option<Value>,
+    /// what our side has there, `None` when we removed it
+    pub ours: Option<Value>,
+    /// what their side has there, `None` when they removed it
+    pub theirs: Option<Value>,
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let describe = |value: &Option<Value>| match value {
+            Some(value) => to_string(value).unwrap_or_else(|_| format!("{value:?}")),
+            None => String::from("nothing"),
+        };
+        write!(
+            f,
+            "{:?}: ours changed {} to {}, theirs to {}",
+            self.path.to_string(),
+            describe(&self.base),
+            describe(&self.ours),
+            describe(&self.theirs)
+        )
+    }
+}
+
+/// Applies the changes `ours` and `theirs` each made to `base` to one value, for collaborative
+/// editing and syncing config. A value changed by only one side takes that change, and objects
+/// changed by both are merged member by member, members being added and removed as either
+/// side did. Anything else changed differently by both sides, arrays included as a whole, is
+/// a conflict that keeps our version.
+pub fn merge3(base: &Value, ours: &Value, theirs: &Value) -> Merged {
+    let mut conflicts = Vec::new();
+    let merged = merge(
+        Some(base),
+        Some(ours),
+        Some(theirs),
+        &mut JsonPointer::root(),
+        &mut conflicts,
+    );
+    let Some(value) = merged else {
+        unreachable!("the root is there on every side, so it's there after merging");
+    };
+    Merged { value, conflicts }
+}
+
+fn merge(
+    base: Option<&Value>,
+    ours: Option<&Value>,
+    theirs: Option<&Value>,
+    path: &mut JsonPointer,
+    conflicts: &mut Vec<Conflict>,
+) -> Option<Value> {
+    if ours == theirs || theirs == base {
+        return ours.cloned();
+    }
+    if ours == base {
+        return theirs.cloned();
+    }
+    if let (Some(Value::Object(ours)), Some(Value::Object(theirs))) = (ours, theirs) {
+        let empty = Map::new();
+        // an object added by both sides, or replacing something else, is merged from nothing
+        let base = match base {
+            Some(Value::Object(base)) => base,
+            _ => &empty,
+        };
+        let keys: BTreeSet<&String> = base
+            .keys()
+            .chain(ours.keys())
+            .chain(theirs.keys())
+            .collect();
+        let mut merged = Map::new();
+        for key in keys {
+            path.push(key.clone());
+            let member = merge(
+                base.get(key),
+                ours.get(key),
+                theirs.get(key),
+                path,
+                conflicts,
+            );
+            path.pop();
+            if let Some(member) = member {
+                merged.insert(key.clone(), member);
+            }
+        }
+        return Some(Value::Object(merged));
+    }
+    conflicts.push(Conflict {
+        path: path.clone(),
+        base: base.cloned(),
+        ours: ours.cloned(),
+        theirs: theirs.cloned(),
+    });
+    ours.cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::merge3;
+    use crate::{parse, Value};
+
+    fn value(input: &str) -> Value {
+        parse(String::from(input)).unwrap()
+    }
+
+    #[test]
+    fn merges_both_sides() {
+        let base = value(r#"{"name": "app", "port": 80, "debug": false, "tags": ["a"]}"#);
+        let ours = value(r#"{"name": "app", "port": 8080, "tags": ["a"], "env": {"a": 1}}"#);
+        let theirs = value(
+            r#"{"name": "service", "port": 80, "debug": false, "tags": ["a"], "env": {"b": 2}}"#,
+        );
+        let expected =
+            value(r#"{"name": "service", "port": 8080, "tags": ["a"], "env": {"a": 1, "b": 2}}"#);
+
+        let actual = merge3(&base, &ours, &theirs);
+
+        assert_eq!(actual.value, expected);
+        assert!(actual.conflicts.is_empty());
+    }
+
+    #[test]
+    fn reports_conflicts() {
+        let base = value(r#"{"port": 80, "tags": ["a"], "old": 1}"#);
+        let ours = value(r#"{"port": 8080, "tags": ["a", "b"]}"#);
+        let theirs = value(r#"{"port": 9090, "tags": ["c"], "old": 2}"#);
+        let expected = value(r#"{"port": 8080, "tags": ["a", "b"]}"#);
+        let expected_conflicts = vec![
+            r#""/old": ours changed 1 to nothing, theirs to 2"#,
+            r#""/port": ours changed 80 to 8080, theirs to 9090"#,
+            r#""/tags": ours changed ["a"] to ["a","b"], theirs to ["c"]"#,
+        ];
+
+        let actual = merge3(&base, &ours, &theirs);
+        let conflicts: Vec<_> = actual.conflicts.iter().map(|c| c.to_string()).collect();
+
+        assert_eq!(actual.value, expected);
+        assert_eq!(conflicts, expected_conflicts);
+    }
+}
@@ -0,0 +1,95 @@
+use crate::error::Error;
+use crate::options::ParseOptions;
+use crate::span::Position;
+use crate::{parse_with, Value};
+use std::{thread, vec};
+
+/// Parses newline delimited json, one value per line, spread across a thread per core. Results
+/// come back in the order of the lines, blank lines are skipped. Each line is parsed on its
+/// own, so a bad line is reported and the lines after it are still read.
+pub fn par_iter_ndjson(input: &str) -> vec::IntoIter<Result<Value, Error>> {
+    par_iter_ndjson_with(input, &ParseOptions::default())
+}
+
+pub fn par_iter_ndjson_with(
+    input: &str,
+    options: &ParseOptions,
+) -> vec::IntoIter<Result<Value, Error>> {
+    // where each line starts, errors are found within a line then moved there
+    let mut lines = Vec::new();
+    let mut offset = 0;
+    for (index, line) in input.split('\n').enumerate() {
+        let start = Position {
+            offset,
+            line: index + 1,
+            column: 1,
+        };
+        offset += line.len() + 1;
+        let line = line.strip_suffix('\r').unwrap_or(line);
+        if !line.trim_matches([' ', '\t']).is_empty() {
+            lines.push((start, line));
+        }
+    }
+    let threads = thread::available_parallelism().map_or(1, usize::from);
+    let batch = lines.len().div_ceil(threads).max(1);
+
+    let parse_line = |&(start, line): &(Position, &str)| {
+        parse_with(String::from(line), options).map_err(|err| start.relocate(err))
+    };
+    let results: Vec<_> = thread::scope(|scope| {
+        let handles: Vec<_> = lines
+            .chunks(batch)
+            .map(|lines| scope.spawn(move || lines.iter().map(parse_line).collect::<Vec<_>>()))
+            .collect();
+        handles
+            .into_iter()
+            .flat_map(|handle| match handle.join() {
+                Ok(results) => results,
+                Err(panic) => std::panic::resume_unwind(panic),
+            })
+            .collect()
+    });
+    results.into_iter()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::par_iter_ndjson;
+    use crate::{ErrorCode, Map, TokenParseError, Value};
+
+    #[test]
+    fn parses_lines_in_order() {
+        let input: String = (0..1000).map(|i| format!("[{i}]\n")).collect();
+        let expected: Vec<_> = (0..1000)
+            .map(|i| Ok(Value::Array(vec![Value::Number(f64::from(i))])))
+            .collect();
+
+        let actual: Vec<_> = par_iter_ndjson(&input).collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reports_bad_lines() {
+        let input = "{\"a\": 1}\r\n\n  \n{\"a\" 2}\ntrue";
+        let expected = vec![
+            Ok(Value::Object(Map::from([(
+                String::from("a"),
+                Value::Number(1.0),
+            )]))),
+            Err((
+                ErrorCode::ParseError(TokenParseError::ExpectedColon),
+                19,
+                4,
+                6,
+            )),
+            Ok(Value::Boolean(true)),
+        ];
+
+        let actual: Vec<_> = par_iter_ndjson(input)
+            .map(|result| result.map_err(|err| (err.code, err.offset, err.line, err.column)))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,132 @@
+use std::fmt;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Settings that change how a document is parsed, the default is strict RFC 8259
+#[derive(Debug, Clone)]
+pub struct ParseOptions {
+    /// what to do with a `\u` escape that is an unpaired surrogate
+    pub surrogates: SurrogatePolicy,
+    /// how many arrays and objects may be nested inside each other
+    pub max_depth: usize,
+    /// the longest input in bytes that will be looked at, `None` for no limit
+    pub max_input_len: Option<usize>,
+    /// how many array elements the whole document may hold, `None` for no limit
+    pub max_array_elements: Option<usize>,
+    /// how many object members the whole document may hold, `None` for no limit
+    pub max_object_members: Option<usize>,
+    /// what to do when an object has the same key more than once
+    pub duplicate_keys: DuplicateKeyPolicy,
+    /// set to true from another thread to abort the parse with a `Cancelled` error,
+    /// it is checked every few thousand tokens
+    pub cancel: Option<Arc<AtomicBool>>,
+    /// told how far through the input the parse has got, for drawing progress bars
+    pub progress: Option<Progress>,
+    /// skip `//` and `/* */` comments as if they were whitespace
+    pub allow_comments: bool,
+    /// accept a comma straight before a closing `]` or `}`
+    pub allow_trailing_commas: bool,
+    /// which values may be the whole document
+    pub root: RootPolicy,
+    /// ignore whatever follows the first value instead of failing with `TrailingTokens`
+    pub allow_trailing_data: bool,
+    /// store object members under their keys lowercased, for documents from case-insensitive
+    /// systems; keys that are the same once lowercased are duplicates
+    pub lowercase_keys: bool,
+}
+
+impl Default for ParseOptions {
+    fn default() -> Self {
+        Self {
+            surrogates: SurrogatePolicy::default(),
+            max_depth: 128,
+            max_input_len: None,
+            max_array_elements: None,
+            max_object_members: None,
+            duplicate_keys: DuplicateKeyPolicy::default(),
+            cancel: None,
+            progress: None,
+            allow_comments: false,
+            allow_trailing_commas: false,
+            root: RootPolicy::default(),
+            allow_trailing_data: false,
+            lowercase_keys: false,
+        }
+    }
+}
+
+impl ParseOptions {
+    /// Whether the caller has asked for the parse to stop
+    pub(crate) fn is_cancelled(&self) -> bool {
+        self.cancel
+            .as_ref()
+            .is_some_and(|cancel| cancel.load(Ordering::Relaxed))
+    }
+}
+
+/// How many tokens are handled between checks of `ParseOptions::cancel`
+pub(crate) const CANCEL_CHECK_INTERVAL: usize = 4096;
+
+/// How to decode a `\u` escape naming a surrogate without its other half, e.g. `"\uD800"`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SurrogatePolicy {
+    /// fail with `InvalidCodePointValue`, for strict validators
+    #[default]
+    Error,
+    /// substitute U+FFFD REPLACEMENT CHARACTER, the usual choice for log ingestion
+    Replace,
+    /// keep the escape as it was written, since a `String` can't hold a surrogate itself
+    ///
+    /// This is lossy: `"\uD800"` decodes to the same six characters as `"\\uD800"`, so
+    /// serializing the value escapes the backslash and parsing that again gives a string
+    /// holding a backslash rather than a surrogate
+    Preserve,
+}
+
+/// Which member to keep when an object repeats a key, e.g. `{"a": 1, "a": 2}`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DuplicateKeyPolicy {
+    /// keep the last member, as most json parsers do
+    #[default]
+    LastWins,
+    /// keep the first member and ignore the rest
+    FirstWins,
+    /// fail with `DuplicateKey`, for consumers where an ambiguous object is a security risk
+    Error,
+}
+
+/// Which values a document may consist of
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RootPolicy {
+    /// any value, as RFC 8259 allows
+    #[default]
+    Any,
+    /// only an object or an array, as RFC 4627 and some ecosystems require
+    ObjectOrArray,
+}
+
+/// A callback given the byte offset reached so far, called each time at least `interval`
+/// more bytes have been read and once more where reading stopped
+#[derive(Clone)]
+pub struct Progress {
+    /// the fewest bytes read between calls
+    pub interval: usize,
+    pub callback: Arc<dyn Fn(usize) + Send + Sync>,
+}
+
+impl Progress {
+    pub fn new(interval: usize, callback: impl Fn(usize) + Send + Sync + 'static) -> Self {
+        Self {
+            interval,
+            callback: Arc::new(callback),
+        }
+    }
+}
+
+impl fmt::Debug for Progress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Progress")
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
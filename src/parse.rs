@@ -0,0 +1,1217 @@
+use crate::options::CANCEL_CHECK_INTERVAL;
+use crate::path::{pointer, Path, PathSegment};
+use crate::span::{line_column, Span};
+use crate::tokenize::{Spanned, StringToken, Token};
+use crate::warning::{Warning, WarningKind};
+use crate::{DuplicateKeyPolicy, Error, ErrorCode, Map, ParseOptions, RootPolicy, Value};
+use std::collections::HashMap;
+use std::fmt;
+use std::num::ParseFloatError;
+
+type ParseResult = Result<Value, Error>;
+
+/// What the parser does next, driving the loop in `parse_value`
+enum Step {
+    /// parse the value starting at the current token
+    Value,
+    /// start the next element or member of the innermost container
+    Element,
+    /// a value is complete, or failed, and goes to the innermost container
+    Finished(ParseResult),
+    /// the innermost container is complete
+    Close,
+}
+
+/// A container that is still being parsed
+enum Frame<'a> {
+    Array(Vec<Value>),
+    Object {
+        map: Map<Value>,
+        /// where each key was first seen, only needed to report duplicates
+        key_spans: HashMap<String, Span>,
+        /// the key of the member whose value is being parsed
+        key: Option<&'a StringToken>,
+        /// whether no comma has been seen yet, so the object could still end
+        first: bool,
+    },
+}
+
+impl Frame<'_> {
+    fn closer(&self) -> Token {
+        match self {
+            Frame::Array(_) => Token::RightBracket,
+            Frame::Object { .. } => Token::RightBrace,
+        }
+    }
+
+    fn into_value(self) -> Value {
+        match self {
+            Frame::Array(array) => Value::Array(array),
+            Frame::Object { map, .. } => Value::Object(map),
+        }
+    }
+}
+
+/// Turns spanned tokens into Values, either stopping at the first error or recovering from it
+pub struct Parser<'a> {
+    input: &'a str,
+    tokens: &'a [Spanned<Token>],
+    options: &'a ParseOptions,
+    /// the token the parser is looking at
+    pub index: usize,
+    /// when set, syntax errors are collected and the parser resynchronizes instead of stopping
+    recover: bool,
+    /// when set, the grammar is checked but no Value is built, everything parses as `Null`
+    discard: bool,
+    /// the errors recovered from so far
+    pub errors: Vec<Error>,
+    /// every deviation from strict json that the options allowed
+    pub warnings: Vec<Warning>,
+    /// the closing token of every container currently open, innermost last
+    closers: Vec<Token>,
+    /// the key or index of every value currently being parsed, innermost last
+    path: Vec<PathSegment>,
+    /// where each open container started, innermost last
+    starts: Vec<usize>,
+    /// where every parsed value came from, keyed by JSON Pointer, when asked for
+    pub spans: Option<HashMap<String, Span>>,
+    /// array elements seen so far in the whole document
+    elements: usize,
+    /// object members seen so far in the whole document
+    members: usize,
+}
+
+impl<'a> Parser<'a> {
+    pub fn new(input: &'a str, tokens: &'a [Spanned<Token>], options: &'a ParseOptions) -> Self {
+        Self {
+            input,
+            tokens,
+            options,
+            index: 0,
+            recover: false,
+            discard: false,
+            errors: Vec::new(),
+            warnings: Vec::new(),
+            closers: Vec::new(),
+            path: Vec::new(),
+            starts: Vec::new(),
+            spans: None,
+            elements: 0,
+            members: 0,
+        }
+    }
+
+    /// Keep going after syntax errors, skipping to the next `,`, `}` or `]` each time
+    pub fn recovering(mut self) -> Self {
+        self.recover = true;
+        self
+    }
+
+    /// Only check the document is valid, without building the Value it describes
+    pub fn validating(mut self) -> Self {
+        self.discard = true;
+        self
+    }
+
+    /// Record where every value came from in `spans`
+    pub fn with_spans(mut self) -> Self {
+        self.spans = Some(HashMap::new());
+        self
+    }
+
+    /// Whether every token has been consumed
+    pub fn is_finished(&self) -> bool {
+        self.index >= self.tokens.len()
+    }
+
+    /// Parses every remaining token as a single document
+    pub fn parse_document(&mut self) -> ParseResult {
+        let value = self.parse_root()?;
+        // a document is exactly one value, unless what follows is to be ignored
+        if !self.is_finished() && !self.options.allow_trailing_data {
+            let kind = TokenParseError::TrailingTokens;
+            return Err(self.unexpected(kind, &[Expected::Eof]));
+        }
+        Ok(value)
+    }
+
+    /// Parses the value a document consists of, which `ParseOptions::root` may restrict
+    pub fn parse_root(&mut self) -> ParseResult {
+        let container = matches!(
+            self.peek(),
+            Some(Token::LeftBrace | Token::LeftBracket) | None
+        );
+        if self.options.root == RootPolicy::ObjectOrArray && !container {
+            let kind = TokenParseError::ExpectedContainer;
+            return Err(self.unexpected(kind, &[]));
+        }
+        self.parse_value()
+    }
+
+    /// Parses the value starting at `index`, leaving `index` just past its last token.
+    /// Open containers are kept on the heap rather than the call stack, so nesting depth
+    /// is bounded by `ParseOptions::max_depth` and memory, never by recursion.
+    pub fn parse_value(&mut self) -> ParseResult {
+        let mut frames: Vec<Frame<'a>> = Vec::new();
+        let mut step = Step::Value;
+        let mut steps: usize = 0;
+        loop {
+            steps = steps.wrapping_add(1);
+            if steps.is_multiple_of(CANCEL_CHECK_INTERVAL) && self.options.is_cancelled() {
+                return Err(self.unexpected(TokenParseError::Cancelled, &[]));
+            }
+            step = match step {
+                Step::Value => self.begin_value(&mut frames)?,
+                Step::Element => self.begin_element(&mut frames)?,
+                Step::Finished(result) => match frames.last_mut() {
+                    Some(frame) => self.end_element(frame, result)?,
+                    None => return result,
+                },
+                Step::Close => {
+                    // containers are only closed while they are open
+                    let Some(frame) = frames.pop() else {
+                        unreachable!("closed a container that was never opened")
+                    };
+                    self.closers.pop();
+                    if let Some(start) = self.starts.pop() {
+                        self.record_span(start);
+                    }
+                    Step::Finished(Ok(frame.into_value()))
+                }
+            };
+        }
+    }
+
+    /// Starts on the value at `index`, finishing scalars straight away and opening containers
+    fn begin_value(&mut self, frames: &mut Vec<Frame<'a>>) -> Result<Step, Error> {
+        let Some(token) = self.tokens.get(self.index) else {
+            let err = self.unexpected(TokenParseError::UnexpectedEof, &[Expected::Value]);
+            return Ok(Step::Finished(Err(err)));
+        };
+        // scalar values are a single token so they are consumed here
+        if matches!(
+            token.value,
+            Token::Null | Token::False | Token::True | Token::Number(_) | Token::String(_)
+        ) {
+            self.index += 1
+        }
+        let result = match &token.value {
+            Token::Null => Ok(Value::Null),
+            Token::False => Ok(Value::Boolean(false)),
+            Token::True => Ok(Value::Boolean(true)),
+            Token::Number(raw) if self.discard => match out_of_range(raw) {
+                true => Err(self.error(token.span.start, TokenParseError::NumberOutOfRange.into())),
+                false => Ok(Value::Null),
+            },
+            Token::Number(raw) => {
+                parse_number(raw).map_err(|err| self.error(token.span.start, err.into()))
+            }
+            Token::String(_) if self.discard => Ok(Value::Null),
+            Token::String(string) => Ok(Value::String(string.value.clone())),
+            Token::LeftBracket | Token::LeftBrace
+                if self.closers.len() >= self.options.max_depth =>
+            {
+                Err(self.unexpected(TokenParseError::DepthLimitExceeded, &[]))
+            }
+            Token::LeftBracket => return self.open_array(frames),
+            Token::LeftBrace => return Ok(self.open_object(frames)),
+            _ => Err(self.unexpected(TokenParseError::ExpectedValue, &[Expected::Value])),
+        };
+        if result.is_ok() {
+            self.record_span(token.span.start);
+        }
+        Ok(Step::Finished(result))
+    }
+
+    fn open_array(&mut self, frames: &mut Vec<Frame<'a>>) -> Result<Step, Error> {
+        debug_assert!(self.tokens[self.index].value == Token::LeftBracket);
+
+        let start = self.tokens[self.index].span.start;
+        // consume the LeftBracket token
+        self.index += 1;
+        // an empty array closes straight away
+        match self.peek() {
+            Some(Token::RightBracket) => {
+                self.index += 1;
+                self.record_span(start);
+                return Ok(Step::Finished(Ok(Value::Array(Vec::new()))));
+            }
+            Some(token) if !starts_value(token) => {
+                let expected = [Expected::Value, Expected::RightBracket];
+                let err = self.unexpected(TokenParseError::ExpectedValue, &expected);
+                if !self.recover_from(err, &Token::RightBracket)? {
+                    self.record_span(start);
+                    return Ok(Step::Finished(Ok(Value::Array(Vec::new()))));
+                }
+            }
+            _ => {}
+        }
+
+        frames.push(Frame::Array(Vec::new()));
+        self.closers.push(Token::RightBracket);
+        self.starts.push(start);
+        Ok(Step::Element)
+    }
+
+    fn open_object(&mut self, frames: &mut Vec<Frame<'a>>) -> Step {
+        debug_assert!(self.tokens[self.index].value == Token::LeftBrace);
+
+        let start = self.tokens[self.index].span.start;
+        // consume the LeftBrace token
+        self.index += 1;
+        // an empty object closes straight away
+        if self.peek() == Some(&Token::RightBrace) {
+            self.index += 1;
+            self.record_span(start);
+            return Step::Finished(Ok(Value::Object(Map::new())));
+        }
+
+        frames.push(Frame::Object {
+            map: Map::new(),
+            key_spans: HashMap::new(),
+            key: None,
+            first: true,
+        });
+        self.closers.push(Token::RightBrace);
+        self.starts.push(start);
+        Step::Element
+    }
+
+    /// Readies the innermost container for its next element, reading the key of a member
+    fn begin_element(&mut self, frames: &mut [Frame<'a>]) -> Result<Step, Error> {
+        let Some(frame) = frames.last_mut() else {
+            unreachable!("asked for an element outside of any container")
+        };
+        match frame {
+            Frame::Array(array) => {
+                self.elements += 1;
+                if self
+                    .options
+                    .max_array_elements
+                    .is_some_and(|max| self.elements > max)
+                {
+                    let kind = TokenParseError::ArrayElementLimitExceeded;
+                    return Err(self.unexpected(kind, &[]));
+                }
+                self.path.push(PathSegment::Index(array.len()));
+                Ok(Step::Value)
+            }
+            Frame::Object { key, first, .. } => {
+                self.members += 1;
+                if self
+                    .options
+                    .max_object_members
+                    .is_some_and(|max| self.members > max)
+                {
+                    let kind = TokenParseError::ObjectMemberLimitExceeded;
+                    return Err(self.unexpected(kind, &[]));
+                }
+                // the first key may also be the end of the object, later ones may not
+                let expected_key: &[Expected] = match first {
+                    true => &[Expected::Key, Expected::RightBrace],
+                    false => &[Expected::Key],
+                };
+                // every member starts with a string key followed by a colon
+                match self.peek() {
+                    Some(Token::String(string)) => {
+                        *key = Some(string);
+                        self.path.push(PathSegment::Key(string.value.clone()));
+                    }
+                    _ => {
+                        let kind = TokenParseError::ExpectedProperty;
+                        return Ok(Step::Finished(Err(self.unexpected(kind, expected_key))));
+                    }
+                }
+                self.index += 1;
+                match self.peek() {
+                    Some(Token::Colon) => self.index += 1,
+                    _ => {
+                        let kind = TokenParseError::ExpectedColon;
+                        let err = self.unexpected(kind, &[Expected::Colon]);
+                        return Ok(Step::Finished(Err(err)));
+                    }
+                }
+                Ok(Step::Value)
+            }
+        }
+    }
+
+    /// Adds a finished element to its container, then moves past the `,` or closer after it
+    fn end_element(&mut self, frame: &mut Frame<'a>, result: ParseResult) -> Result<Step, Error> {
+        let closer = frame.closer();
+        match (result, &mut *frame) {
+            (Ok(_), Frame::Array(_)) if self.discard => {}
+            (Ok(value), Frame::Array(array)) => array.push(value),
+            (
+                Ok(value),
+                Frame::Object {
+                    map,
+                    key_spans,
+                    key,
+                    ..
+                },
+            ) => {
+                // a member value is only parsed once its key has been read
+                let Some(key) = key.take() else {
+                    unreachable!("finished a member without a key")
+                };
+                self.insert_member(map, key_spans, key, value)?;
+            }
+            (Err(err), _) => {
+                self.end_path();
+                return match self.recover_from(err, &closer)? {
+                    true => Ok(Step::Element),
+                    false => Ok(Step::Close),
+                };
+            }
+        }
+
+        self.end_path();
+        match self.peek() {
+            Some(Token::Comma) => {
+                let comma = self.index;
+                // consume the Comma token
+                self.index += 1;
+                if self.options.allow_trailing_commas && self.peek() == Some(&closer) {
+                    let offset = self.tokens[comma].span.start;
+                    let warning = Warning::new(self.input, offset, WarningKind::TrailingComma);
+                    self.warnings.push(warning);
+                    // consume the closing token
+                    self.index += 1;
+                    return Ok(Step::Close);
+                }
+                if let Frame::Object { first, .. } = frame {
+                    *first = false;
+                }
+                Ok(Step::Element)
+            }
+            Some(token) if *token == closer => {
+                // consume the closing token
+                self.index += 1;
+                Ok(Step::Close)
+            }
+            _ => {
+                let expected = match frame {
+                    Frame::Array(_) => [Expected::Comma, Expected::RightBracket],
+                    Frame::Object { .. } => [Expected::Comma, Expected::RightBrace],
+                };
+                let err = self.unexpected(TokenParseError::ExpectedComma, &expected);
+                match self.recover_from(err, &closer)? {
+                    true => Ok(Step::Element),
+                    false => Ok(Step::Close),
+                }
+            }
+        }
+    }
+
+    /// Adds a member to the object, settling a repeated key by `ParseOptions::duplicate_keys`
+    fn insert_member(
+        &mut self,
+        map: &mut Map<Value>,
+        key_spans: &mut HashMap<String, Span>,
+        key: &StringToken,
+        value: Value,
+    ) -> Result<(), Error> {
+        // without a Value to build, only keys that are errors need remembering
+        if self.discard && self.options.duplicate_keys != DuplicateKeyPolicy::Error {
+            return Ok(());
+        }
+        let name = match self.options.lowercase_keys {
+            true => key.value.to_lowercase(),
+            false => key.value.clone(),
+        };
+        let duplicate = match self.options.duplicate_keys {
+            DuplicateKeyPolicy::LastWins => map.insert(name, value).is_some(),
+            DuplicateKeyPolicy::FirstWins => {
+                let mut inserted = false;
+                map.entry(name).or_insert_with(|| {
+                    inserted = true;
+                    value
+                });
+                !inserted
+            }
+            DuplicateKeyPolicy::Error => match key_spans.get(&name) {
+                Some(first) => {
+                    let (first_line, first_column) = line_column(self.input, first.start);
+                    let kind = TokenParseError::DuplicateKey {
+                        key: key.value.clone(),
+                        first: *first,
+                        first_line,
+                        first_column,
+                    };
+                    let err = self.error(key.span.start, kind.into());
+                    // the object is still well formed, so a recovering parse keeps the first member
+                    if !self.recover {
+                        return Err(err);
+                    }
+                    self.errors.push(err);
+                    false
+                }
+                None => {
+                    key_spans.insert(name.clone(), key.span);
+                    if !self.discard {
+                        map.insert(name, value);
+                    }
+                    false
+                }
+            },
+        };
+        if duplicate {
+            let kind = WarningKind::DuplicateKey(key.value.clone());
+            self.warnings
+                .push(Warning::new(self.input, key.span.start, kind));
+        }
+        Ok(())
+    }
+
+    /// Notes that the value at the current path ran from `start` to the end of the last token
+    fn record_span(&mut self, start: usize) {
+        let Some(spans) = &mut self.spans else {
+            return;
+        };
+        let end = self
+            .index
+            .checked_sub(1)
+            .and_then(|last| self.tokens.get(last));
+        let end = end.map_or(start, |token| token.span.end);
+        spans.insert(pointer(&self.path), Span::new(start, end));
+    }
+
+    /// Leaves the element that just finished, back to the path of its container
+    fn end_path(&mut self) {
+        self.path.truncate(self.closers.len().saturating_sub(1));
+    }
+
+    /// Records the error and skips ahead to where the container can carry on, returning
+    /// whether the container has more elements. Hands the error back when not recovering.
+    fn recover_from(&mut self, err: Error, closer: &Token) -> Result<bool, Error> {
+        // size limits and cancelling stop everything, carrying on would defeat their point
+        let fatal = matches!(
+            err.code,
+            ErrorCode::ParseError(
+                TokenParseError::ArrayElementLimitExceeded
+                    | TokenParseError::ObjectMemberLimitExceeded
+                    | TokenParseError::Cancelled
+            )
+        );
+        if !self.recover || fatal {
+            return Err(err);
+        }
+        // errors at the same spot share a cause, such as every container hitting the end
+        let missing_comma = err.code == TokenParseError::ExpectedComma.into();
+        if self.errors.last().map(|last| last.offset) != Some(err.offset) {
+            self.errors.push(err);
+        }
+
+        // a value right where a comma should be is most likely a missing comma
+        if missing_comma
+            && matches!(self.peek(), Some(token) if starts_value(token))
+            && matches!(self.previous(), Some(token) if ends_value(token))
+        {
+            return Ok(true);
+        }
+
+        let mut depth = 0;
+        while let Some(token) = self.peek() {
+            match token {
+                Token::LeftBrace | Token::LeftBracket => depth += 1,
+                Token::RightBrace | Token::RightBracket if depth > 0 => depth -= 1,
+                Token::Comma if depth == 0 => {
+                    self.index += 1;
+                    return Ok(true);
+                }
+                token if depth == 0 && token == closer => {
+                    self.index += 1;
+                    return Ok(false);
+                }
+                // an enclosing container's closer ends this one as well
+                token if depth == 0 && self.closers.contains(token) => return Ok(false),
+                _ => {}
+            }
+            self.index += 1;
+        }
+        Ok(false)
+    }
+
+    fn peek(&self) -> Option<&'a Token> {
+        self.tokens.get(self.index).map(|token| &token.value)
+    }
+
+    fn previous(&self) -> Option<&'a Token> {
+        let index = self.index.checked_sub(1)?;
+        self.tokens.get(index).map(|token| &token.value)
+    }
+
+    /// Builds the error for the current token, running out of tokens is always `UnexpectedEof`
+    pub fn unexpected(&self, kind: TokenParseError, expected: &[Expected]) -> Error {
+        let found = self.tokens.get(self.index);
+        let (kind, offset) = match found {
+            Some(token) => (kind, token.span.start),
+            None => (TokenParseError::UnexpectedEof, self.input.len()),
+        };
+        Error {
+            expected: expected.to_vec(),
+            found: found.map(|token| token.value.clone()),
+            ..self.error(offset, kind.into())
+        }
+    }
+
+    /// Builds an error at `offset` inside the value currently being parsed
+    fn error(&self, offset: usize, code: ErrorCode) -> Error {
+        Error {
+            path: Path(self.path.clone()),
+            ..Error::new(self.input, offset, code)
+        }
+    }
+}
+
+pub(crate) fn parse_number(raw: &str) -> Result<Value, TokenParseError> {
+    // the tokenizer already checked the grammar, this is the only place floats get parsed
+    let number: f64 = raw.parse().map_err(TokenParseError::ParseNumberError)?;
+    // a literal such as `1e400` would become infinity, which nothing can serialize back
+    match number.is_finite() {
+        true => Ok(Value::Number(number)),
+        false => Err(TokenParseError::NumberOutOfRange),
+    }
+}
+
+/// Whether `parse_number` would reject `raw` as too large, read from its digits so validation
+/// doesn't convert every number, only those right at the edge of the range
+fn out_of_range(raw: &str) -> bool {
+    let (mantissa, exponent) = match raw.split_once(['e', 'E']) {
+        Some((mantissa, exponent)) => (mantissa, exponent),
+        None => (raw, "0"),
+    };
+    let mantissa = mantissa.trim_start_matches('-');
+    let (integer, fraction) = mantissa.split_once('.').unwrap_or((mantissa, ""));
+    // the power of ten of the leading non-zero digit
+    let leading = match integer.trim_start_matches('0') {
+        "" => match fraction.find(|c| c != '0') {
+            Some(at) => -(at as i64) - 1,
+            // zero is in range whatever its exponent
+            None => return false,
+        },
+        significant => significant.len() as i64 - 1,
+    };
+    let negative = exponent.starts_with('-');
+    let exponent = exponent
+        .trim_start_matches(['+', '-'])
+        .bytes()
+        .fold(0i64, |acc, b| {
+            acc.saturating_mul(10).saturating_add(i64::from(b - b'0'))
+        });
+    let exponent = if negative { -exponent } else { exponent };
+    // f64::MAX is about 1.8e308, so only a leading digit at 10^308 needs the full conversion
+    match leading.saturating_add(exponent) {
+        308 => parse_number(raw).is_err(),
+        magnitude => magnitude > 308,
+    }
+}
+
+/// Whether the token can be the first token of a value
+fn starts_value(token: &Token) -> bool {
+    !matches!(
+        token,
+        Token::RightBrace | Token::RightBracket | Token::Comma | Token::Colon
+    )
+}
+
+/// Whether the token can be the last token of a value
+fn ends_value(token: &Token) -> bool {
+    !matches!(
+        token,
+        Token::LeftBrace | Token::LeftBracket | Token::Comma | Token::Colon
+    )
+}
+
+/// Possible errors from turning tokens into a Value
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum TokenParseError {
+    /// a number that couldn't be converted to a float
+    ParseNumberError(ParseFloatError),
+    /// a number too large in magnitude for a 64 bit float, such as `1e400`
+    NumberOutOfRange,
+    /// a token that can't start a value
+    ExpectedValue,
+    /// array elements and object members must be separated by commas
+    ExpectedComma,
+    /// object members must start with a string key
+    ExpectedProperty,
+    /// object keys must be followed by a colon
+    ExpectedColon,
+    /// the tokens ran out in the middle of a value
+    UnexpectedEof,
+    /// there were tokens left over after the value
+    TrailingTokens,
+    /// arrays and objects were nested deeper than `ParseOptions::max_depth`
+    DepthLimitExceeded,
+    /// the document has more array elements than `ParseOptions::max_array_elements`
+    ArrayElementLimitExceeded,
+    /// the document has more object members than `ParseOptions::max_object_members`
+    ObjectMemberLimitExceeded,
+    /// an object repeated a key under `DuplicateKeyPolicy::Error`, the error points at the
+    /// repeat and `first` is where the key was first seen
+    DuplicateKey {
+        key: String,
+        first: Span,
+        first_line: usize,
+        first_column: usize,
+    },
+    /// `ParseOptions::cancel` was set before the value was fully parsed
+    Cancelled,
+    /// the document is a scalar where `ParseOptions::root` asks for an object or array
+    ExpectedContainer,
+    /// the document isn't the array `iter_array` reads the elements of
+    ExpectedArray,
+}
+
+impl fmt::Display for TokenParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenParseError::ParseNumberError(err) => write!(f, "invalid number: {err}"),
+            TokenParseError::NumberOutOfRange => write!(f, "number out of range"),
+            TokenParseError::ExpectedValue => write!(f, "expected a value"),
+            TokenParseError::ExpectedComma => write!(f, "expected a comma"),
+            TokenParseError::ExpectedProperty => write!(f, "expected a string key"),
+            TokenParseError::ExpectedColon => write!(f, "expected a colon"),
+            TokenParseError::UnexpectedEof => write!(f, "unexpected end of input"),
+            TokenParseError::TrailingTokens => write!(f, "unexpected tokens after the value"),
+            TokenParseError::DepthLimitExceeded => write!(f, "nesting depth limit exceeded"),
+            TokenParseError::ArrayElementLimitExceeded => {
+                write!(f, "array element limit exceeded")
+            }
+            TokenParseError::ObjectMemberLimitExceeded => {
+                write!(f, "object member limit exceeded")
+            }
+            TokenParseError::DuplicateKey {
+                key,
+                first_line,
+                first_column,
+                ..
+            } => write!(
+                f,
+                "duplicate key {key:?}, first seen at line {first_line}, column {first_column}"
+            ),
+            TokenParseError::Cancelled => write!(f, "cancelled"),
+            TokenParseError::ExpectedContainer => write!(f, "expected an object or array"),
+            TokenParseError::ExpectedArray => write!(f, "expected an array"),
+        }
+    }
+}
+
+impl std::error::Error for TokenParseError {}
+
+/// Something the parser would have accepted where an error occurred
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub enum Expected {
+    /// the start of any value
+    Value,
+    /// a string object key
+    Key,
+    /// `,`
+    Comma,
+    /// `:`
+    Colon,
+    /// `]`
+    RightBracket,
+    /// `}`
+    RightBrace,
+    /// nothing more, the document is complete
+    Eof,
+}
+
+impl fmt::Display for Expected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expected::Value => write!(f, "a value"),
+            Expected::Key => write!(f, "a string key"),
+            Expected::Comma => write!(f, "`,`"),
+            Expected::Colon => write!(f, "`:`"),
+            Expected::RightBracket => write!(f, "`]`"),
+            Expected::RightBrace => write!(f, "`}}`"),
+            Expected::Eof => write!(f, "end of input"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Expected, Parser, TokenParseError};
+    use crate::span::Span;
+    use crate::tokenize::{tokenize_spanned, Token};
+    use crate::{DuplicateKeyPolicy, Error, ErrorCode, Map, ParseOptions, Value};
+    use std::collections::HashMap;
+    use std::sync::atomic::AtomicBool;
+    use std::sync::Arc;
+
+    fn parse(input: &str) -> Result<Value, Error> {
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        Parser::new(input, &tokens, &ParseOptions::default()).parse_value()
+    }
+
+    fn parse_recovering(input: &str) -> (Value, Vec<Error>) {
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions::default();
+        let mut parser = Parser::new(input, &tokens, &options).recovering();
+        let value = parser.parse_value().unwrap();
+        (value, parser.errors)
+    }
+
+    #[test]
+    fn parses_null() {
+        let input = "null";
+        let expected = Value::Null;
+
+        let actual = parse(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_number() {
+        let input = "-12.5e1";
+        let expected = Value::Number(-125.0);
+
+        let actual = parse(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_nested_array() {
+        let input = "[true, []]";
+        let expected = Value::Array(vec![Value::Boolean(true), Value::Array(vec![])]);
+
+        let actual = parse(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn parses_object() {
+        let input = r#"{"key": null}"#;
+        let expected = Value::Object(Map::from([(String::from("key"), Value::Null)]));
+
+        let actual = parse(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn trailing_comma_in_array() {
+        let input = "[null,]";
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedValue);
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.expected, [Expected::Value]);
+        assert_eq!(actual.found, Some(Token::RightBracket));
+    }
+
+    #[test]
+    fn missing_colon() {
+        let input = "{\n  \"key\" null\n}";
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedColon);
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!((actual.offset, actual.line, actual.column), (10, 2, 9));
+        assert_eq!(actual.found, Some(Token::Null));
+    }
+
+    #[test]
+    fn expected_comma_or_brace() {
+        let input = r#"{"a": 1 ]"#;
+        let expected = "expected `,` or `}` at line 1, column 9, found `]`";
+
+        let actual = parse(input).unwrap_err().to_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unclosed_array() {
+        let input = "[null";
+        let expected = ErrorCode::ParseError(TokenParseError::UnexpectedEof);
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.expected, [Expected::Comma, Expected::RightBracket]);
+        assert_eq!(actual.found, None);
+        assert_eq!(actual.offset, 5);
+    }
+
+    #[test]
+    fn recovers_from_every_error() {
+        let input = "[1 2, }, {\"a\" 3, \"b\": 4}, 5]";
+        let expected = Value::Array(vec![
+            Value::Number(1.0),
+            Value::Number(2.0),
+            Value::Object(Map::from([(String::from("b"), Value::Number(4.0))])),
+            Value::Number(5.0),
+        ]);
+
+        let (actual, errors) = parse_recovering(input);
+
+        assert_eq!(actual, expected);
+        let kinds: Vec<_> = errors.iter().map(|err| err.code.clone()).collect();
+        assert_eq!(
+            kinds,
+            [
+                ErrorCode::ParseError(TokenParseError::ExpectedComma),
+                ErrorCode::ParseError(TokenParseError::ExpectedValue),
+                ErrorCode::ParseError(TokenParseError::ExpectedColon),
+            ]
+        );
+        let offsets: Vec<_> = errors.iter().map(|err| err.offset).collect();
+        assert_eq!(offsets, [3, 6, 14]);
+    }
+
+    #[test]
+    fn recovers_from_truncation_once() {
+        let input = "{\"a\": [1, {\"b\": 2";
+        let expected = Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![
+                Value::Number(1.0),
+                Value::Object(Map::from([(String::from("b"), Value::Number(2.0))])),
+            ]),
+        )]));
+
+        let (actual, errors) = parse_recovering(input);
+
+        assert_eq!(actual, expected);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].offset, input.len());
+    }
+
+    #[test]
+    fn wrong_closer_ends_inner_container() {
+        let input = "{\"a\": [1}";
+        let expected = Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![Value::Number(1.0)]),
+        )]));
+
+        let (actual, errors) = parse_recovering(input);
+
+        assert_eq!(actual, expected);
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].found, Some(Token::RightBrace));
+    }
+
+    #[test]
+    fn depth_limit() {
+        let input = "{\"a\": [[1]]}";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::DepthLimitExceeded);
+
+        let actual = Parser::new(input, &tokens, &options)
+            .parse_value()
+            .unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, 7);
+    }
+
+    #[test]
+    fn default_depth_limit() {
+        let input = "[".repeat(100_000);
+        let tokens = tokenize_spanned(&input, &ParseOptions::default()).unwrap();
+        let expected = ErrorCode::ParseError(TokenParseError::DepthLimitExceeded);
+
+        let actual = Parser::new(&input, &tokens, &ParseOptions::default())
+            .parse_value()
+            .unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, 128);
+    }
+
+    #[test]
+    fn unlimited_depth_without_recursion() {
+        let input = format!("{}1", "[".repeat(100_000));
+        let tokens = tokenize_spanned(&input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            max_depth: usize::MAX,
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::UnexpectedEof);
+
+        let actual = Parser::new(&input, &tokens, &options)
+            .parse_value()
+            .unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, input.len());
+    }
+
+    #[test]
+    fn recovers_past_too_deep_value() {
+        let input = "[[[1]], 2]";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            max_depth: 2,
+            ..ParseOptions::default()
+        };
+        let expected = Value::Array(vec![Value::Array(vec![]), Value::Number(2.0)]);
+
+        let mut parser = Parser::new(input, &tokens, &options).recovering();
+        let actual = parser.parse_value().unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(parser.errors.len(), 1);
+    }
+
+    #[test]
+    fn array_element_limit_counts_whole_document() {
+        let input = "[[1, 2], [3, 4]]";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            max_array_elements: Some(5),
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::ArrayElementLimitExceeded);
+
+        let actual = Parser::new(input, &tokens, &options)
+            .parse_value()
+            .unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!(actual.offset, 13);
+    }
+
+    #[test]
+    fn object_member_limit_stops_recovery() {
+        let input = "[{\"a\": 1, \"b\": 2}, {\"c\" 3}]";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            max_object_members: Some(2),
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::ObjectMemberLimitExceeded);
+
+        let mut parser = Parser::new(input, &tokens, &options).recovering();
+        let actual = parser.parse_value().unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert!(parser.errors.is_empty());
+    }
+
+    fn parse_keys(input: &str, duplicate_keys: DuplicateKeyPolicy) -> Result<Value, Error> {
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            duplicate_keys,
+            ..ParseOptions::default()
+        };
+        Parser::new(input, &tokens, &options).parse_value()
+    }
+
+    #[test]
+    fn duplicate_key_last_wins() {
+        let input = "{\"a\": 1, \"a\": 2}";
+        let expected = Value::Object(Map::from([(String::from("a"), Value::Number(2.0))]));
+
+        let actual = parse_keys(input, DuplicateKeyPolicy::LastWins).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn lowercases_keys() {
+        let input = r#"{"Content-Type": "a", "ACCEPT": "b", "accept": "c"}"#;
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            lowercase_keys: true,
+            duplicate_keys: DuplicateKeyPolicy::FirstWins,
+            ..ParseOptions::default()
+        };
+        let expected = Value::Object(Map::from([
+            (
+                String::from("content-type"),
+                Value::String(String::from("a")),
+            ),
+            (String::from("accept"), Value::String(String::from("b"))),
+        ]));
+
+        let actual = Parser::new(input, &tokens, &options).parse_value().unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn duplicate_key_first_wins() {
+        let input = "{\"a\": 1, \"a\": 2}";
+        let expected = Value::Object(Map::from([(String::from("a"), Value::Number(1.0))]));
+
+        let actual = parse_keys(input, DuplicateKeyPolicy::FirstWins).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn duplicate_key_error() {
+        let input = "{\n  \"a\": 1,\n  \"a\": 2\n}";
+        let expected = ErrorCode::ParseError(TokenParseError::DuplicateKey {
+            key: String::from("a"),
+            first: Span::new(4, 7),
+            first_line: 2,
+            first_column: 3,
+        });
+
+        let actual = parse_keys(input, DuplicateKeyPolicy::Error).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert_eq!((actual.line, actual.column), (3, 3));
+        assert_eq!(
+            actual.to_string(),
+            "duplicate key \"a\", first seen at line 2, column 3 at line 3, column 3 in $.a"
+        );
+    }
+
+    #[test]
+    fn duplicate_keys_in_separate_objects() {
+        let input = "[{\"a\": 1}, {\"a\": 2}]";
+
+        let actual = parse_keys(input, DuplicateKeyPolicy::Error);
+
+        assert!(actual.is_ok());
+    }
+
+    #[test]
+    fn cancelled_mid_parse() {
+        let input = format!("[{}0]", "0, ".repeat(10_000));
+        let tokens = tokenize_spanned(&input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..ParseOptions::default()
+        };
+        let expected = ErrorCode::ParseError(TokenParseError::Cancelled);
+
+        let mut parser = Parser::new(&input, &tokens, &options).recovering();
+        let actual = parser.parse_value().unwrap_err();
+
+        assert_eq!(actual.code, expected);
+        assert!(parser.errors.is_empty());
+    }
+
+    #[test]
+    fn error_path() {
+        let input = "{\"users\": [{}, {}, {}, {\"address\": [1 2]}]}";
+        let expected = "$.users[3].address";
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(actual.path.to_string(), expected);
+    }
+
+    #[test]
+    fn recovered_error_paths() {
+        let input = "{\"a\": [1, ], \"b\" 2, \"c\": {\"d\": ]}}";
+        let expected = ["$.a[1]", "$.b", "$.c.d"];
+
+        let (_, errors) = parse_recovering(input);
+
+        let actual: Vec<_> = errors.iter().map(|err| err.path.to_string()).collect();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn records_spans() {
+        let input = "{\"a\": [1, {}], \"b/c\": \"x\"}";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions::default();
+        let expected = HashMap::from([
+            (String::new(), Span::new(0, 26)),
+            (String::from("/a"), Span::new(6, 13)),
+            (String::from("/a/0"), Span::new(7, 8)),
+            (String::from("/a/1"), Span::new(10, 12)),
+            (String::from("/b~1c"), Span::new(22, 25)),
+        ]);
+
+        let mut parser = Parser::new(input, &tokens, &options).with_spans();
+        parser.parse_value().unwrap();
+
+        assert_eq!(parser.spans, Some(expected));
+    }
+
+    #[test]
+    fn trailing_commas() {
+        let input = "{\"a\": [1, 2,],}";
+        let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+        let options = ParseOptions {
+            allow_trailing_commas: true,
+            ..ParseOptions::default()
+        };
+        let expected = Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+        )]));
+
+        let mut parser = Parser::new(input, &tokens, &options);
+        let actual = parser.parse_value().unwrap();
+
+        assert_eq!(actual, expected);
+        let offsets: Vec<_> = parser.warnings.iter().map(|w| w.offset).collect();
+        assert_eq!(offsets, [11, 13]);
+    }
+
+    #[test]
+    fn rejects_out_of_range_numbers() {
+        let expected = ErrorCode::ParseError(TokenParseError::NumberOutOfRange);
+
+        let positive = parse("[1e400]").unwrap_err();
+        let negative = parse("-1e400").unwrap_err();
+        let tiny = parse("1e-400").unwrap();
+
+        assert_eq!(positive.code, expected);
+        assert_eq!(positive.offset, 1);
+        assert_eq!(negative.code, expected);
+        assert_eq!(tiny, Value::Number(0.0));
+    }
+
+    #[test]
+    fn validating_rejects_out_of_range_numbers() {
+        let cases = [
+            ("[1e400]", false),
+            ("-1e400", false),
+            ("1e-400", true),
+            ("0e99999999999999999999", true),
+            ("0.001e311", true),
+            ("0.01e311", false),
+            ("1.7976931348623157e308", true),
+            ("1.8e308", false),
+            ("17976931348623157e292", true),
+            ("234567891e300", false),
+        ];
+
+        for (input, expected) in cases {
+            let tokens = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+            let options = ParseOptions::default();
+
+            let actual = Parser::new(input, &tokens, &options)
+                .validating()
+                .parse_value();
+
+            assert_eq!(actual.is_ok(), expected, "{input}");
+            assert_eq!(parse(input).is_ok(), expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn trailing_commas_need_allowing() {
+        let input = "[1,]";
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedValue);
+
+        let actual = parse(input).unwrap_err();
+
+        assert_eq!(actual.code, expected);
+    }
+}
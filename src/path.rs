@@ -0,0 +1,120 @@
+use crate::pointer::JsonPointer;
+use crate::writer::write_string;
+use std::fmt;
+
+/// Where a value sits inside a document, written JSONPath style such as `$.users[3].address`
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub struct Path(pub Vec<PathSegment>);
+
+/// One step from a container down to one of its values
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum PathSegment {
+    /// the member of an object with this key
+    Key(String),
+    /// the element of an array at this position
+    Index(usize),
+}
+
+impl Path {
+    /// Whether the path points at the whole document
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The same location as an RFC 6901 JSON Pointer such as `/users/3/address`
+    pub fn to_pointer(&self) -> String {
+        pointer(&self.0)
+    }
+}
+
+/// Writes segments as a JSON Pointer, the root being the empty string
+pub(crate) fn pointer(segments: &[PathSegment]) -> String {
+    JsonPointer::from(segments).to_string()
+}
+
+impl fmt::Display for Path {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "$")?;
+        for segment in &self.0 {
+            write!(f, "{segment}")?;
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for PathSegment {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PathSegment::Key(key) if is_identifier(key) => write!(f, ".{key}"),
+            // anything else is quoted as a json string, e.g. `$["first name"]`
+            PathSegment::Key(key) => {
+                let mut quoted = Vec::new();
+                write_string(&mut quoted, key).map_err(|_| fmt::Error)?;
+                write!(f, "[{}]", String::from_utf8_lossy(&quoted))
+            }
+            PathSegment::Index(index) => write!(f, "[{index}]"),
+        }
+    }
+}
+
+/// Whether the key can be written after a dot without quoting
+fn is_identifier(key: &str) -> bool {
+    let mut chars = key.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Path, PathSegment};
+
+    #[test]
+    fn root() {
+        let input = Path::default();
+
+        let actual = input.to_string();
+
+        assert_eq!(actual, "$");
+    }
+
+    #[test]
+    fn keys_and_indexes() {
+        let input = Path(vec![
+            PathSegment::Key(String::from("users")),
+            PathSegment::Index(3),
+            PathSegment::Key(String::from("address")),
+        ]);
+
+        let actual = input.to_string();
+
+        assert_eq!(actual, "$.users[3].address");
+    }
+
+    #[test]
+    fn quoted_keys() {
+        let input = Path(vec![
+            PathSegment::Key(String::from("first name")),
+            PathSegment::Key(String::new()),
+            PathSegment::Key(String::from("2nd")),
+        ]);
+
+        let actual = input.to_string();
+
+        assert_eq!(actual, "$[\"first name\"][\"\"][\"2nd\"]");
+    }
+
+    #[test]
+    fn pointer() {
+        let input = Path(vec![
+            PathSegment::Key(String::from("users")),
+            PathSegment::Index(3),
+            PathSegment::Key(String::from("a/b~c")),
+        ]);
+
+        let actual = input.to_pointer();
+
+        assert_eq!(actual, "/users/3/a~1b~0c");
+    }
+}
@@ -0,0 +1,540 @@
+/// A regular expression for the `pattern` keyword of JSON Schema, which is searched for
+/// anywhere in a string unless anchored with `^` and `$`. Covers the common ECMA 262 subset:
+/// literals, `.`, classes like `[a-z]` and `[^,]`, the `\d \w \s` escapes and their negations,
+/// groups with `|`, and the `* + ? {n} {n,} {n,m}` quantifiers. Matching takes time in
+/// proportion to the length of the text times the size of the pattern, never backtracking.
+#[derive(Debug, Clone)]
+pub(crate) struct Pattern {
+    program: Vec<Inst>,
+}
+
+#[derive(Debug, Clone)]
+enum Node {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    Group(Vec<Vec<Node>>),
+    Repeat {
+        node: Box<Node>,
+        min: usize,
+        max: Option<usize>,
+    },
+}
+
+/// A set of chars such as `[a-z_]`, `\d` or `[^\s,]`
+#[derive(Debug, Clone, Default)]
+struct Class {
+    negated: bool,
+    ranges: Vec<(char, char)>,
+    classes: Vec<Class>,
+}
+
+impl Class {
+    fn of(ranges: &[(char, char)], negated: bool) -> Self {
+        Self {
+            negated,
+            ranges: ranges.to_vec(),
+            classes: Vec::new(),
+        }
+    }
+
+    fn matches(&self, c: char) -> bool {
+        let within = self
+            .ranges
+            .iter()
+            .any(|&(low, high)| (low..=high).contains(&c))
+            || self.classes.iter().any(|class| class.matches(c));
+        within != self.negated
+    }
+}
+
+impl Pattern {
+    /// Reads a pattern, `None` when it is malformed, uses something not covered or is too
+    /// large once its counted repeats are written out
+    pub(crate) fn new(pattern: &str) -> Option<Self> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternatives()?;
+        if parser.pos != parser.chars.len() {
+            return None;
+        }
+        let mut compiler = Compiler::default();
+        compiler.alternatives(&alternatives)?;
+        compiler.push(Inst::Match)?;
+        Some(Self {
+            program: compiler.program,
+        })
+    }
+
+    /// Whether the pattern matches anywhere in `text`
+    pub(crate) fn is_match(&self, text: &str) -> bool {
+        let mut matcher = Matcher {
+            program: &self.program,
+            chars: text.chars().collect(),
+            stack: Vec::new(),
+        };
+        matcher.is_match()
+    }
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let found = self.peek() == Some(c);
+        if found {
+            self.pos += 1;
+        }
+        found
+    }
+
+    /// Reads sequences separated by `|` up to a `)` or the end
+    fn alternatives(&mut self) -> Option<Vec<Vec<Node>>> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Some(alternatives)
+    }
+
+    fn sequence(&mut self) -> Option<Vec<Node>> {
+        let mut nodes = Vec::new();
+        while let Some(c) = self.peek() {
+            if matches!(c, '|' | ')') {
+                break;
+            }
+            let atom = self.atom()?;
+            nodes.push(self.quantified(atom)?);
+        }
+        Some(nodes)
+    }
+
+    fn atom(&mut self) -> Option<Node> {
+        Some(match self.next()? {
+            '(' => {
+                // non-capturing groups match the same, nothing is captured anyway
+                if self.eat('?') && !self.eat(':') {
+                    return None;
+                }
+                let alternatives = self.alternatives()?;
+                self.eat(')').then_some(Node::Group(alternatives))?
+            }
+            '[' => Node::Class(self.class()?),
+            '.' => Node::Any,
+            '^' => Node::Start,
+            '$' => Node::End,
+            '\\' => match self.escape()? {
+                Escaped::Char(c) => Node::Char(c),
+                Escaped::Class(class) => Node::Class(class),
+            },
+            '*' | '+' | '?' | '{' | ']' | '}' => return None,
+            c => Node::Char(c),
+        })
+    }
+
+    /// Reads any quantifier after `atom`, a trailing `?` making it lazy changes nothing when
+    /// only asking whether there is a match
+    fn quantified(&mut self, atom: Node) -> Option<Node> {
+        let (min, max) = if self.eat('*') {
+            (0, None)
+        } else if self.eat('+') {
+            (1, None)
+        } else if self.eat('?') {
+            (0, Some(1))
+        } else if self.eat('{') {
+            let min = self.number()?;
+            let max = match self.eat(',') {
+                true if self.peek() == Some('}') => None,
+                true => Some(self.number()?),
+                false => Some(min),
+            };
+            let too_many = min.max(max.unwrap_or(0)) > MAX_PROGRAM_LEN;
+            if !self.eat('}') || max.is_some_and(|max| max < min) || too_many {
+                return None;
+            }
+            (min, max)
+        } else {
+            return Some(atom);
+        };
+        self.eat('?');
+        if matches!(atom, Node::Start | Node::End) {
+            return None;
+        }
+        Some(Node::Repeat {
+            node: Box::new(atom),
+            min,
+            max,
+        })
+    }
+
+    fn number(&mut self) -> Option<usize> {
+        let start = self.pos;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.chars[start..self.pos]
+            .iter()
+            .collect::<String>()
+            .parse()
+            .ok()
+    }
+
+    /// Reads a class after its `[` up to and including the `]`
+    fn class(&mut self) -> Option<Class> {
+        let mut class = Class {
+            negated: self.eat('^'),
+            ..Class::default()
+        };
+        // a `]` straight away is a literal one
+        let mut first = true;
+        loop {
+            let low = match self.next()? {
+                ']' if !first => return Some(class),
+                '\\' => match self.escape()? {
+                    Escaped::Char(c) => c,
+                    Escaped::Class(inner) => {
+                        class.classes.push(inner);
+                        first = false;
+                        continue;
+                    }
+                },
+                c => c,
+            };
+            first = false;
+            let is_range = self.peek() == Some('-') && self.chars.get(self.pos + 1) != Some(&']');
+            let high = match is_range {
+                true => {
+                    self.pos += 1;
+                    match self.next()? {
+                        '\\' => match self.escape()? {
+                            Escaped::Char(c) => c,
+                            Escaped::Class(_) => return None,
+                        },
+                        c => c,
+                    }
+                }
+                false => low,
+            };
+            if high < low {
+                return None;
+            }
+            class.ranges.push((low, high));
+        }
+    }
+
+    /// Reads what follows a backslash
+    fn escape(&mut self) -> Option<Escaped> {
+        const DIGITS: &[(char, char)] = &[('0', '9')];
+        const WORD: &[(char, char)] = &[('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')];
+        const SPACE: &[(char, char)] = &[(' ', ' '), ('\t', '\r')];
+        Some(match self.next()? {
+            'd' => Escaped::Class(Class::of(DIGITS, false)),
+            'D' => Escaped::Class(Class::of(DIGITS, true)),
+            'w' => Escaped::Class(Class::of(WORD, false)),
+            'W' => Escaped::Class(Class::of(WORD, true)),
+            's' => Escaped::Class(Class::of(SPACE, false)),
+            'S' => Escaped::Class(Class::of(SPACE, true)),
+            'n' => Escaped::Char('\n'),
+            't' => Escaped::Char('\t'),
+            'r' => Escaped::Char('\r'),
+            c if c.is_ascii_alphanumeric() => return None,
+            c => Escaped::Char(c),
+        })
+    }
+}
+
+enum Escaped {
+    Char(char),
+    Class(Class),
+}
+
+/// The most instructions a pattern compiles to, counted repeats being written out in full, so
+/// something like `a{1000000}` is turned away rather than built. Also the most times counted
+/// repeats are written out in all, as repeating an empty group adds no instructions.
+const MAX_PROGRAM_LEN: usize = 10_000;
+
+/// One step of a compiled pattern. Matching runs a thread for every way the pattern could be
+/// part way through the text, all advancing a char at a time, so neither time nor stack grows
+/// with anything worse than the length of the text.
+#[derive(Debug, Clone)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Class),
+    Start,
+    End,
+    /// carry on at both instructions
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+#[derive(Default)]
+struct Compiler {
+    program: Vec<Inst>,
+    /// how many times counted repeats have been written out so far
+    repeated: usize,
+}
+
+impl Compiler {
+    fn push(&mut self, inst: Inst) -> Option<usize> {
+        if self.program.len() >= MAX_PROGRAM_LEN {
+            return None;
+        }
+        self.program.push(inst);
+        Some(self.program.len() - 1)
+    }
+
+    /// Counts writing out one more copy of a repeated node, `None` once there have been too many
+    fn repeat_once(&mut self) -> Option<()> {
+        self.repeated += 1;
+        (self.repeated <= MAX_PROGRAM_LEN).then_some(())
+    }
+
+    /// Points the split at `at` to the instruction after it and to wherever the program has
+    /// got to
+    fn patch_split(&mut self, at: usize) {
+        self.program[at] = Inst::Split(at + 1, self.program.len());
+    }
+
+    fn alternatives(&mut self, alternatives: &[Vec<Node>]) -> Option<()> {
+        let mut jumps = Vec::new();
+        for (i, nodes) in alternatives.iter().enumerate() {
+            if i + 1 == alternatives.len() {
+                self.sequence(nodes)?;
+                break;
+            }
+            let split = self.push(Inst::Split(0, 0))?;
+            self.sequence(nodes)?;
+            jumps.push(self.push(Inst::Jump(0))?);
+            self.patch_split(split);
+        }
+        let end = self.program.len();
+        for jump in jumps {
+            self.program[jump] = Inst::Jump(end);
+        }
+        Some(())
+    }
+
+    fn sequence(&mut self, nodes: &[Node]) -> Option<()> {
+        nodes.iter().try_for_each(|node| self.node(node))
+    }
+
+    fn node(&mut self, node: &Node) -> Option<()> {
+        match node {
+            Node::Char(c) => self.push(Inst::Char(*c)).map(drop),
+            Node::Any => self.push(Inst::Any).map(drop),
+            Node::Class(class) => self.push(Inst::Class(class.clone())).map(drop),
+            Node::Start => self.push(Inst::Start).map(drop),
+            Node::End => self.push(Inst::End).map(drop),
+            Node::Group(alternatives) => self.alternatives(alternatives),
+            Node::Repeat { node, min, max } => {
+                for _ in 0..*min {
+                    self.repeat_once()?;
+                    self.node(node)?;
+                }
+                match max {
+                    None => {
+                        let split = self.push(Inst::Split(0, 0))?;
+                        self.node(node)?;
+                        self.push(Inst::Jump(split))?;
+                        self.patch_split(split);
+                    }
+                    Some(max) => {
+                        for _ in *min..*max {
+                            self.repeat_once()?;
+                            let split = self.push(Inst::Split(0, 0))?;
+                            self.node(node)?;
+                            self.patch_split(split);
+                        }
+                    }
+                }
+                Some(())
+            }
+        }
+    }
+}
+
+/// The instructions a match has got to, each only once
+struct Threads {
+    /// the instructions waiting on the next char
+    pcs: Vec<usize>,
+    /// every instruction already followed at this position, so loops that match nothing end
+    added: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            pcs: Vec::new(),
+            added: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.pcs.clear();
+        self.added.fill(false);
+    }
+}
+
+struct Matcher<'a> {
+    program: &'a [Inst],
+    chars: Vec<char>,
+    /// instructions still to follow while adding threads, kept to save allocating each time
+    stack: Vec<usize>,
+}
+
+impl Matcher<'_> {
+    /// Adds a thread at `pc` for the text at `pos`, following jumps, splits and anchors on to
+    /// the instructions that consume a char. Returns whether that reaches the end of the
+    /// pattern.
+    fn add(&mut self, threads: &mut Threads, pc: usize, pos: usize) -> bool {
+        self.stack.push(pc);
+        while let Some(pc) = self.stack.pop() {
+            if threads.added[pc] {
+                continue;
+            }
+            threads.added[pc] = true;
+            match self.program[pc] {
+                Inst::Jump(to) => self.stack.push(to),
+                Inst::Split(first, second) => {
+                    self.stack.push(second);
+                    self.stack.push(first);
+                }
+                Inst::Start if pos == 0 => self.stack.push(pc + 1),
+                Inst::End if pos == self.chars.len() => self.stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                Inst::Match => {
+                    self.stack.clear();
+                    return true;
+                }
+                Inst::Char(_) | Inst::Any | Inst::Class(_) => threads.pcs.push(pc),
+            }
+        }
+        false
+    }
+
+    fn is_match(&mut self) -> bool {
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        for pos in 0..=self.chars.len() {
+            // a match may start anywhere, so a fresh thread joins at every position
+            if self.add(&mut current, 0, pos) {
+                return true;
+            }
+            let Some(&c) = self.chars.get(pos) else {
+                break;
+            };
+            for i in 0..current.pcs.len() {
+                let pc = current.pcs[i];
+                let consumed = match &self.program[pc] {
+                    Inst::Char(expected) => *expected == c,
+                    Inst::Any => c != '\n',
+                    Inst::Class(class) => class.matches(c),
+                    _ => false,
+                };
+                if consumed && self.add(&mut next, pc + 1, pos + 1) {
+                    return true;
+                }
+            }
+            current.clear();
+            std::mem::swap(&mut current, &mut next);
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Pattern;
+
+    #[test]
+    fn matches() {
+        let cases = [
+            ("abc", "xxabcxx", true),
+            ("^abc$", "xxabcxx", false),
+            ("^a.c$", "abc", true),
+            ("^\\d{3}-\\d{4}$", "555-1234", true),
+            ("^\\d{3}-\\d{4}$", "555-123", false),
+            ("^[a-z_][a-z0-9_]*$", "snake_case_1", true),
+            ("^[a-z_][a-z0-9_]*$", "CamelCase", false),
+            ("^[^,]+$", "a,b", false),
+            ("^(cat|dog)s?$", "dogs", true),
+            ("^(cat|dog)s?$", "bird", false),
+            ("^(ab)+$", "ababab", true),
+            ("^(ab)+$", "ababa", false),
+            ("^a{2,}b{0,1}$", "aaab", true),
+            ("^a*?b$", "aaab", true),
+            (
+                "^[\\w.+-]+@[\\w-]+\\.[\\w.]+$",
+                "me+x@mail.example.com",
+                true,
+            ),
+            ("^(a*)*$", "aaa", true),
+            ("^\\S+$", "no spaces", false),
+            ("^$", "", true),
+            ("é", "café", true),
+        ];
+
+        for (pattern, text, expected) in cases {
+            let actual = Pattern::new(pattern).unwrap().is_match(text);
+
+            assert_eq!(actual, expected, "{pattern} {text}");
+        }
+    }
+
+    #[test]
+    fn matches_long_text() {
+        let input = "a".repeat(100_000);
+        let mismatched = format!("{input}1");
+
+        let actual = Pattern::new("^[a-z]*$").unwrap();
+
+        assert!(actual.is_match(&input));
+        assert!(!actual.is_match(&mismatched));
+        assert!(Pattern::new("^(a|aa)*(b|c)+$")
+            .unwrap()
+            .is_match(&format!("{input}bc")));
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let input = [
+            "(a",
+            "a)",
+            "[a",
+            "*a",
+            "a{2,1}",
+            "[z-a]",
+            "\\p{L}",
+            "^*",
+            "(?=a)",
+            "a{1000000}",
+            "(){99999999999}",
+            "((){9999}){9999}",
+        ];
+
+        for pattern in input {
+            let actual = Pattern::new(pattern);
+
+            assert!(actual.is_none(), "{pattern}");
+        }
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error::kind_predicates;
+use crate::path::{Path, PathSegment};
+use crate::{ErrorKind, Value};
+use std::fmt;
+use std::str::FromStr;
+
+/// An RFC 6901 JSON Pointer such as `/users/3/address`, kept as its unescaped reference
+/// tokens so keys holding `/` or `~` need no care until it is written out again
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Default)]
+pub struct JsonPointer(Vec<String>);
+
+/// Why a string isn't a JSON Pointer
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PointerError {
+    /// the pointer is neither empty nor starts with `/`
+    MissingSlash,
+    /// a `~` at this byte offset isn't followed by `0` or `1`
+    InvalidEscape { offset: usize },
+}
+
+impl fmt::Display for PointerError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PointerError::MissingSlash => write!(f, "a json pointer must start with '/'"),
+            PointerError::InvalidEscape { offset } => {
+                write!(
+                    f,
+                    "invalid escape at offset {offset}, expected '~0' or '~1'"
+                )
+            }
+        }
+    }
+}
+
+impl PointerError {
+    /// The category of the error, always `Syntax` as a pointer that can't be read never will be
+    pub fn kind(&self) -> ErrorKind {
+        ErrorKind::Syntax
+    }
+}
+
+kind_predicates!(PointerError);
+
+impl std::error::Error for PointerError {}
+
+impl JsonPointer {
+    /// The pointer to the whole document, the empty string
+    pub fn root() -> Self {
+        Self::default()
+    }
+
+    /// Reads a pointer, undoing the `~1` and `~0` escapes of each token
+    pub fn parse(pointer: &str) -> Result<Self, PointerError> {
+        if pointer.is_empty() {
+            return Ok(Self::root());
+        }
+        let Some(rest) = pointer.strip_prefix('/') else {
+            return Err(PointerError::MissingSlash);
+        };
+        let mut tokens = Vec::new();
+        // the offset into `pointer` of the token being read, past its `/`
+        let mut start = 1;
+        for token in rest.split('/') {
+            let invalid = token
+                .match_indices('~')
+                .find(|&(at, _)| !matches!(token.as_bytes().get(at + 1), Some(b'0' | b'1')));
+            if let Some((at, _)) = invalid {
+                return Err(PointerError::InvalidEscape { offset: start + at });
+            }
+            tokens.push(unescape(token));
+            start += token.len() + 1;
+        }
+        Ok(Self(tokens))
+    }
+
+    /// Whether the pointer is to the whole document
+    pub fn is_root(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    /// The unescaped reference tokens, outermost first
+    pub fn tokens(&self) -> &[String] {
+        &self.0
+    }
+
+    /// Points one level further down, at the member with this key or the element at this
+    /// index written in decimal
+    pub fn push(&mut self, token: impl Into<String>) {
+        self.0.push(token.into());
+    }
+
+    /// Points one level further up, returning the token taken off, `None` at the root
+    pub fn pop(&mut self) -> Option<String> {
+        self.0.pop()
+    }
+
+    /// The value the pointer leads to in `value`, `None` when there is nothing there
+    pub fn resolve<'a>(&self, value: &'a Value) -> Option<&'a Value> {
+        self.0.iter().try_fold(value, |value, token| match value {
+            Value::Object(members) => members.get(token),
+            Value::Array(elements) => elements.get(array_index(token)?),
+            _ => None,
+        })
+    }
+}
+
+impl FromStr for JsonPointer {
+    type Err = PointerError;
+
+    fn from_str(pointer: &str) -> Result<Self, Self::Err> {
+        Self::parse(pointer)
+    }
+}
+
+impl fmt::Display for JsonPointer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for token in &self.0 {
+            // `~` has to be escaped first so the `~1` for `/` isn't escaped again
+            write!(f, "/{}", token.replace('~', "~0").replace('/', "~1"))?;
+        }
+        Ok(())
+    }
+}
+
+impl From<&[PathSegment]> for JsonPointer {
+    fn from(segments: &[PathSegment]) -> Self {
+        let tokens = segments.iter().map(|segment| match segment {
+            PathSegment::Key(key) => key.clone(),
+            PathSegment::Index(index) => index.to_string(),
+        });
+        Self(tokens.collect())
+    }
+}
+
+impl From<&Path> for JsonPointer {
+    fn from(path: &Path) -> Self {
+        Self::from(path.0.as_slice())
+    }
+}
+
+/// A pointer token with its `~1` and `~0` escapes undone, `~1` first so a `~01` becomes `~1`
+/// rather than `/`
+pub(crate) fn unescape(token: &str) -> String {
+    token.replace("~1", "/").replace("~0", "~")
+}
+
+/// The index a pointer token names, which is written without a sign or leading zeros
+pub(crate) fn array_index(token: &str) -> Option<usize> {
+    let canonical = token == "0" || !token.starts_with('0');
+    match canonical && token.bytes().all(|b| b.is_ascii_digit()) {
+        true => token.parse().ok(),
+        false => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonPointer, PointerError};
+    use crate::path::{Path, PathSegment};
+    use crate::{parse, Value};
+
+    #[test]
+    fn round_trips() {
+        let input = "/users/3/a~1b~0c/";
+        let expected = ["users", "3", "a/b~c", ""];
+
+        let actual = JsonPointer::parse(input).unwrap();
+
+        assert_eq!(actual.tokens(), expected);
+        assert_eq!(actual.to_string(), input);
+        assert!(JsonPointer::parse("").unwrap().is_root());
+    }
+
+    #[test]
+    fn rejects_malformed() {
+        let cases = [
+            ("users", PointerError::MissingSlash),
+            ("/a~2", PointerError::InvalidEscape { offset: 2 }),
+            ("/ok/b~", PointerError::InvalidEscape { offset: 5 }),
+        ];
+
+        for (input, expected) in cases {
+            let actual = input.parse::<JsonPointer>();
+
+            assert_eq!(actual, Err(expected), "{input}");
+        }
+    }
+
+    #[test]
+    fn pushes_and_pops() {
+        let mut input = JsonPointer::root();
+
+        input.push("items");
+        input.push(0.to_string());
+        input.push("a/b");
+        let popped = input.pop();
+
+        assert_eq!(popped.as_deref(), Some("a/b"));
+        assert_eq!(input.to_string(), "/items/0");
+    }
+
+    #[test]
+    fn resolves() {
+        let input = parse(String::from(r#"{"items": [{"a/b": 1}], "~": 2}"#)).unwrap();
+        let path = Path(vec![
+            PathSegment::Key(String::from("items")),
+            PathSegment::Index(0),
+            PathSegment::Key(String::from("a/b")),
+        ]);
+
+        let actual = JsonPointer::from(&path);
+
+        assert_eq!(actual.resolve(&input), Some(&Value::from(1)));
+        assert_eq!(
+            JsonPointer::parse("/~0").unwrap().resolve(&input),
+            Some(&Value::from(2))
+        );
+        assert_eq!(
+            JsonPointer::parse("/items/01").unwrap().resolve(&input),
+            None
+        );
+    }
+}
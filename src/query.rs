@@ -0,0 +1,683 @@
+use crate::error::kind_predicates;
+use crate::pointer::{array_index, unescape, JsonPointer};
+use crate::{ErrorKind, Map, Value};
+use std::fmt;
+use std::iter::Peekable;
+use std::str::CharIndices;
+
+/// Why a query couldn't be understood
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QueryError {
+    /// queries start with `$`, the whole document
+    MissingRoot,
+    /// something that can't go here, at this byte offset into the query
+    Unexpected { offset: usize, found: char },
+    /// the query ends part way through a segment
+    UnexpectedEnd,
+}
+
+impl fmt::Display for QueryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            QueryError::MissingRoot => write!(f, "a query must start with `$`"),
+            QueryError::Unexpected { offset, found } => {
+                write!(f, "unexpected {found:?} at offset {offset} of the query")
+            }
+            QueryError::UnexpectedEnd => write!(f, "the query ends part way through a segment"),
+        }
+    }
+}
+
+impl QueryError {
+    /// The category of the error: a query cut off part way is `Eof`, anything else `Syntax`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            QueryError::UnexpectedEnd => ErrorKind::Eof,
+            QueryError::MissingRoot | QueryError::Unexpected { .. } => ErrorKind::Syntax,
+        }
+    }
+}
+
+kind_predicates!(QueryError);
+
+impl std::error::Error for QueryError {}
+
+/// Picks children out of the values a segment is applied to
+#[derive(Debug, Clone, PartialEq)]
+enum Selector {
+    /// the member with this key
+    Name(String),
+    /// every element or member
+    Wildcard,
+    /// the element at this position, counting back from the end when negative
+    Index(i64),
+    /// every `step`th element from `start` up to but not including `end`
+    Slice {
+        start: Option<i64>,
+        end: Option<i64>,
+        step: i64,
+    },
+}
+
+/// One step of a query, applied to each value matched so far, or with `descendant` to each of
+/// them and everything nested inside them
+#[derive(Debug, Clone, PartialEq)]
+struct Segment {
+    descendant: bool,
+    selectors: Vec<Selector>,
+}
+
+impl Value {
+    /// The values a JSONPath query such as `$.store.book[*].author` matches, in document
+    /// order, objects giving up their members in map order. Supported are `.name` and
+    /// `['name']` children, `*` wildcards, `[0]` and `[-1]` indexes, `[start:end:step]` slices,
+    /// unions like `[0,2]` and `..` recursive descent, but not filters.
+    pub fn query(&self, query: &str) -> Result<Vec<&Value>, QueryError> {
+        let segments = parse_query(query)?;
+        let mut nodes = vec![self];
+        for segment in &segments {
+            if segment.descendant {
+                let mut all = Vec::new();
+                for node in nodes {
+                    descendants(node, &mut all);
+                }
+                nodes = all;
+            }
+            let mut selected = Vec::new();
+            for node in nodes {
+                for selector in &segment.selectors {
+                    select(node, selector, &mut selected);
+                }
+            }
+            nodes = selected;
+        }
+        Ok(nodes)
+    }
+
+    /// The values at a JSON Pointer in which a `*` segment matches every element or member,
+    /// e.g. `/items/*/id`, in document order with objects giving up their members in map
+    /// order. Nothing matches a pointer that doesn't start with `/`, other than the empty one.
+    pub fn get_all(&self, pointer: &str) -> Vec<&Value> {
+        let mut nodes = vec![self];
+        if pointer.is_empty() {
+            return nodes;
+        }
+        let Some(pointer) = pointer.strip_prefix('/') else {
+            return Vec::new();
+        };
+        for token in pointer.split('/') {
+            let selector = match token {
+                "*" => Selector::Wildcard,
+                token => Selector::Name(unescape(token)),
+            };
+            let mut selected = Vec::new();
+            for node in nodes {
+                match (&selector, node) {
+                    (Selector::Name(token), Value::Array(elements)) => {
+                        selected.extend(array_index(token).and_then(|index| elements.get(index)))
+                    }
+                    (selector, node) => select(node, selector, &mut selected),
+                }
+            }
+            nodes = selected;
+        }
+        nodes
+    }
+
+    /// The value at a JSON Pointer such as `/items/0/id`, `None` when there is nothing there or
+    /// the pointer is malformed. Unlike `get_all`, a `*` is an ordinary key.
+    pub fn pointer(&self, pointer: &str) -> Option<&Value> {
+        JsonPointer::parse(pointer).ok()?.resolve(self)
+    }
+
+    /// The first value under `key` anywhere in the tree, with its JSON Pointer, searching in
+    /// document order with object members in map order
+    pub fn find_key(&self, key: &str) -> Option<(JsonPointer, &Value)> {
+        let mut found = Vec::new();
+        find(self, key, &mut JsonPointer::root(), &mut found, true);
+        found.pop()
+    }
+
+    /// Every value under `key` anywhere in the tree, with its JSON Pointer, e.g. to find each
+    /// `email` in a document for a compliance scan. Values under the key are searched too.
+    pub fn find_all_keys(&self, key: &str) -> Vec<(JsonPointer, &Value)> {
+        let mut found = Vec::new();
+        find(self, key, &mut JsonPointer::root(), &mut found, false);
+        found
+    }
+
+    /// A copy holding only what the JSON Pointers pick out, with the objects and arrays
+    /// around it kept, for building sparse responses. A `*` token picks every member or
+    /// element, e.g. `/items/*/sku`. Arrays keep the elements anything was picked from in
+    /// their order, so indexes close up. Containers with nothing picked are left out, except
+    /// the root which is kept empty. Pointers that don't start with `/` pick nothing.
+    pub fn select(&self, pointers: &[&str]) -> Value {
+        let mut selection = Selection::default();
+        for pointer in pointers {
+            if pointer.is_empty() {
+                selection.whole = true;
+            } else if let Some(pointer) = pointer.strip_prefix('/') {
+                selection.add(pointer.split('/'));
+            }
+        }
+        match (selection_of(self, &[&selection]), self) {
+            (Some(selected), _) => selected,
+            (None, Value::Array(_)) => Value::Array(Vec::new()),
+            (None, _) => Value::Object(Map::new()),
+        }
+    }
+}
+
+/// The pointers given to `select` as a tree of their tokens
+#[derive(Debug, Default)]
+struct Selection {
+    /// a pointer ends here, so everything under it is picked
+    whole: bool,
+    children: Map<Selection>,
+    /// what a `*` token picks in every child
+    wildcard: Option<Box<Selection>>,
+}
+
+impl Selection {
+    fn add<'a>(&mut self, mut tokens: impl Iterator<Item = &'a str>) {
+        let selection = match tokens.next() {
+            None => return self.whole = true,
+            Some("*") => self.wildcard.get_or_insert_with(Box::default),
+            Some(token) => self.children.entry(unescape(token)).or_default(),
+        };
+        selection.add(tokens);
+    }
+
+    /// The selections of the child with this key or index token
+    fn child(&self, token: &str) -> impl Iterator<Item = &Selection> {
+        self.children
+            .get(token)
+            .into_iter()
+            .chain(self.wildcard.as_deref())
+    }
+}
+
+/// What `selections` pick out of `value` between them, `None` when nothing
+fn selection_of(value: &Value, selections: &[&Selection]) -> Option<Value> {
+    if selections.iter().any(|selection| selection.whole) {
+        return Some(value.clone());
+    }
+    match value {
+        Value::Object(members) => {
+            let selected: Map<Value> = members
+                .iter()
+                .filter_map(|(key, member)| {
+                    let children: Vec<_> = selections.iter().flat_map(|s| s.child(key)).collect();
+                    Some((key.clone(), selection_of(member, &children)?))
+                })
+                .collect();
+            (!selected.is_empty()).then_some(Value::Object(selected))
+        }
+        Value::Array(elements) => {
+            let selected: Vec<_> = elements
+                .iter()
+                .enumerate()
+                .filter_map(|(index, element)| {
+                    let token = index.to_string();
+                    let children: Vec<_> =
+                        selections.iter().flat_map(|s| s.child(&token)).collect();
+                    selection_of(element, &children)
+                })
+                .collect();
+            (!selected.is_empty()).then_some(Value::Array(selected))
+        }
+        _ => None,
+    }
+}
+
+/// Adds the values under `key` in `value` to `found`, stopping at the first with `first`.
+/// Returns whether to stop.
+fn find<'a>(
+    value: &'a Value,
+    key: &str,
+    path: &mut JsonPointer,
+    found: &mut Vec<(JsonPointer, &'a Value)>,
+    first: bool,
+) -> bool {
+    let children: Vec<_> = match value {
+        Value::Object(members) => members
+            .iter()
+            .map(|(name, member)| (Some(name), name.clone(), member))
+            .collect(),
+        Value::Array(elements) => elements
+            .iter()
+            .enumerate()
+            .map(|(index, element)| (None, index.to_string(), element))
+            .collect(),
+        _ => return false,
+    };
+    for (name, token, child) in children {
+        let matched = name.is_some_and(|name| name == key);
+        path.push(token);
+        if matched {
+            found.push((path.clone(), child));
+        }
+        let stop = (matched && first) || find(child, key, path, found, first);
+        path.pop();
+        if stop {
+            return true;
+        }
+    }
+    false
+}
+
+/// Adds `value` and everything nested in it, parents before their children
+fn descendants<'a>(value: &'a Value, all: &mut Vec<&'a Value>) {
+    all.push(value);
+    match value {
+        Value::Array(elements) => elements
+            .iter()
+            .for_each(|element| descendants(element, all)),
+        Value::Object(members) => members.values().for_each(|member| descendants(member, all)),
+        _ => {}
+    }
+}
+
+/// Adds the children of `value` that `selector` picks
+fn select<'a>(value: &'a Value, selector: &Selector, selected: &mut Vec<&'a Value>) {
+    match (selector, value) {
+        (Selector::Name(name), Value::Object(members)) => selected.extend(members.get(name)),
+        (Selector::Wildcard, Value::Array(elements)) => selected.extend(elements),
+        (Selector::Wildcard, Value::Object(members)) => selected.extend(members.values()),
+        (Selector::Index(index), Value::Array(elements)) => {
+            let len = elements.len() as i64;
+            let index = if *index < 0 { len + index } else { *index };
+            if (0..len).contains(&index) {
+                selected.push(&elements[index as usize]);
+            }
+        }
+        (Selector::Slice { start, end, step }, Value::Array(elements)) => {
+            let len = elements.len() as i64;
+            // negative bounds count back from the end, then are clamped to the array
+            let bound = |bound: i64, low: i64, high: i64| {
+                let bound = if bound < 0 { len + bound } else { bound };
+                bound.clamp(low, high)
+            };
+            match *step {
+                0 => {}
+                step if step > 0 => {
+                    let mut i = start.map_or(0, |start| bound(start, 0, len));
+                    let end = end.map_or(len, |end| bound(end, 0, len));
+                    while i < end {
+                        selected.push(&elements[i as usize]);
+                        // a step past the end of the array ends it rather than overflowing
+                        let Some(next) = i.checked_add(step) else {
+                            break;
+                        };
+                        i = next;
+                    }
+                }
+                step => {
+                    let mut i = start.map_or(len - 1, |start| bound(start, -1, len - 1));
+                    let end = end.map_or(-1, |end| bound(end, -1, len - 1));
+                    while i > end {
+                        selected.push(&elements[i as usize]);
+                        // a step past the end of the array ends it rather than overflowing
+                        let Some(next) = i.checked_add(step) else {
+                            break;
+                        };
+                        i = next;
+                    }
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Reads a query into its segments
+fn parse_query(query: &str) -> Result<Vec<Segment>, QueryError> {
+    let mut chars = query.char_indices().peekable();
+    if chars.next_if(|&(_, c)| c == '$').is_none() {
+        return Err(QueryError::MissingRoot);
+    }
+    let mut segments = Vec::new();
+    while let Some((offset, c)) = chars.next() {
+        let segment = match c {
+            '.' if chars.next_if(|&(_, c)| c == '.').is_some() => {
+                let selectors = match chars.next_if(|&(_, c)| c == '[') {
+                    Some(_) => parse_brackets(&mut chars)?,
+                    None => vec![parse_dotted(&mut chars)?],
+                };
+                Segment {
+                    descendant: true,
+                    selectors,
+                }
+            }
+            '.' => Segment {
+                descendant: false,
+                selectors: vec![parse_dotted(&mut chars)?],
+            },
+            '[' => Segment {
+                descendant: false,
+                selectors: parse_brackets(&mut chars)?,
+            },
+            found => return Err(QueryError::Unexpected { offset, found }),
+        };
+        segments.push(segment);
+    }
+    Ok(segments)
+}
+
+type Chars<'a> = Peekable<CharIndices<'a>>;
+
+/// Reads the `name` or `*` after a dot
+fn parse_dotted(chars: &mut Chars) -> Result<Selector, QueryError> {
+    if chars.next_if(|&(_, c)| c == '*').is_some() {
+        return Ok(Selector::Wildcard);
+    }
+    let mut name = String::new();
+    while let Some((_, c)) = chars.next_if(|&(_, c)| is_name_char(c)) {
+        name.push(c);
+    }
+    match (name.is_empty(), chars.peek()) {
+        (false, _) => Ok(Selector::Name(name)),
+        (true, Some(&(offset, found))) => Err(QueryError::Unexpected { offset, found }),
+        (true, None) => Err(QueryError::UnexpectedEnd),
+    }
+}
+
+fn is_name_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '-' || !c.is_ascii()
+}
+
+/// Reads the comma separated selectors after a `[`, up to and including the `]`
+fn parse_brackets(chars: &mut Chars) -> Result<Vec<Selector>, QueryError> {
+    let mut selectors = Vec::new();
+    loop {
+        skip_spaces(chars);
+        let selector = match chars.peek() {
+            Some(&(_, quote @ ('\'' | '"'))) => {
+                chars.next();
+                Selector::Name(parse_quoted(chars, quote)?)
+            }
+            Some((_, '*')) => {
+                chars.next();
+                Selector::Wildcard
+            }
+            _ => parse_index_or_slice(chars)?,
+        };
+        selectors.push(selector);
+        skip_spaces(chars);
+        match chars.next() {
+            Some((_, ',')) => {}
+            Some((_, ']')) => return Ok(selectors),
+            Some((offset, found)) => return Err(QueryError::Unexpected { offset, found }),
+            None => return Err(QueryError::UnexpectedEnd),
+        }
+    }
+}
+
+fn skip_spaces(chars: &mut Chars) {
+    while chars.next_if(|&(_, c)| c == ' ').is_some() {}
+}
+
+/// Reads a quoted name up to its closing `quote`, a backslash keeps the char after it
+fn parse_quoted(chars: &mut Chars, quote: char) -> Result<String, QueryError> {
+    let mut name = String::new();
+    loop {
+        match chars.next() {
+            Some((_, c)) if c == quote => return Ok(name),
+            Some((_, '\\')) => match chars.next() {
+                Some((_, c)) => name.push(c),
+                None => return Err(QueryError::UnexpectedEnd),
+            },
+            Some((_, c)) => name.push(c),
+            None => return Err(QueryError::UnexpectedEnd),
+        }
+    }
+}
+
+/// Reads `3`, `-1` or a slice such as `1:`, `:2` or `::-1`
+fn parse_index_or_slice(chars: &mut Chars) -> Result<Selector, QueryError> {
+    let start = parse_integer(chars)?;
+    skip_spaces(chars);
+    if chars.next_if(|&(_, c)| c == ':').is_none() {
+        return match start {
+            Some(index) => Ok(Selector::Index(index)),
+            None => Err(unexpected(chars)),
+        };
+    }
+    skip_spaces(chars);
+    let end = parse_integer(chars)?;
+    skip_spaces(chars);
+    let mut step = 1;
+    if chars.next_if(|&(_, c)| c == ':').is_some() {
+        skip_spaces(chars);
+        step = parse_integer(chars)?.unwrap_or(1);
+    }
+    Ok(Selector::Slice { start, end, step })
+}
+
+/// Reads an optionally negative integer, `None` when there isn't one here
+fn parse_integer(chars: &mut Chars) -> Result<Option<i64>, QueryError> {
+    let negative = chars.next_if(|&(_, c)| c == '-').is_some();
+    let mut digits = String::new();
+    while let Some((_, c)) = chars.next_if(|&(_, c)| c.is_ascii_digit()) {
+        digits.push(c);
+    }
+    if digits.is_empty() {
+        return match negative {
+            true => Err(unexpected(chars)),
+            false => Ok(None),
+        };
+    }
+    // too many digits for any array anyway
+    let magnitude: i64 = digits.parse().unwrap_or(i64::MAX);
+    Ok(Some(if negative { -magnitude } else { magnitude }))
+}
+
+/// The error for whatever comes next
+fn unexpected(chars: &mut Chars) -> QueryError {
+    match chars.peek() {
+        Some(&(offset, found)) => QueryError::Unexpected { offset, found },
+        None => QueryError::UnexpectedEnd,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::QueryError;
+    use crate::{parse, JsonPointer, Value};
+
+    fn store() -> Value {
+        let input = r#"{"store": {
+            "book": [
+                {"author": "Rees", "price": 8.95},
+                {"author": "Waugh", "price": 12.99},
+                {"author": "Melville", "price": 8.99, "isbn": "0-553"},
+                {"author": "Tolkien", "price": 22.99, "isbn": "0-395"}
+            ],
+            "bicycle": {"color": "red", "price": 19.95}
+        }}"#;
+        parse(String::from(input)).unwrap()
+    }
+
+    #[test]
+    fn queries_children() {
+        let value = store();
+        let cases = [
+            (
+                "$.store.book[*].author",
+                vec!["Rees", "Waugh", "Melville", "Tolkien"],
+            ),
+            ("$['store']['book'][0].author", vec!["Rees"]),
+            ("$.store.book[-1].author", vec!["Tolkien"]),
+            ("$.store.book[1:3].author", vec!["Waugh", "Melville"]),
+            ("$.store.book[::-2].author", vec!["Tolkien", "Waugh"]),
+            ("$.store.book[0, 2].author", vec!["Rees", "Melville"]),
+            ("$.store.book[1::9223372036854775807].author", vec!["Waugh"]),
+            (
+                "$.store.book[::-9223372036854775808].author",
+                vec!["Tolkien"],
+            ),
+            ("$.store.bicycle.color", vec!["red"]),
+            ("$.store.book[9].author", vec![]),
+        ];
+
+        for (query, expected) in cases {
+            let expected: Vec<_> = expected
+                .into_iter()
+                .map(|author| Value::String(String::from(author)))
+                .collect();
+
+            let actual = value.query(query).unwrap();
+
+            assert_eq!(actual, expected.iter().collect::<Vec<_>>(), "{query}");
+        }
+    }
+
+    #[test]
+    fn queries_descendants() {
+        let value = store();
+
+        let mut isbns = value.query("$..isbn").unwrap();
+        isbns.sort_by(|a, b| format!("{a:?}").cmp(&format!("{b:?}")));
+        let prices = value.query("$..price").unwrap();
+        let everything = value.query("$..*").unwrap();
+
+        let expected = [
+            Value::String(String::from("0-395")),
+            Value::String(String::from("0-553")),
+        ];
+        assert_eq!(isbns, expected.iter().collect::<Vec<_>>());
+        assert_eq!(prices.len(), 5);
+        // the store, the book array, 4 books and their 10 members, the bicycle and its 2 members
+        assert_eq!(everything.len(), 19);
+    }
+
+    #[test]
+    fn gets_all_matches() {
+        let input =
+            r#"{"items": [{"id": 1}, {"id": 2}, {"name": "x"}, {"id": 3}], "a/b": {"~": 4}}"#;
+        let value = parse(String::from(input)).unwrap();
+        let cases = [
+            ("/items/*/id", vec![1.0, 2.0, 3.0]),
+            ("/items/1/id", vec![2.0]),
+            ("/items/01/id", vec![]),
+            ("/a~1b/~0", vec![4.0]),
+            ("/missing/*", vec![]),
+            ("items", vec![]),
+        ];
+
+        for (pointer, expected) in cases {
+            let expected: Vec<_> = expected.into_iter().map(Value::Number).collect();
+
+            let actual = value.get_all(pointer);
+
+            assert_eq!(actual, expected.iter().collect::<Vec<_>>(), "{pointer}");
+        }
+        assert_eq!(value.get_all(""), vec![&value]);
+    }
+
+    #[test]
+    fn looks_up_pointers() {
+        let value = parse(String::from(r#"{"a": [{"*": 1}], "b/c": 2, "": 3}"#)).unwrap();
+        let cases = [
+            ("/a/0/*", Some(Value::Number(1.0))),
+            ("/b~1c", Some(Value::Number(2.0))),
+            ("/", Some(Value::Number(3.0))),
+            ("/a/00/*", None),
+            ("/a/-", None),
+            ("a", None),
+        ];
+
+        for (pointer, expected) in cases {
+            let actual = value.pointer(pointer);
+
+            assert_eq!(actual, expected.as_ref(), "{pointer}");
+        }
+        assert_eq!(value.pointer(""), Some(&value));
+    }
+
+    #[test]
+    fn rejects_bad_queries() {
+        let value = Value::Null;
+        let cases = [
+            ("store", QueryError::MissingRoot),
+            ("$.", QueryError::UnexpectedEnd),
+            ("$[0", QueryError::UnexpectedEnd),
+            ("$['a]", QueryError::UnexpectedEnd),
+            (
+                "$[?]",
+                QueryError::Unexpected {
+                    offset: 2,
+                    found: '?',
+                },
+            ),
+            (
+                "$a",
+                QueryError::Unexpected {
+                    offset: 1,
+                    found: 'a',
+                },
+            ),
+        ];
+
+        for (query, expected) in cases {
+            let actual = value.query(query).unwrap_err();
+
+            assert_eq!(actual, expected, "{query}");
+        }
+    }
+
+    #[test]
+    fn finds_keys() {
+        let input = r#"[{"user": {"email": "a@x", "name": "a"}}, {"email": {"email": "b@x"}}]"#;
+        let value = parse(String::from(input)).unwrap();
+        let expected = vec![
+            (String::from("/0/user/email"), Value::from("a@x")),
+            (
+                String::from("/1/email"),
+                value.get_path("[1].email").unwrap().clone(),
+            ),
+            (String::from("/1/email/email"), Value::from("b@x")),
+        ];
+
+        let all: Vec<_> = value
+            .find_all_keys("email")
+            .into_iter()
+            .map(|(pointer, found)| (pointer.to_string(), found.clone()))
+            .collect();
+        let first = value.find_key("email");
+        let missing = value.find_key("phone");
+
+        assert_eq!(all, expected);
+        assert_eq!(
+            first,
+            Some((
+                JsonPointer::parse("/0/user/email").unwrap(),
+                &Value::from("a@x")
+            ))
+        );
+        assert_eq!(missing, None);
+    }
+
+    #[test]
+    fn selects_pointers() {
+        let input = r#"{
+            "id": 7,
+            "user": {"name": "a", "email": "a@x"},
+            "items": [{"sku": "s1", "qty": 1}, {"qty": 2}, {"sku": "s3", "qty": 3}],
+            "meta": {"tags": ["x"]}
+        }"#;
+        let value = parse(String::from(input)).unwrap();
+        let expected = parse(String::from(
+            r#"{"id": 7, "user": {"name": "a"}, "items": [{"sku": "s1"}, {"sku": "s3", "qty": 3}]}"#,
+        ))
+        .unwrap();
+
+        let actual = value.select(&["/id", "/user/name", "/items/*/sku", "/items/2", "/meta/x"]);
+        let nothing = value.select(&["/missing", "id"]);
+        let whole = value.select(&[""]);
+
+        assert_eq!(actual, expected);
+        assert_eq!(nothing, parse(String::from("{}")).unwrap());
+        assert_eq!(whole, value);
+    }
+}
@@ -0,0 +1,1504 @@
+use crate::error::{Error, ErrorCode};
+use crate::options::{DuplicateKeyPolicy, ParseOptions, RootPolicy, CANCEL_CHECK_INTERVAL};
+use crate::parse::{parse_number, Expected, TokenParseError};
+use crate::path::{Path, PathSegment};
+use crate::pointer::JsonPointer;
+use crate::span::{Position, Span, Spanned};
+use crate::tokenize::{next_lexeme, skip_comment, Lexeme, Token, TokenizeError};
+use crate::{Map, Value};
+use std::collections::HashMap;
+use std::io::{self, BufRead};
+
+/// The fewest bytes pulled from the underlying reader at a time
+const CHUNK_SIZE: usize = 8 * 1024;
+
+/// One step through a document, as reported by `JsonReader::next_event`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Event {
+    /// `{`
+    StartObject,
+    /// `}`
+    EndObject,
+    /// `[`
+    StartArray,
+    /// `]`
+    EndArray,
+    /// the key of an object member, its value is reported next
+    Key(String),
+    String(String),
+    Number(f64),
+    Boolean(bool),
+    Null,
+}
+
+/// Callbacks for each part of a document, every one does nothing unless overridden so only the
+/// parts that matter need implementing. Given to `JsonReader::handle`.
+pub trait Handler {
+    fn on_start_object(&mut self) {}
+    fn on_end_object(&mut self) {}
+    fn on_start_array(&mut self) {}
+    fn on_end_array(&mut self) {}
+    /// the key of an object member, the callback for its value comes next
+    fn on_key(&mut self, _key: String) {}
+    fn on_string(&mut self, _value: String) {}
+    fn on_number(&mut self, _value: f64) {}
+    fn on_boolean(&mut self, _value: bool) {}
+    fn on_null(&mut self) {}
+}
+
+/// A container the reader is inside of
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Container {
+    Array,
+    Object,
+}
+
+impl Container {
+    fn closer(self) -> Token {
+        match self {
+            Container::Array => Token::RightBracket,
+            Container::Object => Token::RightBrace,
+        }
+    }
+
+    fn expected_closer(self) -> Expected {
+        match self {
+            Container::Array => Expected::RightBracket,
+            Container::Object => Expected::RightBrace,
+        }
+    }
+}
+
+/// What the reader expects to read next
+#[derive(Debug, Clone)]
+enum State {
+    /// any value
+    Value,
+    /// the first element of an array or its `]`
+    FirstElement,
+    /// the first key of an object or its `}`
+    FirstMember,
+    /// an object key after a `,`
+    Key,
+    /// a `,` or the closer of the innermost container
+    AfterValue,
+    /// nothing, the document is complete
+    Done,
+    /// the document was invalid, the error is reported again on every call
+    Failed(Error),
+}
+
+/// Where a key was read, its span and the line and column it starts at
+type KeyLocation = (Span, usize, usize);
+
+/// An array or object partly built from events
+enum Partial {
+    Array(Vec<Value>),
+    Object {
+        map: Map<Value>,
+        /// where each key was first seen, only kept for `DuplicateKeyPolicy::Error`
+        keys: HashMap<String, KeyLocation>,
+        /// the key of the member whose value is being built
+        key: Option<(String, KeyLocation)>,
+    },
+}
+
+/// A pull parser reporting a document one `Event` at a time, so huge documents can be processed
+/// without ever building a `Value` tree. Only the current token and the nesting of containers
+/// are held in memory, the input is read from `R` in chunks as it is needed.
+///
+/// Text already in memory can be read with `JsonReader::new(input.as_bytes())`.
+#[derive(Debug)]
+pub struct JsonReader<R> {
+    reader: R,
+    options: ParseOptions,
+    /// input that has been read and decoded but not yet dropped
+    buffer: String,
+    /// the start of a char split across two reads
+    partial: Vec<u8>,
+    /// how far into `buffer` tokens have been read
+    pos: usize,
+    /// where in the whole input `buffer` starts
+    start: Position,
+    /// whether `reader` has no more input
+    eof: bool,
+    /// a token read ahead of time, its span is an offset in the whole input
+    peeked: Option<Spanned<Token>>,
+    /// where the last token read ended
+    end: usize,
+    /// where the last key was read
+    key: KeyLocation,
+    stack: Vec<Container>,
+    /// how many tokens, array elements and object members have been read, for the limits in
+    /// `ParseOptions`
+    tokens: usize,
+    elements: usize,
+    members: usize,
+    /// the offset last given to the progress callback
+    reported: usize,
+    /// the literal of the last number read
+    number: String,
+    /// for each container, the key or index of the member or element being read, `None` until
+    /// the first one starts
+    path: Vec<Option<PathSegment>>,
+    state: State,
+}
+
+impl<R: BufRead> JsonReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader,
+            options,
+            buffer: String::new(),
+            partial: Vec::new(),
+            pos: 0,
+            start: Position::default(),
+            eof: false,
+            peeked: None,
+            end: 0,
+            key: (Span::default(), 1, 1),
+            stack: Vec::new(),
+            tokens: 0,
+            elements: 0,
+            members: 0,
+            reported: 0,
+            number: String::new(),
+            path: Vec::new(),
+            state: State::Value,
+        }
+    }
+
+    /// Reads the next event, `None` once the document is complete. After an error the same
+    /// error is returned from every call.
+    pub fn next_event(&mut self) -> Result<Option<Event>, Error> {
+        self.guard(Self::advance)
+    }
+
+    /// Reads the rest of the document in one pass, calling `handler` for each event.
+    /// Callbacks made before an error are not undone.
+    pub fn handle(&mut self, handler: &mut impl Handler) -> Result<(), Error> {
+        while let Some(event) = self.next_event()? {
+            match event {
+                Event::StartObject => handler.on_start_object(),
+                Event::EndObject => handler.on_end_object(),
+                Event::StartArray => handler.on_start_array(),
+                Event::EndArray => handler.on_end_array(),
+                Event::Key(key) => handler.on_key(key),
+                Event::String(value) => handler.on_string(value),
+                Event::Number(value) => handler.on_number(value),
+                Event::Boolean(value) => handler.on_boolean(value),
+                Event::Null => handler.on_null(),
+            }
+        }
+        Ok(())
+    }
+
+    /// Skips the rest of the array or object the last event is inside of, up to and including
+    /// its end
+    pub fn skip_container(&mut self) -> Result<(), Error> {
+        self.guard(Self::skip_rest)
+    }
+
+    fn skip_rest(&mut self) -> Result<(), Error> {
+        if self.stack.is_empty() {
+            return Ok(());
+        }
+        // a token read ahead is already past the bytes a bracket count would start from
+        if self.peeked.is_some() {
+            let depth = self.stack.len();
+            while self.stack.len() >= depth {
+                self.advance()?;
+            }
+            return Ok(());
+        }
+        self.pop();
+        self.skip_brackets()?;
+        self.state = self.after_value();
+        Ok(())
+    }
+
+    /// Skips the value the next event would have started, or the next element of an array,
+    /// without decoding the strings and numbers inside it. Arrays and objects are skipped by
+    /// counting brackets, so their contents aren't checked.
+    pub fn skip_value(&mut self) -> Result<(), Error> {
+        self.guard(Self::skip)
+    }
+
+    fn skip(&mut self) -> Result<(), Error> {
+        self.expect_value()?;
+        let token = self.next_token()?;
+        match token.as_ref().map(|token| &token.value) {
+            Some(Token::LeftBracket | Token::LeftBrace) => {
+                if self.stack.len() >= self.options.max_depth {
+                    let kind = TokenParseError::DepthLimitExceeded;
+                    return Err(self.unexpected(token, kind, &[]));
+                }
+                if let Err(kind) = self.enter_value() {
+                    return Err(self.unexpected(token, kind, &[]));
+                }
+                self.skip_brackets()?;
+            }
+            Some(Token::RightBracket) if matches!(self.state, State::FirstElement) => {
+                let kind = TokenParseError::ExpectedValue;
+                return Err(self.unexpected(token, kind, &[Expected::Value]));
+            }
+            // a scalar is a single token, already read
+            _ => return self.begin_value(token).map(|_| ()),
+        }
+        self.state = self.after_value();
+        Ok(())
+    }
+
+    /// Reads the value the next event would have started, or the next element of an array,
+    /// into a whole `Value`, so a document can be streamed through with only the parts wanted
+    /// built. At the top level, what follows the value is only checked by the next event.
+    pub fn read_value(&mut self) -> Result<Value, Error> {
+        self.guard(|reader| {
+            reader.expect_value()?;
+            if matches!(reader.state, State::FirstElement) {
+                if reader.peek_token()? == Some(&Token::RightBracket) {
+                    let token = reader.peeked.take();
+                    let kind = TokenParseError::ExpectedValue;
+                    return Err(reader.unexpected(token, kind, &[Expected::Value]));
+                }
+                reader.state = State::Value;
+            }
+            reader.build_value()
+        })
+    }
+
+    /// Checks a value comes next, moving past the `,` before it in an array
+    fn expect_value(&mut self) -> Result<(), Error> {
+        // between array elements the `,` is skipped too
+        if matches!(self.state, State::AfterValue) && self.stack.last() == Some(&Container::Array) {
+            let token = self.next_token()?;
+            if token.as_ref().map(|token| &token.value) != Some(&Token::Comma) {
+                let kind = TokenParseError::ExpectedComma;
+                return Err(self.unexpected(token, kind, &[Expected::Comma]));
+            }
+            self.state = State::Value;
+        }
+        if !matches!(self.state, State::Value | State::FirstElement) {
+            let token = self.next_token()?;
+            let kind = TokenParseError::ExpectedValue;
+            return Err(self.unexpected(token, kind, &[Expected::Value]));
+        }
+        Ok(())
+    }
+
+    /// Moves past the closer matching an opener just read
+    fn skip_brackets(&mut self) -> Result<(), Error> {
+        let mut depth = 1;
+        loop {
+            let bytes = self.buffer.as_bytes();
+            // every case needs at least the byte at `pos` and a `/` the byte after it
+            if self.pos + 1 >= bytes.len() && !self.eof {
+                self.fill()?;
+                continue;
+            }
+            let Some(&byte) = bytes.get(self.pos) else {
+                let kind = TokenParseError::UnexpectedEof;
+                return Err(self.unexpected(None, kind, &[]));
+            };
+            match byte {
+                b'"' => match closing_quote(bytes, self.pos) {
+                    Some(end) => self.pos = end,
+                    None if self.eof => {
+                        let offset = self.start.offset + self.pos;
+                        return Err(self.error(offset, TokenizeError::UnclosedQuotes.into()));
+                    }
+                    None => self.fill()?,
+                },
+                b'/' if self.options.allow_comments => match skip_comment(bytes, self.pos) {
+                    // a line comment at the end of the buffer may carry on past it
+                    Some(Ok(end)) if end < bytes.len() || self.eof => self.pos = end,
+                    Some(Err(err)) if self.eof => {
+                        let offset = self.start.offset + self.pos;
+                        return Err(self.error(offset, err.into()));
+                    }
+                    Some(_) => self.fill()?,
+                    None => self.pos += 1,
+                },
+                b'[' | b'{' => {
+                    if self.stack.len() + depth >= self.options.max_depth {
+                        let offset = self.start.offset + self.pos;
+                        let kind = TokenParseError::DepthLimitExceeded;
+                        return Err(self.error(offset, kind.into()));
+                    }
+                    depth += 1;
+                    self.pos += 1;
+                }
+                b']' | b'}' => {
+                    depth -= 1;
+                    self.pos += 1;
+                    if depth == 0 {
+                        return Ok(());
+                    }
+                }
+                _ => self.pos += 1,
+            }
+        }
+    }
+
+    /// Reads the next element of a document that is one array, `None` after its `]`
+    fn next_element(&mut self) -> Result<Option<Value>, Error> {
+        if matches!(self.state, State::Value) && self.stack.is_empty() {
+            let token = self.next_token()?;
+            if token.as_ref().map(|token| &token.value) != Some(&Token::LeftBracket) {
+                return Err(self.unexpected(token, TokenParseError::ExpectedArray, &[]));
+            }
+            self.open(Container::Array);
+            self.state = State::FirstElement;
+        }
+        if self.stack.len() == 1 && self.next_element_start()? {
+            return self.build_value().map(Some);
+        }
+        // the array is closed, only what follows it is left to check
+        self.advance().map(|_| None)
+    }
+
+    /// Reads a whole document that is a single value
+    fn read_document(&mut self) -> Result<Value, Error> {
+        let value = self.build_value()?;
+        // only checks nothing follows the value
+        self.advance()?;
+        Ok(value)
+    }
+
+    /// Reads the next of any number of back-to-back documents, `None` at the end of the input
+    fn next_document(&mut self) -> Result<Option<Spanned<Value>>, Error> {
+        self.state = State::Value;
+        self.peek_token()?;
+        let Some(start) = self.peeked.as_ref().map(|token| token.span.start) else {
+            return Ok(None);
+        };
+        let value = self.build_value()?;
+        Ok(Some(Spanned::new(value, Span::new(start, self.end))))
+    }
+
+    /// Moves past the `,` before the next element of the innermost array, or past its `]` when
+    /// it has no more elements. Returns whether an element comes next.
+    fn next_element_start(&mut self) -> Result<bool, Error> {
+        match self.state {
+            State::FirstElement => {
+                if self.peek_token()? == Some(&Token::RightBracket) {
+                    self.close()?;
+                    return Ok(false);
+                }
+            }
+            State::AfterValue => {
+                let token = self.next_token()?;
+                match token.as_ref().map(|token| &token.value) {
+                    Some(Token::Comma) => {
+                        if self.options.allow_trailing_commas
+                            && self.peek_token()? == Some(&Token::RightBracket)
+                        {
+                            self.close()?;
+                            return Ok(false);
+                        }
+                    }
+                    Some(Token::RightBracket) => {
+                        self.close()?;
+                        return Ok(false);
+                    }
+                    _ => {
+                        let expected = [Expected::Comma, Expected::RightBracket];
+                        let kind = TokenParseError::ExpectedComma;
+                        return Err(self.unexpected(token, kind, &expected));
+                    }
+                }
+            }
+            State::Value => {}
+            _ => return Ok(false),
+        }
+        self.state = State::Value;
+        Ok(true)
+    }
+
+    /// Builds the value the next event starts
+    fn build_value(&mut self) -> Result<Value, Error> {
+        let Some(event) = self.advance()? else {
+            unreachable!("a document can't end inside a value");
+        };
+        self.build_from(event)
+    }
+
+    /// Builds the value `event` started, which must be the last event read
+    pub(crate) fn value_from(&mut self, event: Event) -> Result<Value, Error> {
+        self.guard(|reader| reader.build_from(event))
+    }
+
+    /// `value_from` for use inside a read that is already guarded
+    fn build_from(&mut self, mut event: Event) -> Result<Value, Error> {
+        let mut partials = Vec::new();
+        loop {
+            let value = match event {
+                Event::StartArray => {
+                    partials.push(Partial::Array(Vec::new()));
+                    None
+                }
+                Event::StartObject => {
+                    partials.push(Partial::Object {
+                        map: Map::new(),
+                        keys: HashMap::new(),
+                        key: None,
+                    });
+                    None
+                }
+                Event::Key(name) => {
+                    if let Some(Partial::Object { key, .. }) = partials.last_mut() {
+                        *key = Some((name, self.key));
+                    }
+                    None
+                }
+                Event::EndArray | Event::EndObject => match partials.pop() {
+                    Some(Partial::Array(elements)) => Some(Value::Array(elements)),
+                    Some(Partial::Object { map, .. }) => Some(Value::Object(map)),
+                    None => unreachable!("only values that were started are ended"),
+                },
+                Event::String(string) => Some(Value::String(string)),
+                Event::Number(number) => Some(Value::Number(number)),
+                Event::Boolean(boolean) => Some(Value::Boolean(boolean)),
+                Event::Null => Some(Value::Null),
+            };
+            if let Some(value) = value {
+                match partials.last_mut() {
+                    None => return Ok(value),
+                    Some(Partial::Array(elements)) => elements.push(value),
+                    Some(Partial::Object { map, keys, key }) => {
+                        if let Some((name, location)) = key.take() {
+                            self.insert_member(map, keys, name, location, value)?;
+                        }
+                    }
+                }
+            }
+            let Some(next) = self.advance()? else {
+                unreachable!("a document can't end inside a value");
+            };
+            event = next;
+        }
+    }
+
+    fn insert_member(
+        &self,
+        map: &mut Map<Value>,
+        keys: &mut HashMap<String, KeyLocation>,
+        key: String,
+        location: KeyLocation,
+        value: Value,
+    ) -> Result<(), Error> {
+        let key = match self.options.lowercase_keys {
+            true => key.to_lowercase(),
+            false => key,
+        };
+        match self.options.duplicate_keys {
+            DuplicateKeyPolicy::LastWins => {
+                map.insert(key, value);
+            }
+            DuplicateKeyPolicy::FirstWins => {
+                map.entry(key).or_insert(value);
+            }
+            DuplicateKeyPolicy::Error => {
+                if let Some(&(first, first_line, first_column)) = keys.get(&key) {
+                    let (span, line, column) = location;
+                    let kind = TokenParseError::DuplicateKey {
+                        key,
+                        first,
+                        first_line,
+                        first_column,
+                    };
+                    return Err(Error::located(span.start, line, column, kind.into()));
+                }
+                keys.insert(key.clone(), location);
+                map.insert(key, value);
+            }
+        }
+        Ok(())
+    }
+
+    /// The JSON Pointer of the value the last event belongs to, e.g. `/records/3/payload` for a
+    /// `Key("payload")` and its value, and for an array or object its start and end
+    pub fn current_path(&self) -> JsonPointer {
+        JsonPointer::from(self.segments().as_slice())
+    }
+
+    /// The last `Number` event as it was written in the input, which the `f64` may not
+    /// represent exactly
+    pub(crate) fn raw_number(&self) -> &str {
+        &self.number
+    }
+
+    /// How many arrays and objects the last event is inside of, counting one it started
+    pub fn depth(&self) -> usize {
+        self.stack.len()
+    }
+
+    /// Runs `read` unless an error has already happened, remembering any error it returns
+    fn guard<T>(&mut self, read: impl FnOnce(&mut Self) -> Result<T, Error>) -> Result<T, Error> {
+        if let State::Failed(err) = &self.state {
+            return Err(err.clone());
+        }
+        let result = read(self);
+        if let Err(err) = &result {
+            self.state = State::Failed(err.clone());
+        }
+        result
+    }
+
+    fn advance(&mut self) -> Result<Option<Event>, Error> {
+        loop {
+            match self.state {
+                State::Value => {
+                    let token = self.next_token()?;
+                    return self.begin_value(token).map(Some);
+                }
+                State::FirstElement => match self.peek_token()? {
+                    Some(Token::RightBracket) => return self.close().map(Some),
+                    _ => self.state = State::Value,
+                },
+                State::FirstMember => {
+                    return self
+                        .read_key(&[Expected::Key, Expected::RightBrace])
+                        .map(Some);
+                }
+                State::Key => return self.read_key(&[Expected::Key]).map(Some),
+                State::AfterValue => {
+                    let container = self.stack[self.stack.len() - 1];
+                    let token = self.next_token()?;
+                    match token.as_ref().map(|token| &token.value) {
+                        Some(Token::Comma) => {
+                            // a trailing comma is read as if it wasn't there
+                            if self.options.allow_trailing_commas
+                                && self.peek_token()? == Some(&container.closer())
+                            {
+                                return self.close().map(Some);
+                            }
+                            self.state = match container {
+                                Container::Array => State::Value,
+                                Container::Object => State::Key,
+                            };
+                        }
+                        Some(closer) if *closer == container.closer() => {
+                            self.peeked = token;
+                            return self.close().map(Some);
+                        }
+                        _ => {
+                            let expected = [Expected::Comma, container.expected_closer()];
+                            let kind = TokenParseError::ExpectedComma;
+                            return Err(self.unexpected(token, kind, &expected));
+                        }
+                    }
+                }
+                State::Done => {
+                    // a document is a single value, anything after it is an error
+                    if self.options.allow_trailing_data {
+                        return Ok(None);
+                    }
+                    return match self.next_token()? {
+                        Some(token) => {
+                            let kind = TokenParseError::TrailingTokens;
+                            Err(self.unexpected(Some(token), kind, &[Expected::Eof]))
+                        }
+                        None => Ok(None),
+                    };
+                }
+                State::Failed(_) => unreachable!("failed readers return before advancing"),
+            }
+        }
+    }
+
+    fn begin_value(&mut self, token: Option<Spanned<Token>>) -> Result<Event, Error> {
+        let entered = self.enter_value();
+        let Some(value) = token.as_ref().map(|token| &token.value) else {
+            let kind = TokenParseError::UnexpectedEof;
+            return Err(self.unexpected(None, kind, &[Expected::Value]));
+        };
+        if let Err(kind) = entered {
+            return Err(self.unexpected(token, kind, &[]));
+        }
+        let opens = matches!(value, Token::LeftBracket | Token::LeftBrace);
+        if self.stack.is_empty() && self.options.root == RootPolicy::ObjectOrArray && !opens {
+            let kind = TokenParseError::ExpectedContainer;
+            return Err(self.unexpected(token, kind, &[]));
+        }
+        if opens && self.stack.len() >= self.options.max_depth {
+            return Err(self.unexpected(token, TokenParseError::DepthLimitExceeded, &[]));
+        }
+        let event = match value {
+            Token::Null => Event::Null,
+            Token::False => Event::Boolean(false),
+            Token::True => Event::Boolean(true),
+            Token::Number(raw) => match parse_number(raw) {
+                Ok(Value::Number(number)) => {
+                    self.number.clone_from(raw);
+                    Event::Number(number)
+                }
+                Ok(_) => unreachable!("parse_number only makes numbers"),
+                Err(err) => return Err(self.unexpected(token, err, &[])),
+            },
+            Token::String(string) => Event::String(string.value.clone()),
+            Token::LeftBracket => {
+                self.open(Container::Array);
+                self.state = State::FirstElement;
+                return Ok(Event::StartArray);
+            }
+            Token::LeftBrace => {
+                self.open(Container::Object);
+                self.state = State::FirstMember;
+                return Ok(Event::StartObject);
+            }
+            _ => {
+                let kind = TokenParseError::ExpectedValue;
+                return Err(self.unexpected(token, kind, &[Expected::Value]));
+            }
+        };
+        self.state = self.after_value();
+        Ok(event)
+    }
+
+    /// Reads an object key and the `:` after it, or the `}` of an empty object when it is expected
+    fn read_key(&mut self, expected: &[Expected]) -> Result<Event, Error> {
+        let token = self.next_token()?;
+        let key = match &token {
+            Some(Spanned {
+                value: Token::String(key),
+                span,
+            }) => {
+                self.members += 1;
+                if self
+                    .options
+                    .max_object_members
+                    .is_some_and(|max| self.members > max)
+                {
+                    let kind = TokenParseError::ObjectMemberLimitExceeded;
+                    return Err(self.unexpected(token, kind, &[]));
+                }
+                let (line, column) = self.locate(span.start);
+                self.key = (*span, line, column);
+                if let Some(segment) = self.path.last_mut() {
+                    *segment = Some(PathSegment::Key(key.value.clone()));
+                }
+                key.value.clone()
+            }
+            Some(Spanned {
+                value: Token::RightBrace,
+                ..
+            }) if expected.contains(&Expected::RightBrace) => {
+                self.peeked = token;
+                return self.close();
+            }
+            _ => {
+                let kind = TokenParseError::ExpectedProperty;
+                return Err(self.unexpected(token, kind, expected));
+            }
+        };
+        let colon = self.next_token()?;
+        if colon.as_ref().map(|token| &token.value) != Some(&Token::Colon) {
+            let kind = TokenParseError::ExpectedColon;
+            return Err(self.unexpected(colon, kind, &[Expected::Colon]));
+        }
+        self.state = State::Value;
+        Ok(Event::Key(key))
+    }
+
+    /// Consumes the peeked closer of the innermost container
+    fn close(&mut self) -> Result<Event, Error> {
+        self.peeked = None;
+        let event = match self.pop() {
+            Some(Container::Array) => Event::EndArray,
+            Some(Container::Object) => Event::EndObject,
+            None => unreachable!("only open containers are closed"),
+        };
+        self.state = self.after_value();
+        Ok(event)
+    }
+
+    fn open(&mut self, container: Container) {
+        self.stack.push(container);
+        self.path.push(None);
+    }
+
+    fn pop(&mut self) -> Option<Container> {
+        self.path.pop();
+        self.stack.pop()
+    }
+
+    /// Moves the path on to the value starting, counting it when it is an array element
+    fn enter_value(&mut self) -> Result<(), TokenParseError> {
+        if self.stack.last() != Some(&Container::Array) {
+            return Ok(());
+        }
+        self.elements += 1;
+        if self
+            .options
+            .max_array_elements
+            .is_some_and(|max| self.elements > max)
+        {
+            return Err(TokenParseError::ArrayElementLimitExceeded);
+        }
+        if let Some(segment) = self.path.last_mut() {
+            let index = match segment {
+                Some(PathSegment::Index(index)) => *index + 1,
+                _ => 0,
+            };
+            *segment = Some(PathSegment::Index(index));
+        }
+        Ok(())
+    }
+
+    /// The keys and indexes leading to the value of the last event
+    fn segments(&self) -> Vec<PathSegment> {
+        self.path.iter().flatten().cloned().collect()
+    }
+
+    fn after_value(&self) -> State {
+        match self.stack.is_empty() {
+            true => State::Done,
+            false => State::AfterValue,
+        }
+    }
+
+    fn peek_token(&mut self) -> Result<Option<&Token>, Error> {
+        if self.peeked.is_none() {
+            self.peeked = self.next_token()?;
+        }
+        Ok(self.peeked.as_ref().map(|token| &token.value))
+    }
+
+    /// Reads the next token, `None` at the end of the input
+    fn next_token(&mut self) -> Result<Option<Spanned<Token>>, Error> {
+        if let Some(token) = self.peeked.take() {
+            return Ok(Some(token));
+        }
+        loop {
+            let mut index = self.pos;
+            let lexeme = next_lexeme(&self.buffer, &mut index, &self.options);
+            let end = match &lexeme {
+                None => self.buffer.len(),
+                Some(Ok(Lexeme::Token(token))) => token.span.end,
+                Some(Ok(Lexeme::Comment(span))) => span.end,
+                Some(Err(err)) => err.span.end,
+            };
+            // anything that reaches the end of what has been read may carry on past it, and
+            // a `/` needs the char after it to tell whether it starts a comment
+            if end + 1 >= self.buffer.len() && !self.eof {
+                self.fill()?;
+                continue;
+            }
+            self.pos = index;
+            return match lexeme {
+                None => {
+                    self.report_progress(self.start.offset + self.buffer.len(), true);
+                    Ok(None)
+                }
+                Some(Ok(Lexeme::Comment(_))) => continue,
+                Some(Ok(Lexeme::Token(token))) => {
+                    let span = self.absolute(token.span);
+                    self.tokens += 1;
+                    if self.tokens.is_multiple_of(CANCEL_CHECK_INTERVAL)
+                        && self.options.is_cancelled()
+                    {
+                        return Err(self.error(span.start, TokenizeError::Cancelled.into()));
+                    }
+                    self.end = span.end;
+                    self.report_progress(span.end, false);
+                    Ok(Some(Spanned::new(token.value, span)))
+                }
+                Some(Err(err)) => {
+                    let offset = self.start.offset + err.span.start;
+                    Err(self.error(offset, err.value.into()))
+                }
+            };
+        }
+    }
+
+    /// Tells the progress callback about `offset` once it is an interval past the last report,
+    /// or on `end` once it is past it at all
+    fn report_progress(&mut self, offset: usize, end: bool) {
+        let Some(progress) = &self.options.progress else {
+            return;
+        };
+        if offset > self.reported && (end || offset - self.reported >= progress.interval) {
+            (progress.callback)(offset);
+            self.reported = offset;
+        }
+    }
+
+    /// Reads at least another chunk from the reader, dropping the input already tokenized
+    fn fill(&mut self) -> Result<(), Error> {
+        self.compact();
+        // a token longer than a chunk is read in ever larger chunks, so it isn't
+        // tokenized from its start again and again
+        let wanted = CHUNK_SIZE.max(self.buffer.len());
+        let mut read = 0;
+        while read < wanted {
+            let chunk = match self.reader.fill_buf() {
+                Ok(chunk) => chunk,
+                Err(err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                Err(err) => {
+                    let offset = self.start.offset + self.buffer.len();
+                    return Err(self.error(offset, err.into()));
+                }
+            };
+            if chunk.is_empty() {
+                self.eof = true;
+                break;
+            }
+            let len = chunk.len().min(wanted - read);
+            self.partial.extend_from_slice(&chunk[..len]);
+            self.reader.consume(len);
+            read += len;
+        }
+        let len = self.start.offset + self.buffer.len() + self.partial.len();
+        if let Some(max) = self.options.max_input_len.filter(|max| len > *max) {
+            return Err(self.error(max, TokenizeError::InputTooLarge.into()));
+        }
+        self.decode()
+    }
+
+    /// Moves every complete char from `partial` into `buffer`
+    fn decode(&mut self) -> Result<(), Error> {
+        let (valid, invalid) = match std::str::from_utf8(&self.partial) {
+            Ok(text) => (text.len(), false),
+            // an incomplete char at the very end may be finished by the next read
+            Err(err) => (err.valid_up_to(), err.error_len().is_some() || self.eof),
+        };
+        if let Ok(text) = std::str::from_utf8(&self.partial[..valid]) {
+            self.buffer.push_str(text);
+        }
+        self.partial.drain(..valid);
+        if invalid {
+            let offset = self.start.offset + self.buffer.len();
+            return Err(self.error(offset, TokenizeError::InvalidUtf8.into()));
+        }
+        Ok(())
+    }
+
+    /// Drops the input before `pos`, keeping track of where the buffer now starts
+    fn compact(&mut self) {
+        self.start.advance(&self.buffer.as_bytes()[..self.pos]);
+        self.buffer.drain(..self.pos);
+        self.pos = 0;
+    }
+
+    fn absolute(&self, span: Span) -> Span {
+        Span::new(self.start.offset + span.start, self.start.offset + span.end)
+    }
+
+    /// An error at `token` or, when there is no token, at the end of the input
+    fn unexpected(
+        &self,
+        token: Option<Spanned<Token>>,
+        kind: TokenParseError,
+        expected: &[Expected],
+    ) -> Error {
+        let (kind, offset) = match &token {
+            Some(token) => (kind, token.span.start),
+            None => (
+                TokenParseError::UnexpectedEof,
+                self.start.offset + self.buffer.len(),
+            ),
+        };
+        Error {
+            expected: expected.to_vec(),
+            found: token.map(|token| token.value),
+            ..self.error(offset, kind.into())
+        }
+    }
+
+    /// An error at `offset` in the whole input, which must not be before the buffer
+    fn error(&self, offset: usize, code: ErrorCode) -> Error {
+        let (line, column) = self.locate(offset);
+        // between members the last one is over, so errors are in the container
+        let mut segments = self.segments();
+        if matches!(self.state, State::AfterValue) {
+            segments.pop();
+        }
+        Error {
+            path: Path(segments),
+            ..Error::located(offset, line, column, code)
+        }
+    }
+
+    /// The line and column of `offset` in the whole input, which must not be before the buffer
+    fn locate(&self, offset: usize) -> (usize, usize) {
+        self.start.locate(&self.buffer, offset - self.start.offset)
+    }
+}
+
+/// Parses a document straight from `reader`, a chunk at a time, without reading it all into a
+/// string first
+pub fn from_reader<R: BufRead>(reader: R) -> Result<Value, Error> {
+    from_reader_with(reader, &ParseOptions::default())
+}
+
+pub fn from_reader_with<R: BufRead>(reader: R, options: &ParseOptions) -> Result<Value, Error> {
+    JsonReader::with_options(reader, options.clone()).read_document()
+}
+
+/// Checks whether `reader` holds a valid JSON document in constant memory, however large it is,
+/// returning the first error. Only one token is held at a time, so the limits in the options
+/// apply but duplicate keys aren't looked for, as that means remembering every key.
+pub fn validate_stream<R: BufRead>(reader: R) -> Result<(), Error> {
+    validate_stream_with(reader, &ParseOptions::default())
+}
+
+pub fn validate_stream_with<R: BufRead>(reader: R, options: &ParseOptions) -> Result<(), Error> {
+    let mut reader = JsonReader::with_options(reader, options.clone());
+    while reader.next_event()?.is_some() {}
+    Ok(())
+}
+
+/// Reads a document that is one big array an element at a time, so memory use is bounded by the
+/// largest element rather than the whole document
+pub fn iter_array<R: BufRead>(reader: R) -> ArrayIter<R> {
+    iter_array_with(reader, &ParseOptions::default())
+}
+
+pub fn iter_array_with<R: BufRead>(reader: R, options: &ParseOptions) -> ArrayIter<R> {
+    ArrayIter {
+        reader: JsonReader::with_options(reader, options.clone()),
+        done: false,
+    }
+}
+
+/// The elements of an array document, see `iter_array`. Iteration stops after the first error.
+#[derive(Debug)]
+pub struct ArrayIter<R> {
+    reader: JsonReader<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for ArrayIter<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.reader.guard(JsonReader::next_element).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+/// Reads back-to-back documents such as `{"a":1} {"b":2}` one at a time until the input ends,
+/// each along with the span it was read from
+pub fn iter_documents<R: BufRead>(reader: R) -> DocumentIter<R> {
+    iter_documents_with(reader, &ParseOptions::default())
+}
+
+pub fn iter_documents_with<R: BufRead>(reader: R, options: &ParseOptions) -> DocumentIter<R> {
+    DocumentIter {
+        reader: JsonReader::with_options(reader, options.clone()),
+        done: false,
+    }
+}
+
+/// The documents of a stream of them, see `iter_documents`. Iteration stops after the first
+/// error.
+#[derive(Debug)]
+pub struct DocumentIter<R> {
+    reader: JsonReader<R>,
+    done: bool,
+}
+
+impl<R: BufRead> Iterator for DocumentIter<R> {
+    type Item = Result<Spanned<Value>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let result = self.reader.guard(JsonReader::next_document).transpose();
+        self.done = !matches!(result, Some(Ok(_)));
+        result
+    }
+}
+
+/// Finds the end of the string starting at `start`, `None` when it carries on past `bytes`
+fn closing_quote(bytes: &[u8], start: usize) -> Option<usize> {
+    let mut index = start + 1;
+    while index < bytes.len() {
+        match bytes[index] {
+            b'\\' => index += 2,
+            b'"' => return Some(index + 1),
+            _ => index += 1,
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        from_reader, iter_array, iter_array_with, iter_documents, validate_stream,
+        validate_stream_with, Event, Handler, JsonReader,
+    };
+    use crate::{
+        parse, DuplicateKeyPolicy, ErrorCode, Map, ParseOptions, Span, Spanned, TokenParseError,
+        TokenizeError, Value,
+    };
+    use std::io::BufReader;
+
+    fn events(reader: &mut JsonReader<impl std::io::BufRead>) -> Vec<Event> {
+        let mut events = Vec::new();
+        while let Some(event) = reader.next_event().unwrap() {
+            events.push(event);
+        }
+        events
+    }
+
+    #[test]
+    fn reads_events() {
+        let input = r#"{"name": "a", "sizes": [3.5, true, null], "empty": {}}"#;
+        let expected = vec![
+            Event::StartObject,
+            Event::Key(String::from("name")),
+            Event::String(String::from("a")),
+            Event::Key(String::from("sizes")),
+            Event::StartArray,
+            Event::Number(3.5),
+            Event::Boolean(true),
+            Event::Null,
+            Event::EndArray,
+            Event::Key(String::from("empty")),
+            Event::StartObject,
+            Event::EndObject,
+            Event::EndObject,
+        ];
+
+        let actual = events(&mut JsonReader::new(input.as_bytes()));
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reads_byte_at_a_time() {
+        // every token, and the two byte `é`, is split across reads
+        let input = "[\"caf\u{e9}\", 12.5e1, false, /* x */ []]";
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let expected = vec![
+            Event::StartArray,
+            Event::String(String::from("caf\u{e9}")),
+            Event::Number(125.0),
+            Event::Boolean(false),
+            Event::StartArray,
+            Event::EndArray,
+            Event::EndArray,
+        ];
+
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let actual = events(&mut JsonReader::with_options(reader, options));
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn locates_errors() {
+        let input = "[1,\n 2,\n 3 4]";
+        let expected = (
+            ErrorCode::ParseError(TokenParseError::ExpectedComma),
+            11,
+            3,
+            4,
+        );
+
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let mut reader = JsonReader::new(reader);
+        let actual = loop {
+            match reader.next_event() {
+                Ok(_) => continue,
+                Err(err) => break (err.code, err.offset, err.line, err.column),
+            }
+        };
+
+        assert_eq!(actual, expected);
+        assert!(reader.next_event().is_err());
+    }
+
+    #[test]
+    fn trailing_tokens() {
+        let input = "1 2";
+        let expected = ErrorCode::ParseError(TokenParseError::TrailingTokens);
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::Number(1.0))));
+        let actual = reader.next_event().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn invalid_utf8() {
+        let input = b"[\"\xff\"]";
+        let expected = ErrorCode::TokenizeError(TokenizeError::InvalidUtf8);
+
+        let actual = JsonReader::new(&input[..]).next_event().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn handles_events() {
+        // picks out every "id" at any depth
+        #[derive(Default)]
+        struct Ids {
+            in_id: bool,
+            ids: Vec<f64>,
+        }
+        impl Handler for Ids {
+            fn on_key(&mut self, key: String) {
+                self.in_id = key == "id";
+            }
+            fn on_number(&mut self, value: f64) {
+                if self.in_id {
+                    self.ids.push(value);
+                }
+                self.in_id = false;
+            }
+        }
+        let input = r#"[{"id": 1, "tags": [7]}, {"name": "x", "id": 2}]"#;
+        let expected = vec![1.0, 2.0];
+
+        let mut handler = Ids::default();
+        JsonReader::new(input.as_bytes())
+            .handle(&mut handler)
+            .unwrap();
+
+        assert_eq!(handler.ids, expected);
+    }
+
+    #[test]
+    fn skips_values() {
+        let input = r#"{"skip": {"a": ["]", "\"}", [[]]], "b": 1}, "keep": [1, "x"], "last": 2}"#;
+        let expected = vec![
+            Event::Key(String::from("keep")),
+            Event::Key(String::from("last")),
+            Event::Number(2.0),
+            Event::EndObject,
+        ];
+
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let mut reader = JsonReader::new(reader);
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartObject)));
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Key(String::from("skip"))))
+        );
+        reader.skip_value().unwrap();
+        let mut actual = vec![reader.next_event().unwrap().unwrap()];
+        reader.skip_value().unwrap();
+        actual.extend(events(&mut reader));
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn skips_unclosed_value() {
+        let input = r#"["a", {"b": "]}"#;
+        let expected = ErrorCode::TokenizeError(TokenizeError::UnclosedQuotes);
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        reader.skip_value().unwrap();
+        let actual = reader.skip_value().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn iterates_array() {
+        let input = r#"[{"id": 1, "tags": ["a"]}, [], 3]"#;
+        let expected = vec![
+            Value::Object(Map::from([
+                (String::from("id"), Value::Number(1.0)),
+                (
+                    String::from("tags"),
+                    Value::Array(vec![Value::String(String::from("a"))]),
+                ),
+            ])),
+            Value::Array(Vec::new()),
+            Value::Number(3.0),
+        ];
+
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let actual: Result<Vec<_>, _> = iter_array(reader).collect();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn iterates_until_error() {
+        let input = "[1, 2 3]";
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedComma);
+
+        let mut elements = iter_array(input.as_bytes());
+        assert_eq!(elements.next(), Some(Ok(Value::Number(1.0))));
+        assert_eq!(elements.next(), Some(Ok(Value::Number(2.0))));
+        let actual = elements.next().unwrap().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+        assert_eq!(elements.next(), None);
+    }
+
+    #[test]
+    fn iterates_array_only() {
+        let input = r#"{"a": 1}"#;
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedArray);
+
+        let actual = iter_array(input.as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err()
+            .code;
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn iterates_duplicate_keys() {
+        let input = "[{\"a\": {\"b\": 1},\n \"a\": 2}]";
+        let options = ParseOptions {
+            duplicate_keys: DuplicateKeyPolicy::Error,
+            ..ParseOptions::default()
+        };
+        let expected = (
+            ErrorCode::ParseError(TokenParseError::DuplicateKey {
+                key: String::from("a"),
+                first: Span::new(2, 5),
+                first_line: 1,
+                first_column: 3,
+            }),
+            2,
+            2,
+        );
+
+        let err = iter_array_with(input.as_bytes(), &options)
+            .next()
+            .unwrap()
+            .unwrap_err();
+        let actual = (err.code, err.line, err.column);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn iterates_documents() {
+        let input = "{\"a\": 1} {\"b\": 2}\n3 [] ";
+        let object = |key: &str, number: f64| {
+            Value::Object(Map::from([(String::from(key), Value::Number(number))]))
+        };
+        let expected = vec![
+            Spanned::new(object("a", 1.0), Span::new(0, 8)),
+            Spanned::new(object("b", 2.0), Span::new(9, 17)),
+            Spanned::new(Value::Number(3.0), Span::new(18, 19)),
+            Spanned::new(Value::Array(Vec::new()), Span::new(20, 22)),
+        ];
+
+        let reader = BufReader::with_capacity(1, input.as_bytes());
+        let actual: Result<Vec<_>, _> = iter_documents(reader).collect();
+
+        assert_eq!(actual, Ok(expected));
+    }
+
+    #[test]
+    fn iterates_truncated_document() {
+        let input = "1 [2";
+        let expected = ErrorCode::ParseError(TokenParseError::UnexpectedEof);
+
+        let mut documents = iter_documents(input.as_bytes());
+        assert!(matches!(documents.next(), Some(Ok(_))));
+        let actual = documents.next().unwrap().unwrap_err().code;
+
+        assert_eq!(actual, expected);
+        assert!(documents.next().is_none());
+        assert!(iter_documents(&b"  "[..]).next().is_none());
+    }
+
+    #[test]
+    fn parses_from_reader() {
+        let input = "{\"a\": [1, {\"b\": null}], \"c\": \"\\u00e9\"}\n";
+        let expected = parse(String::from(input));
+
+        let reader = BufReader::with_capacity(3, input.as_bytes());
+        let actual = from_reader(reader);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reader_trailing_tokens() {
+        let cases = ["[1] 2", "", "[1"];
+
+        for input in cases {
+            let expected = parse(String::from(input)).unwrap_err().code;
+
+            let actual = from_reader(input.as_bytes()).unwrap_err().code;
+
+            assert_eq!(actual, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn tracks_path() {
+        let input = r#"{"records": [{"payload": 1}, 2, []], "a/b": null}"#;
+        let expected = [
+            "",
+            "/records",
+            "/records",
+            "/records/0",
+            "/records/0/payload",
+            "/records/0/payload",
+            "/records/0",
+            "/records/1",
+            "/records/2",
+            "/records/2",
+            "/records",
+            "/a~1b",
+            "/a~1b",
+            "",
+        ];
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let mut actual = Vec::new();
+        while reader.next_event().unwrap().is_some() {
+            actual.push(reader.current_path().to_string());
+        }
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn error_path() {
+        let input = r#"{"users": [{}, {}, {"address": [1, ]}]}"#;
+        let expected = "$.users[2].address[1]";
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let actual = loop {
+            match reader.next_event() {
+                Ok(Some(_)) => {}
+                Ok(None) => panic!("no error"),
+                Err(err) => break err.path.to_string(),
+            }
+        };
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn validates_stream() {
+        let cases = [
+            "{\"a\": [1, \"two\", {\"b\": null}]}",
+            "[1 2]",
+            "{\"a\": 1} 2",
+            "\"\\x\"",
+            "[[[",
+        ];
+
+        for input in cases {
+            let expected = crate::validate(input);
+
+            let actual = validate_stream(BufReader::with_capacity(1, input.as_bytes()));
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn validates_stream_limits() {
+        let input = r#"[{"a": 1, "b": 2}, {"c": [3, 4, 5]}]"#;
+        let cases = [
+            (
+                ParseOptions {
+                    max_input_len: Some(10),
+                    ..ParseOptions::default()
+                },
+                ErrorCode::TokenizeError(TokenizeError::InputTooLarge),
+                10,
+            ),
+            (
+                ParseOptions {
+                    max_array_elements: Some(4),
+                    ..ParseOptions::default()
+                },
+                ErrorCode::ParseError(TokenParseError::ArrayElementLimitExceeded),
+                32,
+            ),
+            (
+                ParseOptions {
+                    max_object_members: Some(2),
+                    ..ParseOptions::default()
+                },
+                ErrorCode::ParseError(TokenParseError::ObjectMemberLimitExceeded),
+                20,
+            ),
+        ];
+
+        for (options, code, offset) in cases {
+            let expected = (code, offset);
+
+            let err = validate_stream_with(input.as_bytes(), &options).unwrap_err();
+            let actual = (err.code, err.offset);
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn reads_values() {
+        let input = r#"{"skip": [1, 2], "keep": {"a": [true]}, "list": [1, {"b": null}, 3]}"#;
+        let expected = [
+            parse(String::from(r#"{"a": [true]}"#)).unwrap(),
+            parse(String::from(r#"{"b": null}"#)).unwrap(),
+            Value::Number(3.0),
+        ];
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let mut actual = Vec::new();
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartObject)));
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Key(String::from("skip"))))
+        );
+        reader.skip_value().unwrap();
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Key(String::from("keep"))))
+        );
+        actual.push(reader.read_value().unwrap());
+        assert_eq!(
+            reader.next_event(),
+            Ok(Some(Event::Key(String::from("list"))))
+        );
+        assert_eq!(reader.next_event(), Ok(Some(Event::StartArray)));
+        reader.skip_value().unwrap();
+        actual.push(reader.read_value().unwrap());
+        actual.push(reader.read_value().unwrap());
+
+        assert_eq!(actual, expected);
+        assert_eq!(events(&mut reader), [Event::EndArray, Event::EndObject]);
+    }
+
+    #[test]
+    fn reads_value_only_where_one_starts() {
+        let cases = [("[]", 1), (r#"{"a": 1}"#, 1), ("[1 2]", 2)];
+
+        for (input, start_events) in cases {
+            let mut reader = JsonReader::new(input.as_bytes());
+            for _ in 0..start_events {
+                reader.next_event().unwrap();
+            }
+            let actual = reader.read_value();
+
+            assert!(actual.is_err(), "{input}");
+        }
+    }
+}
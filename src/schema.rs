@@ -0,0 +1,432 @@
+//! JSON Schema support: inferring a schema from sample documents, and validating values
+//! against the core keywords of one
+
+use crate::pattern::Pattern;
+use crate::pointer::JsonPointer;
+use crate::{Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+/// The draft the inferred schemas declare themselves as
+const DRAFT: &str = "https://json-schema.org/draft/2020-12/schema";
+
+/// The most distinct strings a field may have to be written as an `enum`
+const MAX_ENUM_VALUES: usize = 10;
+
+/// Everything seen at one place across the samples
+#[derive(Debug, Default)]
+struct Shape {
+    nulls: bool,
+    booleans: bool,
+    integers: bool,
+    fractions: bool,
+    strings: usize,
+    /// the distinct strings, up to one more than an enum may have
+    string_values: BTreeSet<String>,
+    /// the shape of every element of every array
+    items: Option<Box<Shape>>,
+    objects: usize,
+    /// each key with how many objects had it and the shape of its values
+    properties: BTreeMap<String, (usize, Shape)>,
+}
+
+impl Shape {
+    fn observe(&mut self, value: &Value) {
+        match value {
+            Value::Null => self.nulls = true,
+            Value::Boolean(_) => self.booleans = true,
+            Value::Number(number) if number.fract() == 0.0 => self.integers = true,
+            Value::Number(_) => self.fractions = true,
+            Value::String(string) => {
+                self.strings += 1;
+                if self.string_values.len() <= MAX_ENUM_VALUES {
+                    self.string_values.insert(string.clone());
+                }
+            }
+            Value::Array(elements) => {
+                let items = self.items.get_or_insert_with(Box::default);
+                elements.iter().for_each(|element| items.observe(element));
+            }
+            Value::Object(members) => {
+                self.objects += 1;
+                for (key, member) in members {
+                    let (count, shape) = self.properties.entry(key.clone()).or_default();
+                    *count += 1;
+                    shape.observe(member);
+                }
+            }
+        }
+    }
+
+    /// The schema describing what was seen
+    fn to_schema(&self) -> Map<Value> {
+        let mut schema = Map::new();
+        let kinds = [
+            (self.nulls, "null"),
+            (self.booleans, "boolean"),
+            // a field holding both is a number, integers being numbers too
+            (self.integers && !self.fractions, "integer"),
+            (self.fractions, "number"),
+            (self.strings > 0, "string"),
+            (self.items.is_some(), "array"),
+            (self.objects > 0, "object"),
+        ];
+        let mut types: Vec<_> = kinds
+            .into_iter()
+            .filter(|(seen, _)| *seen)
+            .map(|(_, kind)| Value::from(kind))
+            .collect();
+        let only_strings = types == [Value::from("string")];
+        if types.len() == 1 {
+            schema.insert(String::from("type"), types.remove(0));
+        } else if !types.is_empty() {
+            schema.insert(String::from("type"), Value::Array(types));
+        }
+        // a string only counts as one of a few choices when the choices come up repeatedly
+        let values = &self.string_values;
+        if only_strings && values.len() <= MAX_ENUM_VALUES && values.len() < self.strings {
+            let values = values.iter().map(|value| Value::from(value.as_str()));
+            schema.insert(String::from("enum"), Value::Array(values.collect()));
+        }
+        if let Some(items) = &self.items {
+            schema.insert(String::from("items"), Value::Object(items.to_schema()));
+        }
+        if self.objects > 0 {
+            let properties = self
+                .properties
+                .iter()
+                .map(|(key, (_, shape))| (key.clone(), Value::Object(shape.to_schema())));
+            schema.insert(
+                String::from("properties"),
+                Value::Object(properties.collect()),
+            );
+            // a key missing from any of the objects is optional
+            let required: Vec<_> = self
+                .properties
+                .iter()
+                .filter(|(_, (count, _))| *count == self.objects)
+                .map(|(key, _)| Value::from(key.as_str()))
+                .collect();
+            if !required.is_empty() {
+                schema.insert(String::from("required"), Value::Array(required));
+            }
+        }
+        schema
+    }
+}
+
+/// A JSON Schema describing every one of the sample values: the types seen at each place,
+/// which object members are always there and so `required`, and an `enum` for strings that
+/// only ever take a few repeated values. Numbers that never have a fraction are `integer`.
+/// With no samples the schema accepts anything.
+pub fn infer_schema<'a>(values: impl Iterator<Item = &'a Value>) -> Value {
+    let mut shape = Shape::default();
+    values.for_each(|value| shape.observe(value));
+    let mut schema = shape.to_schema();
+    schema.insert(String::from("$schema"), Value::from(DRAFT));
+    Value::Object(schema)
+}
+
+/// One way a value breaks its schema
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    /// where in the value the problem is
+    pub path: JsonPointer,
+    /// the schema keyword that isn't met, e.g. `required`
+    pub keyword: &'static str,
+    /// what is wrong, for people
+    pub message: String,
+}
+
+impl fmt::Display for Violation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?}: {}", self.path.to_string(), self.message)
+    }
+}
+
+/// Checks `value` against `schema`, returning every violation rather than only the first.
+/// Understood are `type`, `properties`, `required`, `items`, `enum`, `minimum`, `maximum`
+/// and `pattern`, plus the `true` and `false` schemas, other keywords are ignored. A
+/// `pattern` outside the supported regex subset is itself reported as a violation.
+pub fn validate(schema: &Value, value: &Value) -> Result<(), Vec<Violation>> {
+    let mut violations = Vec::new();
+    check(schema, value, &mut JsonPointer::root(), &mut violations);
+    match violations.is_empty() {
+        true => Ok(()),
+        false => Err(violations),
+    }
+}
+
+fn check(schema: &Value, value: &Value, path: &mut JsonPointer, violations: &mut Vec<Violation>) {
+    let mut violate = |keyword, message| {
+        violations.push(Violation {
+            path: path.clone(),
+            keyword,
+            message,
+        })
+    };
+    let schema = match schema {
+        Value::Boolean(false) => return violate("false", String::from("no value is allowed")),
+        Value::Object(schema) => schema,
+        _ => return,
+    };
+    if let Some(types) = schema.get("type") {
+        let allowed: Vec<_> = match types {
+            Value::Array(types) => types.iter().filter_map(as_str).collect(),
+            types => as_str(types).into_iter().collect(),
+        };
+        if !allowed.iter().any(|kind| is_kind(value, kind)) {
+            let message = format!("expected {}, found {}", allowed.join(" or "), kind(value));
+            violate("type", message);
+        }
+    }
+    if let Some(Value::Array(choices)) = schema.get("enum") {
+        if !choices.contains(value) {
+            violate("enum", String::from("not one of the allowed values"));
+        }
+    }
+    if let Value::Number(number) = value {
+        if let Some(Value::Number(minimum)) = schema.get("minimum") {
+            if number < minimum {
+                violate(
+                    "minimum",
+                    format!("{number} is less than the minimum of {minimum}"),
+                );
+            }
+        }
+        if let Some(Value::Number(maximum)) = schema.get("maximum") {
+            if number > maximum {
+                violate(
+                    "maximum",
+                    format!("{number} is more than the maximum of {maximum}"),
+                );
+            }
+        }
+    }
+    if let (Value::String(string), Some(Value::String(pattern))) = (value, schema.get("pattern")) {
+        match Pattern::new(pattern) {
+            Some(compiled) if compiled.is_match(string) => {}
+            Some(_) => violate("pattern", format!("{string:?} doesn't match {pattern:?}")),
+            None => violate(
+                "pattern",
+                format!("the pattern {pattern:?} isn't supported"),
+            ),
+        }
+    }
+    match value {
+        Value::Object(members) => check_object(schema, members, path, violations),
+        Value::Array(elements) => check_array(schema, elements, path, violations),
+        _ => {}
+    }
+}
+
+fn check_object(
+    schema: &Map<Value>,
+    members: &Map<Value>,
+    path: &mut JsonPointer,
+    violations: &mut Vec<Violation>,
+) {
+    if let Some(Value::Array(required)) = schema.get("required") {
+        for key in required.iter().filter_map(as_str) {
+            if !members.contains_key(key) {
+                violations.push(Violation {
+                    path: path.clone(),
+                    keyword: "required",
+                    message: format!("missing the required member {key:?}"),
+                });
+            }
+        }
+    }
+    if let Some(Value::Object(properties)) = schema.get("properties") {
+        // in key order, so the violations come out the same every time
+        let mut properties: Vec<_> = properties.iter().collect();
+        properties.sort_unstable_by_key(|&(key, _)| key);
+        for (key, property) in properties {
+            if let Some(member) = members.get(key) {
+                path.push(key.clone());
+                check(property, member, path, violations);
+                path.pop();
+            }
+        }
+    }
+}
+
+fn check_array(
+    schema: &Map<Value>,
+    elements: &[Value],
+    path: &mut JsonPointer,
+    violations: &mut Vec<Violation>,
+) {
+    let Some(items) = schema.get("items") else {
+        return;
+    };
+    for (index, element) in elements.iter().enumerate() {
+        // an array of schemas checks the elements at the same positions, the rest are free
+        let item = match items {
+            Value::Array(positional) => match positional.get(index) {
+                Some(item) => item,
+                None => break,
+            },
+            items => items,
+        };
+        path.push(index.to_string());
+        check(item, element, path, violations);
+        path.pop();
+    }
+}
+
+fn as_str(value: &Value) -> Option<&str> {
+    match value {
+        Value::String(string) => Some(string),
+        _ => None,
+    }
+}
+
+/// Whether `value` is of the JSON Schema type `kind`, integers being numbers without a fraction
+fn is_kind(value: &Value, kind: &str) -> bool {
+    match (kind, value) {
+        ("integer", Value::Number(number)) => number.fract() == 0.0,
+        (kind, value) => kind == self::kind(value),
+    }
+}
+
+/// The JSON Schema type of `value`
+fn kind(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Boolean(_) => "boolean",
+        Value::Number(_) => "number",
+        Value::String(_) => "string",
+        Value::Array(_) => "array",
+        Value::Object(_) => "object",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{infer_schema, validate};
+    use crate::{parse, Value};
+
+    fn value(input: &str) -> Value {
+        parse(String::from(input)).unwrap()
+    }
+
+    #[test]
+    fn infers_from_samples() {
+        let input = [
+            r#"{"id": 1, "status": "open", "tags": ["a"], "owner": {"name": "x"}}"#,
+            r#"{"id": 2, "status": "closed", "tags": [], "score": 1.5, "owner": null}"#,
+            r#"{"id": 3, "status": "open", "tags": ["b", 2], "score": 2}"#,
+        ]
+        .map(|input| parse(String::from(input)).unwrap());
+        let expected = parse(String::from(
+            r#"{
+                "$schema": "https://json-schema.org/draft/2020-12/schema",
+                "type": "object",
+                "properties": {
+                    "id": {"type": "integer"},
+                    "status": {"type": "string", "enum": ["closed", "open"]},
+                    "tags": {"type": "array", "items": {"type": ["integer", "string"]}},
+                    "score": {"type": "number"},
+                    "owner": {
+                        "type": ["null", "object"],
+                        "properties": {"name": {"type": "string"}},
+                        "required": ["name"]
+                    }
+                },
+                "required": ["id", "status", "tags"]
+            }"#,
+        ))
+        .unwrap();
+
+        let actual = infer_schema(input.iter());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn infers_nothing_from_no_samples() {
+        let expected = parse(String::from(
+            r#"{"$schema": "https://json-schema.org/draft/2020-12/schema"}"#,
+        ))
+        .unwrap();
+
+        let actual = infer_schema([].iter());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn validates() {
+        let schema = value(
+            r#"{
+                "type": "object",
+                "required": ["id", "name"],
+                "properties": {
+                    "id": {"type": "integer", "minimum": 1},
+                    "name": {"type": "string", "pattern": "^[a-z]+$"},
+                    "role": {"enum": ["admin", "user"]},
+                    "scores": {"type": "array", "items": {"type": "number", "maximum": 10}},
+                    "pair": {"items": [{"type": "string"}, {"type": "null"}]},
+                    "never": false
+                }
+            }"#,
+        );
+        let input = value(
+            r#"{"id": 0.5, "role": "root", "scores": [1, 11, "x"], "pair": ["a", 1], "never": 1}"#,
+        );
+        let expected = vec![
+            r#""": missing the required member "name""#,
+            r#""/id": expected integer, found number"#,
+            r#""/id": 0.5 is less than the minimum of 1"#,
+            r#""/never": no value is allowed"#,
+            r#""/pair/1": expected null, found number"#,
+            r#""/role": not one of the allowed values"#,
+            r#""/scores/1": 11 is more than the maximum of 10"#,
+            r#""/scores/2": expected number, found string"#,
+        ];
+
+        let actual: Vec<_> = validate(&schema, &input)
+            .unwrap_err()
+            .iter()
+            .map(|violation| violation.to_string())
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn validates_patterns() {
+        let schema =
+            value(r#"{"properties": {"a": {"pattern": "\\d+"}, "b": {"pattern": "(?<=x)"}}}"#);
+        let cases = [
+            (r#"{"a": "x1"}"#, vec![]),
+            (r#"{"a": "x"}"#, vec![r#""/a": "x" doesn't match "\\d+""#]),
+            (
+                r#"{"b": "x"}"#,
+                vec![r#""/b": the pattern "(?<=x)" isn't supported"#],
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let actual: Vec<_> = match validate(&schema, &value(input)) {
+                Ok(()) => Vec::new(),
+                Err(violations) => violations.iter().map(|v| v.to_string()).collect(),
+            };
+
+            assert_eq!(actual, expected, "{input}");
+        }
+    }
+
+    #[test]
+    fn validates_inferred_schemas() {
+        let input = [value(r#"{"a": 1, "b": "x"}"#), value(r#"{"a": 2}"#)];
+        let schema = infer_schema(input.iter());
+
+        let accepted = input.iter().all(|input| validate(&schema, input).is_ok());
+        let rejected = validate(&schema, &value(r#"{"b": 1}"#));
+
+        assert!(accepted);
+        assert_eq!(rejected.unwrap_err().len(), 2);
+    }
+}
@@ -0,0 +1,197 @@
+use crate::error::Error;
+use crate::options::ParseOptions;
+use crate::parse::TokenParseError;
+use crate::span::Position;
+use crate::tokenize::TokenizeError;
+use crate::writer::{FlushPolicy, NdjsonWriter, WriteError};
+use crate::{parse_with, Value};
+use std::io::{BufRead, Write};
+
+/// The byte starting every record of a json text sequence
+pub const RECORD_SEPARATOR: u8 = 0x1e;
+
+/// Reads an RFC 7464 json text sequence (`application/json-seq`), yielding each record's value
+/// or the reason it is corrupt. Each record is parsed on its own, so reading carries on at the
+/// next record separator after a bad one.
+#[derive(Debug)]
+pub struct JsonSeqReader<R> {
+    reader: R,
+    options: ParseOptions,
+    /// the bytes of the record being parsed, up to and including the separator after it
+    record: Vec<u8>,
+    /// where the next record starts
+    position: Position,
+    /// whether the reader has failed or has no more input
+    done: bool,
+}
+
+impl<R: BufRead> JsonSeqReader<R> {
+    pub fn new(reader: R) -> Self {
+        Self::with_options(reader, ParseOptions::default())
+    }
+
+    pub fn with_options(reader: R, options: ParseOptions) -> Self {
+        Self {
+            reader,
+            options,
+            record: Vec::new(),
+            position: Position::default(),
+            done: false,
+        }
+    }
+
+    /// Parses the record just read, which started at `start`
+    fn parse_record(&self, start: Position) -> Result<Value, Error> {
+        let bytes = match self.record.last() {
+            Some(&RECORD_SEPARATOR) => &self.record[..self.record.len() - 1],
+            _ => &self.record[..],
+        };
+        // errors are found within the record, then moved to where the record is in the input
+        let relocate = |err| start.relocate(err);
+        let text = match std::str::from_utf8(bytes) {
+            Ok(text) => text,
+            Err(err) => {
+                let text = String::from_utf8_lossy(&bytes[..err.valid_up_to()]);
+                let err = Error::new(&text, text.len(), TokenizeError::InvalidUtf8.into());
+                return Err(relocate(err));
+            }
+        };
+        let value = parse_with(String::from(text), &self.options).map_err(relocate)?;
+        // a number without the newline ending every record may have been cut short
+        if matches!(value, Value::Number(_)) && !text.ends_with('\n') {
+            let err = Error::new(text, text.len(), TokenParseError::UnexpectedEof.into());
+            return Err(relocate(err));
+        }
+        Ok(value)
+    }
+}
+
+impl<R: BufRead> Iterator for JsonSeqReader<R> {
+    type Item = Result<Value, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while !self.done {
+            let start = self.position;
+            self.record.clear();
+            match self.reader.read_until(RECORD_SEPARATOR, &mut self.record) {
+                Ok(0) => self.done = true,
+                Ok(_) => {
+                    self.position.advance(&self.record);
+                    // separators may repeat, and nothing comes before the first one
+                    let empty = self.record.iter().all(|&byte| {
+                        matches!(byte, RECORD_SEPARATOR | b' ' | b'\t' | b'\n' | b'\r')
+                    });
+                    if !empty {
+                        return Some(self.parse_record(start));
+                    }
+                }
+                Err(err) => {
+                    self.done = true;
+                    let Position {
+                        offset,
+                        line,
+                        column,
+                    } = start;
+                    return Some(Err(Error::located(offset, line, column, err.into())));
+                }
+            }
+        }
+        None
+    }
+}
+
+/// Writes an RFC 7464 json text sequence, each value compact between a record separator and a
+/// newline
+#[derive(Debug)]
+pub struct JsonSeqWriter<W> {
+    writer: NdjsonWriter<W>,
+}
+
+impl<W: Write> JsonSeqWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_flush(writer, FlushPolicy::default())
+    }
+
+    pub fn with_flush(writer: W, flush: FlushPolicy) -> Self {
+        Self {
+            writer: NdjsonWriter::with_prefix(writer, flush, &[RECORD_SEPARATOR]),
+        }
+    }
+
+    /// Writes `value` as one record in a single write
+    pub fn write(&mut self, value: &Value) -> Result<(), WriteError> {
+        self.writer.write(value)
+    }
+
+    /// Writes every value in turn, stopping at the first that fails
+    pub fn write_all<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<(), WriteError> {
+        self.writer.write_all(values)
+    }
+
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        self.writer.flush()
+    }
+
+    /// Flushes and hands back the underlying writer
+    pub fn finish(self) -> Result<W, WriteError> {
+        self.writer.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{JsonSeqReader, JsonSeqWriter};
+    use crate::{ErrorCode, Map, TokenParseError, Value};
+
+    #[test]
+    fn round_trips() {
+        let values = vec![
+            Value::Object(Map::from([(String::from("a"), Value::Number(1.0))])),
+            Value::Number(2.0),
+        ];
+        let expected = "\x1e{\"a\":1}\n\x1e2\n";
+
+        let mut writer = JsonSeqWriter::new(Vec::new());
+        writer.write_all(&values).unwrap();
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+        let read: Result<Vec<_>, _> = JsonSeqReader::new(actual.as_bytes()).collect();
+        assert_eq!(read, Ok(values));
+    }
+
+    #[test]
+    fn resyncs_after_corrupt_record() {
+        let input = "\x1e[1,\n\x1e\x1e{\"a\": }\n\x1etrue\n\x1e12";
+        let expected = [
+            Err((
+                ErrorCode::ParseError(TokenParseError::UnexpectedEof),
+                5,
+                2,
+                1,
+            )),
+            Err((
+                ErrorCode::ParseError(TokenParseError::ExpectedValue),
+                13,
+                2,
+                9,
+            )),
+            Ok(Value::Boolean(true)),
+            Err((
+                ErrorCode::ParseError(TokenParseError::UnexpectedEof),
+                24,
+                4,
+                4,
+            )),
+        ];
+
+        let actual: Vec<_> = JsonSeqReader::new(input.as_bytes())
+            .map(|result| result.map_err(|err| (err.code, err.offset, err.line, err.column)))
+            .collect();
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,1617 @@
+use crate::span::{Span, Spanned};
+use crate::tokenize::{decode_string, tokenize_all};
+use crate::writer::{write_number, write_string_escaping, WriteError};
+use crate::{Map, ParseOptions, SpannedValue, Value};
+use std::cmp::Ordering;
+use std::fmt;
+use std::hash::Hasher;
+#[cfg(feature = "color")]
+use std::io::IsTerminal;
+use std::io::{self, BufWriter, Write};
+use std::ops::Range;
+use std::sync::Arc;
+
+/// How many bytes `to_writer_with` collects before writing them out, unless set
+const DEFAULT_BUFFER_SIZE: usize = 8 * 1024;
+
+/// What ends each line of indented output
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LineEnding {
+    #[default]
+    Lf,
+    CrLf,
+}
+
+impl LineEnding {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            LineEnding::Lf => "\n",
+            LineEnding::CrLf => "\r\n",
+        }
+    }
+}
+
+/// When numbers are written in exponent form, e.g. `1.5e21`, the digits are the fewest that
+/// read back as the same number either way
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Notation {
+    /// every digit is written out, however many zeros that takes
+    Never,
+    /// every number, `0` as `0e0`
+    Always,
+    /// when the exponent is below `min` or from `max` up, the default is where JavaScript
+    /// switches, `1e21` and up or `1e-7` and down
+    Outside { min: i32, max: i32 },
+}
+
+impl Default for Notation {
+    fn default() -> Self {
+        Notation::Outside { min: -6, max: 21 }
+    }
+}
+
+/// What to write for NaN and the infinities, which json has no way to represent
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum NonFinitePolicy {
+    /// fail with `NonFiniteNumber`, so nothing is silently lost
+    #[default]
+    Error,
+    /// write `null`, as JavaScript's `JSON.stringify` does
+    Null,
+    /// write `NaN`, `Infinity` and `-Infinity` as JSON5 does, which strict parsers reject
+    Literal,
+}
+
+type Compare = dyn Fn(&str, &str) -> Ordering + Send + Sync;
+
+/// Compares two keys for `SerializeOptions::key_order`
+#[derive(Clone)]
+struct KeyOrder(Arc<Compare>);
+
+impl fmt::Debug for KeyOrder {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("KeyOrder").finish_non_exhaustive()
+    }
+}
+
+type Matches = dyn Fn(&str) -> bool + Send + Sync;
+
+/// Picks out keys whose values `SerializeOptions::redact` hides
+#[derive(Clone)]
+struct Redact(Arc<Matches>);
+
+impl fmt::Debug for Redact {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redact").finish_non_exhaustive()
+    }
+}
+
+/// Written in place of a redacted value
+const REDACTED: &[u8] = b"\"[REDACTED]\"";
+
+/// How `to_string_with` and `to_writer_with` lay out their output, the default is compact json
+/// with no whitespace at all. Built up a setting at a time, e.g.
+/// `SerializeOptions::pretty().indent(4).line_ending(LineEnding::CrLf)`.
+#[derive(Debug, Clone)]
+pub struct SerializeOptions {
+    /// how many `indent_unit`s each level is indented by, `None` keeps everything on one line
+    indent: Option<usize>,
+    indent_unit: String,
+    /// starts every line
+    line_prefix: String,
+    space_after_colon: bool,
+    line_ending: LineEnding,
+    trailing_newline: bool,
+    sort_keys: bool,
+    key_order: Option<KeyOrder>,
+    redact: Vec<Redact>,
+    escape_non_ascii: bool,
+    escape_html: bool,
+    notation: Notation,
+    non_finite: NonFinitePolicy,
+    /// whether `-0` keeps its sign, though it equals `0`
+    negative_zero: bool,
+    /// the widest an array or object kept on one line may reach
+    max_width: Option<usize>,
+    buffer_size: usize,
+    #[cfg(feature = "color")]
+    color: bool,
+}
+
+impl Default for SerializeOptions {
+    fn default() -> Self {
+        Self {
+            indent: None,
+            indent_unit: String::from(" "),
+            line_prefix: String::new(),
+            space_after_colon: false,
+            line_ending: LineEnding::default(),
+            trailing_newline: false,
+            sort_keys: false,
+            key_order: None,
+            redact: Vec::new(),
+            escape_non_ascii: false,
+            escape_html: false,
+            notation: Notation::default(),
+            non_finite: NonFinitePolicy::default(),
+            negative_zero: true,
+            max_width: None,
+            buffer_size: DEFAULT_BUFFER_SIZE,
+            #[cfg(feature = "color")]
+            color: false,
+        }
+    }
+}
+
+impl SerializeOptions {
+    /// Compact output, the same as the default
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every element and member on its own line indented by two spaces, with a space after
+    /// each colon
+    pub fn pretty() -> Self {
+        Self::new().indent(2).space_after_colon(true)
+    }
+
+    /// Output that is the same bytes for any two equal values, for cache keys, deduplication
+    /// and hashing: compact, members ordered by the UTF-16 code units of their keys as in
+    /// RFC 8785, numbers in their shortest form with `-0` as `0`, and only the escapes json
+    /// requires
+    pub fn canonical() -> Self {
+        let mut options = Self::new().key_order(|a, b| a.encode_utf16().cmp(b.encode_utf16()));
+        options.negative_zero = false;
+        options
+    }
+
+    /// Puts every element and member on its own line, indented by `width` indent chars a level
+    pub fn indent(mut self, width: usize) -> Self {
+        self.indent = Some(width);
+        self
+    }
+
+    /// What indentation is made of, a space unless set
+    pub fn indent_char(mut self, indent_char: char) -> Self {
+        self.indent_unit = String::from(indent_char);
+        self
+    }
+
+    /// Puts every element and member on its own line, indented by `unit` a level, e.g. `"\t"`
+    pub fn indent_string(mut self, unit: impl Into<String>) -> Self {
+        self.indent = Some(1);
+        self.indent_unit = unit.into();
+        self
+    }
+
+    /// Starts every line of output with `prefix`, e.g. spaces to nest it in a Markdown list
+    pub fn line_prefix(mut self, prefix: impl Into<String>) -> Self {
+        self.line_prefix = prefix.into();
+        self
+    }
+
+    pub fn space_after_colon(mut self, space: bool) -> Self {
+        self.space_after_colon = space;
+        self
+    }
+
+    pub fn line_ending(mut self, line_ending: LineEnding) -> Self {
+        self.line_ending = line_ending;
+        self
+    }
+
+    /// Ends the output with a line ending, as text files usually are
+    pub fn trailing_newline(mut self, trailing: bool) -> Self {
+        self.trailing_newline = trailing;
+        self
+    }
+
+    /// Writes object members in the order of their keys rather than the map's own order, so
+    /// equal values always give the same output
+    pub fn sort_keys(mut self, sort: bool) -> Self {
+        self.sort_keys = sort;
+        self
+    }
+
+    /// Writes the members of every object in the order `compare` puts their keys, e.g. `id`
+    /// first and metadata last, in place of `sort_keys`
+    pub fn key_order(
+        mut self,
+        compare: impl Fn(&str, &str) -> Ordering + Send + Sync + 'static,
+    ) -> Self {
+        self.key_order = Some(KeyOrder(Arc::new(compare)));
+        self
+    }
+
+    /// Writes `"[REDACTED]"` in place of the value of every member whose key `matches`, however
+    /// deeply it is nested, so secrets held in a Value never reach logs. Each call adds to what
+    /// is hidden.
+    pub fn redact(mut self, matches: impl Fn(&str) -> bool + Send + Sync + 'static) -> Self {
+        self.redact.push(Redact(Arc::new(matches)));
+        self
+    }
+
+    /// Redacts the values of members with any of `keys`, ignoring ASCII case, e.g.
+    /// `["password", "token"]`
+    pub fn redact_keys<K: Into<String>>(self, keys: impl IntoIterator<Item = K>) -> Self {
+        let keys: Vec<String> = keys.into_iter().map(Into::into).collect();
+        self.redact(move |key| keys.iter().any(|hidden| hidden.eq_ignore_ascii_case(key)))
+    }
+
+    /// Writes every char outside ASCII as a `\u` escape, so the output is plain ASCII that
+    /// survives systems that mangle UTF-8
+    pub fn escape_non_ascii(mut self, escape: bool) -> Self {
+        self.escape_non_ascii = escape;
+        self
+    }
+
+    /// Writes `<`, `>`, `&`, U+2028 and U+2029 as `\u` escapes, so the output can go inside a
+    /// `<script>` tag without closing it or breaking older JavaScript parsers
+    pub fn escape_html(mut self, escape: bool) -> Self {
+        self.escape_html = escape;
+        self
+    }
+
+    /// When numbers switch to exponent form, as some consumers reject it
+    pub fn notation(mut self, notation: Notation) -> Self {
+        self.notation = notation;
+        self
+    }
+
+    pub fn non_finite(mut self, policy: NonFinitePolicy) -> Self {
+        self.non_finite = policy;
+        self
+    }
+
+    /// When indenting, keeps an array or object on one line as `[1, 2]` if it ends within
+    /// `width` columns, so only long ones are spread over several lines
+    pub fn max_width(mut self, width: usize) -> Self {
+        self.max_width = Some(width);
+        self
+    }
+
+    /// How many bytes `to_writer_with` collects before passing them to its writer in one write,
+    /// 8 KiB unless set. `0` passes every piece of output on as it is made, for a writer that is
+    /// already buffered.
+    pub fn buffer_size(mut self, size: usize) -> Self {
+        self.buffer_size = size;
+        self
+    }
+
+    /// Colors keys, strings, numbers and literals with ANSI escapes, for showing in a terminal.
+    /// `to_terminal` turns it on only when the output is one.
+    #[cfg(feature = "color")]
+    pub fn color(mut self, color: bool) -> Self {
+        self.color = color;
+        self
+    }
+}
+
+impl SerializeOptions {
+    /// Whether the value of a member with `key` is hidden
+    fn redacts(&self, key: &str) -> bool {
+        self.redact.iter().any(|Redact(matches)| matches(key))
+    }
+
+    /// Whether the options want `c` escaped beyond what json requires
+    fn escapes(&self, c: char) -> bool {
+        self.escape_non_ascii && !c.is_ascii()
+            || self.escape_html && matches!(c, '<' | '>' | '&' | '\u{2028}' | '\u{2029}')
+    }
+}
+
+/// Serializes `value` as compact json
+pub fn to_string(value: &Value) -> Result<String, WriteError> {
+    to_string_with(value, &SerializeOptions::new())
+}
+
+pub fn to_string_with(value: &Value, options: &SerializeOptions) -> Result<String, WriteError> {
+    let output = to_vec_with(value, options)?;
+    // everything written is either valid UTF-8 from a string or ASCII
+    Ok(String::from_utf8(output).expect("serialized json is UTF-8"))
+}
+
+/// Serializes `value` as compact json, as the UTF-8 bytes to hand to a socket or compressor
+pub fn to_vec(value: &Value) -> Result<Vec<u8>, WriteError> {
+    to_vec_with(value, &SerializeOptions::new())
+}
+
+/// Serializes `value` indented as `SerializeOptions::pretty` lays it out
+pub fn to_vec_pretty(value: &Value) -> Result<Vec<u8>, WriteError> {
+    to_vec_with(value, &SerializeOptions::pretty())
+}
+
+pub fn to_vec_with(value: &Value, options: &SerializeOptions) -> Result<Vec<u8>, WriteError> {
+    let mut output = Vec::with_capacity(value.estimated_json_len());
+    write_document(&mut output, value, options)?;
+    Ok(output)
+}
+
+/// Writes `value` to `writer` as compact json
+pub fn to_writer(writer: impl Write, value: &Value) -> Result<(), WriteError> {
+    to_writer_with(writer, value, &SerializeOptions::new())
+}
+
+/// Same as `to_writer` but laid out as `options` say. Output is collected in a buffer of the
+/// options' `buffer_size` so an unbuffered writer such as a file or socket isn't written to a
+/// token at a time. Everything is flushed, through to `writer` itself, before this returns.
+pub fn to_writer_with(
+    writer: impl Write,
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    let mut writer = BufWriter::with_capacity(options.buffer_size, writer);
+    write_document(&mut writer, value, options)?;
+    Ok(writer.flush()?)
+}
+
+/// Serializes a parsed document with each string written the way it was in `source`, the text
+/// it was parsed from, so `"caf\u00e9"` stays escaped. That matters to anything that diffs or
+/// signs the text. A string that has been changed since, or whose original form the options
+/// would escape differently, is written afresh, as are keys, which carry no span.
+pub fn to_string_preserving(
+    value: &Spanned<SpannedValue>,
+    source: &str,
+) -> Result<String, WriteError> {
+    to_string_preserving_with(value, source, &SerializeOptions::new())
+}
+
+pub fn to_string_preserving_with(
+    value: &Spanned<SpannedValue>,
+    source: &str,
+    options: &SerializeOptions,
+) -> Result<String, WriteError> {
+    let mut output = Vec::with_capacity(source.len());
+    write_tree(&mut output, value, Some(source), &[], options)?;
+    // everything written is either valid UTF-8 from a string or the source, or ASCII
+    Ok(String::from_utf8(output).expect("serialized json is UTF-8"))
+}
+
+/// Serializes a document parsed with `ParseOptions::allow_comments` back to JSONC with its
+/// comments kept, so a config file can be read, have a field changed and be written back.
+/// Each comment goes with the member or element after it, or stays on the line of the one
+/// before it when it followed that on the same line. Members come out in the order they were
+/// written and strings as `to_string_preserving` writes them.
+pub fn to_jsonc(value: &Spanned<SpannedValue>, source: &str) -> Result<String, WriteError> {
+    to_jsonc_with(value, source, &SerializeOptions::pretty())
+}
+
+/// Same as `to_jsonc` but laid out as `options` say, a line comment still ends its line in
+/// compact output
+pub fn to_jsonc_with(
+    value: &Spanned<SpannedValue>,
+    source: &str,
+    options: &SerializeOptions,
+) -> Result<String, WriteError> {
+    let commented = ParseOptions {
+        allow_comments: true,
+        ..ParseOptions::default()
+    };
+    let comments = tokenize_all(source, &commented, true).comments;
+    let mut output = Vec::with_capacity(source.len());
+    write_tree(&mut output, value, Some(source), &comments, options)?;
+    // everything written is either valid UTF-8 from a string or the source, or ASCII
+    Ok(String::from_utf8(output).expect("serialized json is UTF-8"))
+}
+
+/// Writes `value` and the trailing newline the options ask for
+pub(crate) fn write_document(
+    writer: impl Write,
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    write_tree(writer, value, None, &[], options)
+}
+
+fn write_tree(
+    writer: impl Write,
+    tree: &impl Tree,
+    source: Option<&str>,
+    comments: &[Span],
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    let mut serializer = Serializer {
+        writer: Columns::new(writer, None),
+        options,
+        source,
+        comments,
+        last_end: 0,
+        inline: false,
+    };
+    serializer
+        .writer
+        .write_all(options.line_prefix.as_bytes())?;
+    serializer.comments_before(tree, 0)?;
+    serializer.value(tree, 0)?;
+    serializer.trailing_comments()?;
+    serializer.comments_within(usize::MAX, 0)?;
+    if options.trailing_newline {
+        serializer
+            .writer
+            .write_all(options.line_ending.as_str().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// What stands in for whatever `Value::preview` leaves out
+const ELIDED: &str = "…";
+
+/// The deepest `Value::preview` goes whatever `max_depth` asks for, the parser's default limit,
+/// so neither it nor serializing what it returns can run out of stack
+const MAX_PREVIEW_DEPTH: usize = 128;
+
+/// Serializes `value` cut down to a size that is safe to log, as `Value::preview` cuts it. The
+/// output is still json, keys sorted so the members kept are the same every time, with
+/// non-finite numbers as `null`. A real `"…"` member can't be told apart from the marker, see
+/// `Value::preview`.
+pub fn to_string_truncated(
+    value: &Value,
+    max_depth: usize,
+    max_items: usize,
+    max_string_len: usize,
+) -> Result<String, WriteError> {
+    let limits = PreviewLimits {
+        max_depth,
+        max_items,
+        max_string_len,
+    };
+    let options = SerializeOptions::new()
+        .sort_keys(true)
+        .non_finite(NonFinitePolicy::Null);
+    to_string_with(&value.preview(&limits), &options)
+}
+
+/// The bounds `Value::preview` cuts a value down to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PreviewLimits {
+    /// how many arrays and objects deep are kept before the rest become placeholders, at most
+    /// 128
+    pub max_depth: usize,
+    /// how many elements of each array and members of each object are kept
+    pub max_items: usize,
+    /// how many chars of each string are kept
+    pub max_string_len: usize,
+}
+
+impl Default for PreviewLimits {
+    fn default() -> Self {
+        Self {
+            max_depth: 4,
+            max_items: 10,
+            max_string_len: 80,
+        }
+    }
+}
+
+impl Value {
+    /// A reduced copy that is safe to show in a UI or log however large the document is.
+    /// Arrays and objects nested more than `max_depth` deep become `["…"]` and `{"…":"…"}`,
+    /// past the first `max_items` elements or members the rest are replaced by one `"…"`
+    /// element or `"…":"…"` member, and strings past `max_string_len` chars end in `…`. The
+    /// members kept are the first keys in sorted order, so the same every time.
+    ///
+    /// The marker member shares its key with any real `"…"` member, which it replaces when the
+    /// object is cut short, and a kept `"…"` member looks like the marker.
+    pub fn preview(&self, limits: &PreviewLimits) -> Value {
+        let limits = PreviewLimits {
+            max_depth: limits.max_depth.min(MAX_PREVIEW_DEPTH),
+            ..*limits
+        };
+        truncate(self, 0, &limits)
+    }
+}
+
+/// A copy of the parts of `value`, which is `depth` containers deep, that fit within `limits`
+fn truncate(value: &Value, depth: usize, limits: &PreviewLimits) -> Value {
+    let elided = || Value::String(String::from(ELIDED));
+    match value {
+        Value::String(string) => match string.char_indices().nth(limits.max_string_len) {
+            Some((end, _)) => Value::String(format!("{}{ELIDED}", &string[..end])),
+            None => value.clone(),
+        },
+        Value::Array(elements) if depth >= limits.max_depth && !elements.is_empty() => {
+            Value::Array(vec![elided()])
+        }
+        Value::Object(members) if depth >= limits.max_depth && !members.is_empty() => {
+            Value::Object(Map::from([(String::from(ELIDED), elided())]))
+        }
+        Value::Array(elements) => {
+            let mut kept: Vec<_> = elements
+                .iter()
+                .take(limits.max_items)
+                .map(|element| truncate(element, depth + 1, limits))
+                .collect();
+            if elements.len() > limits.max_items {
+                kept.push(elided());
+            }
+            Value::Array(kept)
+        }
+        Value::Object(members) => {
+            let mut keys: Vec<_> = members.keys().collect();
+            keys.sort_unstable();
+            let mut kept: Map<_> = keys
+                .into_iter()
+                .take(limits.max_items)
+                .map(|key| (key.clone(), truncate(&members[key], depth + 1, limits)))
+                .collect();
+            if members.len() > limits.max_items {
+                kept.insert(String::from(ELIDED), elided());
+            }
+            Value::Object(kept)
+        }
+        Value::Null | Value::Boolean(_) | Value::Number(_) => value.clone(),
+    }
+}
+
+/// The most bytes the shortest form of a finite number can take, e.g. `-1.2345678901234567e-100`
+const MAX_NUMBER_LEN: usize = 24;
+
+impl Value {
+    /// Roughly how many bytes `to_string` makes of this value, without serializing it, for
+    /// sizing a buffer or turning away a payload that is too large up front. Exact apart from
+    /// numbers with a fraction or an exponent, which count as the longest a number can be, so
+    /// the estimate errs on the high side. Whitespace from other options isn't counted.
+    pub fn estimated_json_len(&self) -> usize {
+        // an explicit stack rather than recursion, so no document is too deep to measure
+        let mut pending = vec![self];
+        let mut len = 0;
+        while let Some(value) = pending.pop() {
+            len += match value {
+                Value::Null | Value::Boolean(true) => 4,
+                Value::Boolean(false) => 5,
+                Value::String(string) => string_len(string),
+                Value::Number(number) => number_len(*number),
+                Value::Array(elements) => {
+                    pending.extend(elements);
+                    2 + elements.len().saturating_sub(1)
+                }
+                Value::Object(members) => {
+                    let keys: usize = members.keys().map(|key| string_len(key) + 1).sum();
+                    pending.extend(members.values());
+                    2 + members.len().saturating_sub(1) + keys
+                }
+            };
+        }
+        len
+    }
+
+    /// A stable 64 bit fingerprint of the value, e.g. for an ETag: the FNV-1a hash of its
+    /// canonical form, so equal values always have the same digest, on any platform and in
+    /// any version. Fails only for a non-finite number.
+    pub fn canonical_digest(&self) -> Result<u64, WriteError> {
+        self.canonical_digest_with(Fnv1a::default())
+    }
+
+    /// Same as `canonical_digest` but hashed by `hasher`, for a stronger or keyed hash
+    pub fn canonical_digest_with<H: Hasher>(&self, hasher: H) -> Result<u64, WriteError> {
+        let mut writer = HashWriter(hasher);
+        write_document(&mut writer, self, &SerializeOptions::canonical())?;
+        Ok(writer.0.finish())
+    }
+}
+
+/// The 64 bit FNV-1a hash, simple and fixed for good unlike std's `DefaultHasher`
+struct Fnv1a(u64);
+
+impl Default for Fnv1a {
+    fn default() -> Self {
+        Self(0xcbf2_9ce4_8422_2325)
+    }
+}
+
+impl Hasher for Fnv1a {
+    fn write(&mut self, bytes: &[u8]) {
+        for &byte in bytes {
+            self.0 = (self.0 ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3);
+        }
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// Feeds everything written to it into a hasher
+struct HashWriter<H>(H);
+
+impl<H: Hasher> Write for HashWriter<H> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf);
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// How long `string` is once quoted and escaped
+fn string_len(string: &str) -> usize {
+    let escapes: usize = string
+        .bytes()
+        .map(|byte| match byte {
+            b'"' | b'\\' | b'\n' | b'\r' | b'\t' | 0x08 | 0x0c => 1,
+            // `\u00XX`
+            0..=0x1f => 5,
+            _ => 0,
+        })
+        .sum();
+    string.len() + 2 + escapes
+}
+
+/// How long `number` is written out, exact for whole numbers short enough to need no exponent
+fn number_len(number: f64) -> usize {
+    if number.fract() != 0.0 || number.abs() >= 1e15 {
+        return MAX_NUMBER_LEN;
+    }
+    let sign = usize::from(number.is_sign_negative());
+    let mut digits = 1;
+    let mut rest = number.abs();
+    while rest >= 10.0 {
+        rest /= 10.0;
+        digits += 1;
+    }
+    sign + digits
+}
+
+/// Serializes a tree of any type implementing `Tree`, laid out as `options` say
+pub fn to_string_tree(tree: &impl Tree, options: &SerializeOptions) -> Result<String, WriteError> {
+    let mut output = Vec::new();
+    write_tree(&mut output, tree, None, &[], options)?;
+    // everything written is either valid UTF-8 from a string or raw node, or ASCII
+    Ok(String::from_utf8(output).expect("serialized json is UTF-8"))
+}
+
+/// Writes a tree of any type implementing `Tree` to `writer`, buffered as `to_writer_with` is
+pub fn to_writer_tree(
+    writer: impl Write,
+    tree: &impl Tree,
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    let mut writer = BufWriter::with_capacity(options.buffer_size, writer);
+    write_tree(&mut writer, tree, None, &[], options)?;
+    Ok(writer.flush()?)
+}
+
+/// Writes `value` as `to_writer_with` does, colored when `writer` is a terminal and plain when
+/// it is redirected to a file or pipe
+#[cfg(feature = "color")]
+pub fn to_terminal(
+    writer: impl Write + IsTerminal,
+    value: &Value,
+    options: &SerializeOptions,
+) -> Result<(), WriteError> {
+    let options = options.clone().color(writer.is_terminal());
+    to_writer_with(writer, value, &options)
+}
+
+/// What a piece of colored output is
+#[derive(Debug, Clone, Copy)]
+enum Color {
+    Key,
+    String,
+    Number,
+    Literal,
+}
+
+#[cfg(feature = "color")]
+impl Color {
+    fn escape(self) -> &'static [u8] {
+        match self {
+            Color::Key => b"\x1b[1;34m",
+            Color::String => b"\x1b[32m",
+            Color::Number => b"\x1b[36m",
+            Color::Literal => b"\x1b[35m",
+        }
+    }
+}
+
+/// Passes output on while counting the column it has reached, leaving out ANSI escapes
+struct Columns<W> {
+    writer: W,
+    column: usize,
+    /// fails any write past this column
+    limit: Option<usize>,
+    /// whether an escape sequence is being written
+    escape: bool,
+}
+
+impl<W> Columns<W> {
+    fn new(writer: W, limit: Option<usize>) -> Self {
+        Self {
+            writer,
+            column: 0,
+            limit,
+            escape: false,
+        }
+    }
+}
+
+impl<W: Write> Write for Columns<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        for &byte in buf {
+            match byte {
+                b'\n' => self.column = 0,
+                0x1b => self.escape = true,
+                b'm' if self.escape => self.escape = false,
+                _ if self.escape => {}
+                // continuation bytes are part of the char before them
+                0x80..=0xbf => {}
+                _ => self.column += 1,
+            }
+        }
+        if self.limit.is_some_and(|limit| self.column > limit) {
+            return Err(io::Error::other("past the column limit"));
+        }
+        self.writer.write_all(buf)?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// One node of a tree being serialized, with its children
+#[derive(Debug)]
+pub enum Node<'a, T> {
+    Null,
+    Boolean(bool),
+    String(&'a str),
+    Number(f64),
+    Array(&'a [T]),
+    Object(&'a Map<T>),
+    /// json text written out as it is, for a node the other variants can't describe, e.g.
+    /// a date as `"2024-01-31"`. Nothing checks that it is valid json.
+    Raw(String),
+}
+
+/// What the serializer can write: a `Value`, a parsed document that knows where each of its
+/// nodes came from, or a value type of an embedder's own, such as one with extra variants,
+/// which then gets every layout option without a serializer of its own
+pub trait Tree: Sized {
+    fn node(&self) -> Node<'_, Self>;
+
+    /// where the node was parsed from, if anywhere
+    fn span(&self) -> Option<Span> {
+        None
+    }
+}
+
+impl Tree for Value {
+    fn node(&self) -> Node<'_, Self> {
+        match self {
+            Value::Null => Node::Null,
+            Value::Boolean(boolean) => Node::Boolean(*boolean),
+            Value::String(string) => Node::String(string),
+            Value::Number(number) => Node::Number(*number),
+            Value::Array(elements) => Node::Array(elements),
+            Value::Object(members) => Node::Object(members),
+        }
+    }
+}
+
+impl Tree for Spanned<SpannedValue> {
+    fn node(&self) -> Node<'_, Self> {
+        match &self.value {
+            SpannedValue::Null => Node::Null,
+            SpannedValue::Boolean(boolean) => Node::Boolean(*boolean),
+            SpannedValue::String(string) => Node::String(string),
+            SpannedValue::Number(number) => Node::Number(*number),
+            SpannedValue::Array(elements) => Node::Array(elements),
+            SpannedValue::Object(members) => Node::Object(members),
+        }
+    }
+
+    fn span(&self) -> Option<Span> {
+        Some(self.span)
+    }
+}
+
+/// Writes values laid out as `options` say
+struct Serializer<'a, W> {
+    writer: Columns<W>,
+    options: &'a SerializeOptions,
+    /// the text the tree was parsed from, to copy strings out of as they were written
+    source: Option<&'a str>,
+    /// the comments in `source` yet to be written, in order
+    comments: &'a [Span],
+    /// where the last value written ended in `source`
+    last_end: usize,
+    /// whether the array or object being written is kept on one line
+    inline: bool,
+}
+
+impl<'a, W: Write> Serializer<'a, W> {
+    fn value(&mut self, value: &impl Tree, depth: usize) -> Result<(), WriteError> {
+        match value.node() {
+            Node::Null => self.literal(Color::Literal, b"null")?,
+            Node::Boolean(true) => self.literal(Color::Literal, b"true")?,
+            Node::Boolean(false) => self.literal(Color::Literal, b"false")?,
+            Node::Number(number) if !number.is_finite() => self.non_finite(number)?,
+            Node::Number(number) => self.paint(Color::Number, |serializer| {
+                // adding zero turns `-0` into `0` and leaves everything else as it is
+                let number = match serializer.options.negative_zero {
+                    true => number,
+                    false => number + 0.0,
+                };
+                write_number(&mut serializer.writer, number, serializer.options.notation)
+            })?,
+            Node::String(string) => self.paint(Color::String, |serializer| {
+                match serializer.original(string, value.span()) {
+                    Some(raw) => Ok(serializer.writer.write_all(raw.as_bytes())?),
+                    None => serializer.string(string),
+                }
+            })?,
+            Node::Raw(json) => self.writer.write_all(json.as_bytes())?,
+            Node::Array([]) if !self.commented(value) => self.writer.write_all(b"[]")?,
+            Node::Object(members) if members.is_empty() && !self.commented(value) => {
+                self.writer.write_all(b"{}")?
+            }
+            Node::Array(_) | Node::Object(_) if !self.inline && self.fits(value) => {
+                self.inline = true;
+                let written = self.value(value, depth);
+                self.inline = false;
+                written?;
+            }
+            Node::Array(elements) => {
+                self.writer.write_all(b"[")?;
+                for (i, element) in elements.iter().enumerate() {
+                    self.separate(i, depth + 1)?;
+                    self.comments_before(element, depth + 1)?;
+                    self.value(element, depth + 1)?;
+                }
+                self.close(value, depth)?;
+                self.writer.write_all(b"]")?;
+            }
+            Node::Object(members) => {
+                self.writer.write_all(b"{")?;
+                let mut members: Vec<_> = members.iter().collect();
+                if let Some(KeyOrder(compare)) = &self.options.key_order {
+                    members.sort_unstable_by(|(a, _), (b, _)| compare(a, b));
+                } else if self.options.sort_keys && !cfg!(feature = "sorted_maps") {
+                    // a BTreeMap is in key order already
+                    members.sort_unstable_by_key(|(key, _)| *key);
+                } else if self.source.is_some() {
+                    // as they were written, any added since go last
+                    members.sort_by_key(|(_, member)| match member.span() {
+                        Some(span) if span != Span::default() => span.start,
+                        _ => usize::MAX,
+                    });
+                }
+                for (i, (key, member)) in members.into_iter().enumerate() {
+                    self.separate(i, depth + 1)?;
+                    self.comments_before(member, depth + 1)?;
+                    self.paint(Color::Key, |serializer| serializer.string(key))?;
+                    match self.options.space_after_colon {
+                        true => self.writer.write_all(b": ")?,
+                        false => self.writer.write_all(b":")?,
+                    }
+                    if self.options.redacts(key) {
+                        self.literal(Color::String, REDACTED)?;
+                    } else {
+                        self.value(member, depth + 1)?;
+                    }
+                }
+                self.close(value, depth)?;
+                self.writer.write_all(b"}")?;
+            }
+        }
+        if let Some(span) = value.span().filter(|span| *span != Span::default()) {
+            self.last_end = span.end;
+        }
+        Ok(())
+    }
+
+    fn non_finite(&mut self, number: f64) -> Result<(), WriteError> {
+        let literal: &[u8] = match (self.options.non_finite, number.is_nan()) {
+            (NonFinitePolicy::Error, _) => return Err(WriteError::NonFiniteNumber),
+            (NonFinitePolicy::Null, _) => b"null",
+            (NonFinitePolicy::Literal, true) => b"NaN",
+            (NonFinitePolicy::Literal, false) if number > 0.0 => b"Infinity",
+            (NonFinitePolicy::Literal, false) => b"-Infinity",
+        };
+        self.literal(Color::Number, literal)
+    }
+
+    fn literal(&mut self, color: Color, literal: &[u8]) -> Result<(), WriteError> {
+        self.paint(color, |serializer| {
+            Ok(serializer.writer.write_all(literal)?)
+        })
+    }
+
+    /// Runs `write` in `color` when the output is colored
+    #[cfg_attr(not(feature = "color"), allow(unused_variables))]
+    fn paint(
+        &mut self,
+        color: Color,
+        write: impl FnOnce(&mut Self) -> Result<(), WriteError>,
+    ) -> Result<(), WriteError> {
+        #[cfg(feature = "color")]
+        if self.options.color {
+            self.writer.write_all(color.escape())?;
+            write(self)?;
+            self.writer.write_all(b"\x1b[0m")?;
+            return Ok(());
+        }
+        write(self)
+    }
+
+    fn string(&mut self, string: &str) -> Result<(), WriteError> {
+        write_string_escaping(&mut self.writer, string, |c| self.options.escapes(c))?;
+        Ok(())
+    }
+
+    /// The quoted string as it was written in the source, if it still reads as `string` and
+    /// has nothing in it the options want escaped
+    fn original(&self, string: &str, span: Option<Span>) -> Option<&'a str> {
+        let raw = self.source?.get(span?.start..span?.end)?;
+        let inner = raw.strip_prefix('"')?.strip_suffix('"')?;
+        let decoded = decode_string(inner, &ParseOptions::default()).ok()?;
+        let escapes = inner.chars().any(|c| self.options.escapes(c));
+        (decoded == string && !escapes).then_some(raw)
+    }
+
+    /// Whether `value` would end within `max_width` if kept on one line
+    fn fits(&self, value: &impl Tree) -> bool {
+        let (Some(_), Some(max_width)) = (self.options.indent, self.options.max_width) else {
+            return false;
+        };
+        let Some(width) = max_width.checked_sub(self.writer.column) else {
+            return false;
+        };
+        if self.commented(value) {
+            return false;
+        }
+        let mut measure = Serializer {
+            writer: Columns::new(io::sink(), Some(width)),
+            options: self.options,
+            source: self.source,
+            comments: &[],
+            last_end: 0,
+            inline: true,
+        };
+        measure.value(value, 0).is_ok()
+    }
+
+    /// Whether there are comments to write within `value`
+    fn commented(&self, value: &impl Tree) -> bool {
+        match (value.span(), self.comments.first()) {
+            (Some(span), Some(comment)) => span != Span::default() && comment.start < span.end,
+            _ => false,
+        }
+    }
+
+    /// Writes the comments before where `value` was, each followed by a new line at `depth`
+    fn comments_before(&mut self, value: &impl Tree, depth: usize) -> Result<(), WriteError> {
+        let Some(span) = value.span().filter(|span| *span != Span::default()) else {
+            return Ok(());
+        };
+        while let Some((&comment, rest)) = self.comments.split_first() {
+            if comment.start >= span.start {
+                break;
+            }
+            self.comments = rest;
+            self.comment(comment)?;
+            self.newline(depth)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the comments that were on the same line after the last value written
+    fn trailing_comments(&mut self) -> Result<(), WriteError> {
+        let Some(source) = self.source else {
+            return Ok(());
+        };
+        while let Some((&comment, rest)) = self.comments.split_first() {
+            match source.get(self.last_end..comment.start) {
+                Some(between) if !between.contains('\n') => {}
+                _ => break,
+            }
+            self.comments = rest;
+            self.writer.write_all(b" ")?;
+            self.comment(comment)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the comments before `end`, each on a new line at `depth`
+    fn comments_within(&mut self, end: usize, depth: usize) -> Result<(), WriteError> {
+        while let Some((&comment, rest)) = self.comments.split_first() {
+            if comment.start >= end {
+                break;
+            }
+            self.comments = rest;
+            self.newline(depth)?;
+            self.comment(comment)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the comments left in `container` and starts the line its closer goes on
+    fn close(&mut self, container: &impl Tree, depth: usize) -> Result<(), WriteError> {
+        if let Some(span) = container.span() {
+            self.trailing_comments()?;
+            self.comments_within(span.end, depth + 1)?;
+        }
+        self.newline(depth)
+    }
+
+    /// Copies a comment from the source, ending the line after a `//` one if nothing else will
+    fn comment(&mut self, comment: Span) -> Result<(), WriteError> {
+        let text = self
+            .source
+            .and_then(|source| source.get(Range::from(comment)))
+            .unwrap_or_default();
+        self.writer.write_all(text.as_bytes())?;
+        if text.starts_with("//") && self.options.indent.is_none() {
+            self.writer
+                .write_all(self.options.line_ending.as_str().as_bytes())?;
+        }
+        Ok(())
+    }
+
+    /// Writes what goes before the `index`th element or member, which is at `depth`
+    fn separate(&mut self, index: usize, depth: usize) -> Result<(), WriteError> {
+        if index > 0 {
+            self.writer.write_all(b",")?;
+            self.trailing_comments()?;
+        }
+        match self.inline {
+            true if index > 0 => Ok(self.writer.write_all(b" ")?),
+            true => Ok(()),
+            false => self.newline(depth),
+        }
+    }
+
+    /// Starts a new line indented for `depth`, unless everything is on one line
+    fn newline(&mut self, depth: usize) -> Result<(), WriteError> {
+        let Some(width) = self.options.indent.filter(|_| !self.inline) else {
+            return Ok(());
+        };
+        self.writer
+            .write_all(self.options.line_ending.as_str().as_bytes())?;
+        self.writer.write_all(self.options.line_prefix.as_bytes())?;
+        for _ in 0..width * depth {
+            self.writer.write_all(self.options.indent_unit.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        to_jsonc, to_string, to_string_preserving, to_string_preserving_with, to_string_tree,
+        to_string_truncated, to_string_with, to_vec, to_vec_pretty, to_writer_with, LineEnding,
+        Node, NonFinitePolicy, Notation, PreviewLimits, SerializeOptions, Tree,
+    };
+    use crate::{
+        parse, parse_spanned, parse_spanned_with, parse_with, Map, ParseOptions, SpannedValue,
+        Value, WriteError,
+    };
+    use std::io::{self, Write};
+
+    fn sample() -> Value {
+        Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![
+                Value::Number(1.5),
+                Value::Object(Map::new()),
+                Value::Object(Map::from([(String::from("b"), Value::Null)])),
+            ]),
+        )]))
+    }
+
+    #[test]
+    fn serializes_compact() {
+        let expected = r#"{"a":[1.5,{},{"b":null}]}"#;
+
+        let actual = to_string(&sample()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn serializes_pretty() {
+        let expected = "{\n  \"a\": [\n    1.5,\n    {},\n    {\n      \"b\": null\n    }\n  ]\n}";
+
+        let actual = to_string_with(&sample(), &SerializeOptions::pretty()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn applies_options() {
+        let options = SerializeOptions::new()
+            .indent(1)
+            .indent_char('\t')
+            .line_ending(LineEnding::CrLf)
+            .trailing_newline(true);
+        let expected =
+            "{\r\n\t\"a\":[\r\n\t\t1.5,\r\n\t\t{},\r\n\t\t{\r\n\t\t\t\"b\":null\r\n\t\t}\r\n\t]\r\n}\r\n";
+
+        let actual = to_string_with(&sample(), &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn round_trips_pretty() {
+        let input = r#"{"x": [true, "é\n", -0.25, []], "y": {"z": [{}]}, "w": "\u0001"}"#;
+        let expected = parse(String::from(input)).unwrap();
+
+        let output = to_string_with(&expected, &SerializeOptions::pretty()).unwrap();
+        let actual = parse(output).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn rejects_non_finite_numbers() {
+        let value = Value::Array(vec![Value::Number(f64::INFINITY)]);
+
+        let actual = to_string_with(&value, &SerializeOptions::pretty());
+
+        assert!(matches!(actual, Err(WriteError::NonFiniteNumber)));
+    }
+
+    #[test]
+    fn sorts_keys() {
+        let input = r#"{"b": 1, "a": {"z": [], "é": 2, "Z": 3}, "aa": null, "": 0}"#;
+        let expected = r#"{"":0,"a":{"Z":3,"z":[],"é":2},"aa":null,"b":1}"#;
+
+        let value = parse(String::from(input)).unwrap();
+        let actual = to_string_with(&value, &SerializeOptions::new().sort_keys(true)).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn escapes_non_ascii() {
+        let value = Value::Object(Map::from([(
+            String::from("clé"),
+            Value::String(String::from("aé€😀\n")),
+        )]));
+        let expected = r#"{"cl\u00e9":"a\u00e9\u20ac\ud83d\ude00\n"}"#;
+
+        let actual =
+            to_string_with(&value, &SerializeOptions::new().escape_non_ascii(true)).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(parse(actual).unwrap(), value);
+    }
+
+    #[test]
+    fn escapes_html() {
+        let value = Value::String(String::from("</script><b>&amp;\u{2028}\u{2029}é"));
+        let expected = r#""\u003c/script\u003e\u003cb\u003e\u0026amp;\u2028\u2029é""#;
+
+        let actual = to_string_with(&value, &SerializeOptions::new().escape_html(true)).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(parse(actual).unwrap(), value);
+    }
+
+    #[test]
+    fn applies_notation() {
+        let value = Value::Array(vec![Value::Number(1e21), Value::Number(1e-7)]);
+        let expected = "[1000000000000000000000,0.0000001]";
+
+        let options = SerializeOptions::new().notation(Notation::Never);
+        let actual = to_string_with(&value, &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn applies_non_finite_policy() {
+        let value = Value::Array(vec![
+            Value::Number(f64::NAN),
+            Value::Number(f64::INFINITY),
+            Value::Number(f64::NEG_INFINITY),
+        ]);
+        let cases = [
+            (NonFinitePolicy::Null, "[null,null,null]"),
+            (NonFinitePolicy::Literal, "[NaN,Infinity,-Infinity]"),
+        ];
+
+        for (policy, expected) in cases {
+            let options = SerializeOptions::new().non_finite(policy);
+            let actual = to_string_with(&value, &options).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn colors_output() {
+        let value = Value::Object(Map::from([(
+            String::from("a"),
+            Value::Array(vec![
+                Value::Number(1.0),
+                Value::String(String::new()),
+                Value::Null,
+            ]),
+        )]));
+        let expected =
+            "{\x1b[1;34m\"a\"\x1b[0m:[\x1b[36m1\x1b[0m,\x1b[32m\"\"\x1b[0m,\x1b[35mnull\x1b[0m]}";
+
+        let actual = to_string_with(&value, &SerializeOptions::new().color(true)).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[cfg(feature = "color")]
+    #[test]
+    fn colors_only_terminals() {
+        let path = std::env::temp_dir().join(format!("json-parser-{}-color", std::process::id()));
+        let value = Value::Array(vec![Value::Boolean(true)]);
+
+        let file = std::fs::File::create(&path).unwrap();
+        super::to_terminal(file, &value, &SerializeOptions::new()).unwrap();
+        let actual = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(actual, "[true]");
+    }
+
+    #[test]
+    fn keeps_narrow_containers_inline() {
+        let value = Value::Object(Map::from([(
+            String::from("list"),
+            Value::Array(vec![
+                Value::Array(vec![Value::Number(1.0), Value::Number(2.0)]),
+                Value::Array(vec![Value::Number(3.0), Value::Number(4.0)]),
+            ]),
+        )]));
+        let cases = [
+            (20, "{\n  \"list\": [\n    [1, 2],\n    [3, 4]\n  ]\n}"),
+            (26, r#"{"list": [[1, 2], [3, 4]]}"#),
+            (5, "{\n  \"list\": [\n    [\n      1,\n      2\n    ],\n    [\n      3,\n      4\n    ]\n  ]\n}"),
+        ];
+
+        for (width, expected) in cases {
+            let options = SerializeOptions::pretty().max_width(width);
+            let actual = to_string_with(&value, &options).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn buffers_writes() {
+        // counts writes and flushes of the writer underneath
+        #[derive(Default)]
+        struct Calls {
+            writes: usize,
+            flushes: usize,
+        }
+        impl Write for Calls {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.writes += 1;
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                self.flushes += 1;
+                Ok(())
+            }
+        }
+        let value = Value::Array(vec![Value::Number(1.0); 100]);
+        let cases = [
+            (SerializeOptions::new(), 1),
+            (SerializeOptions::new().buffer_size(64), 4),
+            (SerializeOptions::new().buffer_size(0), 201),
+        ];
+
+        for (options, expected) in cases {
+            let mut calls = Calls::default();
+            to_writer_with(&mut calls, &value, &options).unwrap();
+            let actual = calls.writes;
+
+            assert_eq!(actual, expected);
+            assert_eq!(calls.flushes, 1);
+        }
+    }
+
+    #[test]
+    fn estimates_length() {
+        let cases = [
+            r#"{"a\nb": [null, true, false, 0, -12, 100000, "\u0001\"é"], "c": {}}"#,
+            "[[], {}, \"\"]",
+            "-0",
+        ];
+
+        for input in cases {
+            let value = parse(String::from(input)).unwrap();
+            let expected = to_string(&value).unwrap().len();
+
+            let actual = value.estimated_json_len();
+
+            assert_eq!(actual, expected, "{input}");
+        }
+        let value = Value::Array(vec![Value::Number(0.5), Value::Number(1e300)]);
+        assert!(value.estimated_json_len() >= to_string(&value).unwrap().len());
+    }
+
+    #[test]
+    fn serializes_to_bytes() {
+        let value = parse(String::from(r#"{"a": ["é", 1]}"#)).unwrap();
+        let expected = (
+            r#"{"a":["é",1]}"#.as_bytes().to_vec(),
+            "{\n  \"a\": [\n    \"é\",\n    1\n  ]\n}"
+                .as_bytes()
+                .to_vec(),
+        );
+
+        let actual = (to_vec(&value).unwrap(), to_vec_pretty(&value).unwrap());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn preserves_string_escapes() {
+        let input = r#"{"a": ["caf\u00e9", "\/", "né", 1.50]}"#;
+        let cases = [
+            (
+                SerializeOptions::new(),
+                r#"{"a":["caf\u00e9","\/","né",1.5]}"#,
+            ),
+            (
+                SerializeOptions::new().escape_non_ascii(true),
+                r#"{"a":["caf\u00e9","\/","n\u00e9",1.5]}"#,
+            ),
+        ];
+        let mut value = parse_spanned(String::from(input)).unwrap();
+
+        for (options, expected) in cases {
+            let actual = to_string_preserving_with(&value, input, &options).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+        let SpannedValue::Object(members) = &mut value.value else {
+            panic!("expected an object");
+        };
+        let SpannedValue::Array(elements) = &mut members.get_mut("a").unwrap().value else {
+            panic!("expected an array");
+        };
+        elements[1].value = SpannedValue::String(String::from("é"));
+        let expected = r#"{"a":["caf\u00e9","é","né",1.5]}"#;
+        let actual = to_string_preserving(&value, input).unwrap();
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn orders_keys() {
+        let value = parse(String::from(r#"{"b": 1, "meta": 2, "id": 3, "a": 4}"#)).unwrap();
+        let rank = |key: &str| match key {
+            "id" => 0,
+            "meta" => 2,
+            _ => 1,
+        };
+        let options = SerializeOptions::new()
+            .sort_keys(true)
+            .key_order(move |a, b| rank(a).cmp(&rank(b)).then(a.cmp(b)));
+        let expected = r#"{"id":3,"a":4,"b":1,"meta":2}"#;
+
+        let actual = to_string_with(&value, &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn truncates_for_logging() {
+        let input =
+            r#"{"d": 4, "c": [1, 2, 3, [4]], "b": "abcdef", "a": {"x": {"y": 1}, "z": {}}}"#;
+        let value = parse(String::from(input)).unwrap();
+        let expected = r#"{"a":{"x":{"…":"…"},"z":{}},"b":"abc…","c":[1,2,3,"…"],"…":"…"}"#;
+
+        let actual = to_string_truncated(&value, 2, 3, 3).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn previews() {
+        let input = parse(String::from(
+            r#"{"items": [1, 2, 3], "name": "abcdef", "x": [[[]]]}"#,
+        ))
+        .unwrap();
+        let limits = PreviewLimits {
+            max_depth: 2,
+            max_items: 2,
+            max_string_len: 4,
+        };
+        let expected = parse(String::from(
+            r#"{"items": [1, 2, "…"], "name": "abcd…", "…": "…"}"#,
+        ))
+        .unwrap();
+
+        let actual = input.preview(&limits);
+
+        assert_eq!(actual, expected);
+        assert_eq!(input.preview(&PreviewLimits::default()), input);
+    }
+
+    #[test]
+    fn previews_deep_values() {
+        let depth = 20_000;
+        let input = (0..depth).fold(Value::Null, |value, _| Value::Array(vec![value]));
+        let limits = PreviewLimits {
+            max_depth: usize::MAX,
+            ..PreviewLimits::default()
+        };
+        let expected = format!("{}\"…\"{}", "[".repeat(129), "]".repeat(129));
+
+        let actual = to_string_truncated(&input, limits.max_depth, 10, 80).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(input.estimated_json_len(), 2 * depth + 4);
+        // dropping recurses too, so the nesting is taken apart a level at a time
+        let mut rest = input;
+        while let Value::Array(mut elements) = rest {
+            rest = elements.pop().unwrap_or(Value::Null);
+        }
+    }
+
+    #[test]
+    fn preview_marker_replaces_real_member() {
+        let input = parse(String::from(r#"{"a": 1, "b": 2, "…": 3}"#)).unwrap();
+        let limits = PreviewLimits {
+            max_items: 1,
+            ..PreviewLimits::default()
+        };
+        let expected = parse(String::from(r#"{"a": 1, "…": "…"}"#)).unwrap();
+
+        let actual = input.preview(&limits);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn redacts_secrets() {
+        let input =
+            r#"{"user": "ann", "Password": "hunter2", "auth": {"token": [1], "api_key": null}}"#;
+        let value = parse(String::from(input)).unwrap();
+        let options = SerializeOptions::new()
+            .sort_keys(true)
+            .redact_keys(["password", "token"])
+            .redact(|key| key.ends_with("_key"));
+        let expected = r#"{"Password":"[REDACTED]","auth":{"api_key":"[REDACTED]","token":"[REDACTED]"},"user":"ann"}"#;
+
+        let actual = to_string_with(&value, &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn serializes_canonically() {
+        let cases = [
+            (
+                r#"{"b": -0, "a": [1.0, "\u00e9"], "\ud83d\ude00": 1, "\uff61": 2}"#,
+                r#"{"a":[1,"é"],"b":0,"😀":1,"｡":2}"#,
+            ),
+            (
+                r#"{"\uff61": 2, "\ud83d\ude00": 1, "a": [1e0, "é"], "b": 0}"#,
+                r#"{"a":[1,"é"],"b":0,"😀":1,"｡":2}"#,
+            ),
+        ];
+
+        for (input, expected) in cases {
+            let value = parse(String::from(input)).unwrap();
+
+            let actual = to_string_with(&value, &SerializeOptions::canonical()).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[test]
+    fn digests_canonically() {
+        let cases = [
+            (r#"{"a": [1, null]}"#, Ok(0xa20d_a29d_f5b8_8b02)),
+            (r#"{ "a" : [1.0, null] }"#, Ok(0xa20d_a29d_f5b8_8b02)),
+        ];
+
+        for (input, expected) in cases {
+            let value = parse(String::from(input)).unwrap();
+
+            let actual = value.canonical_digest().map_err(|err| err.to_string());
+
+            assert_eq!(actual, expected);
+        }
+        let a = parse(String::from(r#"{"x": 0, "y": 1}"#)).unwrap();
+        let b = parse(String::from(r#"{"y": 1, "x": -0}"#)).unwrap();
+        let c = parse(String::from(r#"{"x": 1, "y": 0}"#)).unwrap();
+        assert_eq!(a.canonical_digest().unwrap(), b.canonical_digest().unwrap());
+        assert_ne!(a.canonical_digest().unwrap(), c.canonical_digest().unwrap());
+        assert!(Value::Number(f64::NAN).canonical_digest().is_err());
+    }
+
+    #[test]
+    fn indents_with_strings() {
+        let value = parse(String::from(r#"{"a": [1]}"#)).unwrap();
+        let cases = [
+            (
+                SerializeOptions::new().indent_string("\t"),
+                "{\n\t\"a\":[\n\t\t1\n\t]\n}",
+            ),
+            (
+                SerializeOptions::pretty()
+                    .line_prefix("    ")
+                    .trailing_newline(true),
+                "    {\n      \"a\": [\n        1\n      ]\n    }\n",
+            ),
+            (SerializeOptions::new().line_prefix("> "), r#"> {"a":[1]}"#),
+        ];
+
+        for (options, expected) in cases {
+            let actual = to_string_with(&value, &options).unwrap();
+
+            assert_eq!(actual, expected);
+        }
+    }
+
+    #[cfg(feature = "sorted_maps")]
+    #[test]
+    fn keeps_members_sorted() {
+        let value = parse(String::from(r#"{"c": 1, "a": {"z": 2, "b": 3}, "b": 4}"#)).unwrap();
+        let expected = r#"{"a":{"b":3,"z":2},"b":4,"c":1}"#;
+
+        let actual = to_string(&value).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn writes_jsonc() {
+        let input = "// settings\n{\n  /* the port */\n  \"port\": 80, // default\n  \"hosts\": [\"a\" /* first */, \"b\"],\n  \"extra\": {\n    // none yet\n  }\n}\n// end\n";
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let mut value = parse_spanned_with(String::from(input), &options).unwrap();
+        let SpannedValue::Object(members) = &mut value.value else {
+            panic!("expected an object");
+        };
+        members.get_mut("port").unwrap().value = SpannedValue::Number(8080.0);
+        let expected = "// settings\n{\n  /* the port */\n  \"port\": 8080, // default\n  \"hosts\": [\n    \"a\", /* first */\n    \"b\"\n  ],\n  \"extra\": {\n    // none yet\n  }\n}\n// end";
+
+        let actual = to_jsonc(&value, input).unwrap();
+
+        assert_eq!(actual, expected);
+        let edited = r#"{"port": 8080, "hosts": ["a", "b"], "extra": {}}"#;
+        assert_eq!(
+            parse_with(actual, &options).unwrap(),
+            parse(String::from(edited)).unwrap()
+        );
+    }
+
+    #[test]
+    fn serializes_custom_trees() {
+        // a value type with a variant of its own
+        enum Custom {
+            Number(f64),
+            List(Vec<Custom>),
+            Date(u32, u32, u32),
+        }
+        impl Tree for Custom {
+            fn node(&self) -> Node<'_, Self> {
+                match self {
+                    Custom::Number(number) => Node::Number(*number),
+                    Custom::List(elements) => Node::Array(elements),
+                    Custom::Date(year, month, day) => {
+                        Node::Raw(format!("\"{year:04}-{month:02}-{day:02}\""))
+                    }
+                }
+            }
+        }
+        let tree = Custom::List(vec![Custom::Number(1.0), Custom::Date(2024, 1, 31)]);
+        let expected = "[\n  1,\n  \"2024-01-31\"\n]";
+
+        let actual = to_string_tree(&tree, &SerializeOptions::pretty()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,173 @@
+/// Returns the index of the first non-whitespace byte at or after `index`,
+/// which is where the next structural character or value starts.
+/// Returns `bytes.len()` when only whitespace remains.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub fn skip_whitespace(bytes: &[u8], index: usize) -> usize {
+    use std::arch::x86_64::*;
+
+    let mut index = index;
+    // SAFETY: sse2 is part of the x86_64 baseline so the intrinsics are always available,
+    // and the loop condition guarantees 16 readable bytes from `index` for each load
+    unsafe {
+        let space = _mm_set1_epi8(b' ' as i8);
+        let tab = _mm_set1_epi8(b'\t' as i8);
+        let new_line = _mm_set1_epi8(b'\n' as i8);
+        let carriage_return = _mm_set1_epi8(b'\r' as i8);
+
+        // compare 16 bytes at a time against the four json whitespace characters
+        while index + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(index) as *const __m128i);
+            let is_whitespace = _mm_or_si128(
+                _mm_or_si128(_mm_cmpeq_epi8(chunk, space), _mm_cmpeq_epi8(chunk, tab)),
+                _mm_or_si128(
+                    _mm_cmpeq_epi8(chunk, new_line),
+                    _mm_cmpeq_epi8(chunk, carriage_return),
+                ),
+            );
+            // one bit per byte, set when the byte is whitespace
+            let mask = _mm_movemask_epi8(is_whitespace) as u32;
+            if mask != 0xFFFF {
+                return index + (!mask).trailing_zeros() as usize;
+            }
+            index += 16;
+        }
+    }
+
+    // finish off the tail that doesn't fill a whole register
+    skip_whitespace_scalar(bytes, index)
+}
+
+/// Returns the index of the first non-whitespace byte at or after `index`,
+/// which is where the next structural character or value starts.
+/// Returns `bytes.len()` when only whitespace remains.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub fn skip_whitespace(bytes: &[u8], index: usize) -> usize {
+    skip_whitespace_scalar(bytes, index)
+}
+
+/// Returns the index of the first structural byte at or after `index`, one of `"`, `\`, `,`,
+/// `:`, `[`, `]`, `{` or `}`, so whatever sits between them can be stepped over at once.
+/// Returns `bytes.len()` when none remain.
+#[cfg(all(feature = "simd", target_arch = "x86_64"))]
+pub fn next_structural(bytes: &[u8], index: usize) -> usize {
+    use std::arch::x86_64::*;
+
+    let mut index = index;
+    // SAFETY: as in `skip_whitespace`, sse2 is always available on x86_64 and the loop
+    // condition guarantees 16 readable bytes from `index` for each load
+    unsafe {
+        let structural = STRUCTURAL.map(|byte| _mm_set1_epi8(byte as i8));
+
+        while index + 16 <= bytes.len() {
+            let chunk = _mm_loadu_si128(bytes.as_ptr().add(index) as *const __m128i);
+            let is_structural = structural.iter().fold(_mm_setzero_si128(), |found, &byte| {
+                _mm_or_si128(found, _mm_cmpeq_epi8(chunk, byte))
+            });
+            // one bit per byte, set when the byte is structural
+            let mask = _mm_movemask_epi8(is_structural) as u32;
+            if mask != 0 {
+                return index + mask.trailing_zeros() as usize;
+            }
+            index += 16;
+        }
+    }
+
+    next_structural_scalar(bytes, index)
+}
+
+/// Returns the index of the first structural byte at or after `index`, one of `"`, `\`, `,`,
+/// `:`, `[`, `]`, `{` or `}`, so whatever sits between them can be stepped over at once.
+/// Returns `bytes.len()` when none remain.
+#[cfg(not(all(feature = "simd", target_arch = "x86_64")))]
+pub fn next_structural(bytes: &[u8], index: usize) -> usize {
+    next_structural_scalar(bytes, index)
+}
+
+/// The bytes that delimit strings and containers
+const STRUCTURAL: [u8; 8] = [b'"', b'\\', b',', b':', b'[', b']', b'{', b'}'];
+
+fn next_structural_scalar(bytes: &[u8], index: usize) -> usize {
+    match bytes.get(index..) {
+        Some(rest) => rest
+            .iter()
+            .position(|byte| STRUCTURAL.contains(byte))
+            .map_or(bytes.len(), |at| index + at),
+        None => bytes.len(),
+    }
+}
+
+fn skip_whitespace_scalar(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len() && matches!(bytes[index], b' ' | b'\t' | b'\n' | b'\r') {
+        index += 1;
+    }
+    index
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{next_structural, next_structural_scalar, skip_whitespace, skip_whitespace_scalar};
+
+    #[test]
+    fn no_whitespace() {
+        let input = b"[1]";
+
+        let actual = skip_whitespace(input, 0);
+
+        assert_eq!(actual, 0);
+    }
+
+    #[test]
+    fn only_whitespace() {
+        let input = b" \t\r\n                                ";
+
+        let actual = skip_whitespace(input, 0);
+
+        assert_eq!(actual, input.len());
+    }
+
+    #[test]
+    fn long_run_then_value() {
+        let input = format!("{}\n\t{}", " ".repeat(40), "true");
+        let expected = input.len() - 4;
+
+        let actual = skip_whitespace(input.as_bytes(), 0);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn matches_scalar_at_every_offset() {
+        let input = b"  {\n    \"key\" :\t\t  [ 1 , 2 ]\r\n                   }   ";
+
+        for index in 0..input.len() {
+            assert_eq!(
+                skip_whitespace(input, index),
+                skip_whitespace_scalar(input, index)
+            );
+        }
+    }
+
+    #[test]
+    fn finds_structural_past_a_long_run() {
+        let input = format!("\"{}\\n\", 1]", "a".repeat(40));
+        let expected = 41;
+
+        let actual = next_structural(input.as_bytes(), 1);
+
+        assert_eq!(actual, expected);
+        assert_eq!(next_structural(b"plain text", 0), 10);
+        assert_eq!(next_structural(b"", 3), 0);
+    }
+
+    #[test]
+    fn structural_matches_scalar_at_every_offset() {
+        let input = b"{\"a long key with spaces\": [\"esc\\\"aped\", 1, 2], \"b\": {}}      tail";
+
+        for index in 0..=input.len() {
+            assert_eq!(
+                next_structural(input, index),
+                next_structural_scalar(input, index)
+            );
+        }
+    }
+}
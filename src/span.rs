@@ -0,0 +1,167 @@
+use crate::error::Error;
+use std::ops::Range;
+
+/// Byte offsets into the input, `start` inclusive and `end` exclusive
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    pub fn new(start: usize, end: usize) -> Self {
+        Self { start, end }
+    }
+}
+
+impl From<Span> for Range<usize> {
+    fn from(span: Span) -> Self {
+        span.start..span.end
+    }
+}
+
+/// Anything paired with the place in the input it came from
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Spanned<T> {
+    pub value: T,
+    pub span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+}
+
+/// Works out the 1-based line and column of a byte offset, columns count chars not bytes
+pub fn line_column(input: &str, offset: usize) -> (usize, usize) {
+    // back up onto a char boundary so slicing can't panic
+    let mut offset = offset.min(input.len());
+    while !input.is_char_boundary(offset) {
+        offset -= 1;
+    }
+    let before = &input[..offset];
+
+    let line = before.matches('\n').count() + 1;
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    let column = before[line_start..].chars().count() + 1;
+    (line, column)
+}
+
+/// A place in input that is read a piece at a time, for locating errors once the text before
+/// them has been dropped
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) struct Position {
+    /// byte offset into the whole input
+    pub offset: usize,
+    /// 1-based line, as in `line_column`
+    pub line: usize,
+    /// 1-based column, as in `line_column`
+    pub column: usize,
+}
+
+impl Default for Position {
+    fn default() -> Self {
+        Self {
+            offset: 0,
+            line: 1,
+            column: 1,
+        }
+    }
+}
+
+impl Position {
+    /// Moves past `bytes`, which must be whole chars
+    pub fn advance(&mut self, bytes: &[u8]) {
+        self.offset += bytes.len();
+        for &byte in bytes {
+            match byte {
+                b'\n' => {
+                    self.line += 1;
+                    self.column = 1;
+                }
+                // continuation bytes are part of the char before them
+                0x80..=0xbf => {}
+                _ => self.column += 1,
+            }
+        }
+    }
+
+    /// The line and column of `offset` in `text`, which starts at this position
+    pub fn locate(&self, text: &str, offset: usize) -> (usize, usize) {
+        let (line, column) = line_column(text, offset);
+        match line {
+            1 => (self.line, self.column + column - 1),
+            _ => (self.line + line - 1, column),
+        }
+    }
+
+    /// Moves an error found in text starting at this position to its place in the whole input
+    pub fn relocate(&self, err: Error) -> Error {
+        Error {
+            offset: self.offset + err.offset,
+            line: self.line + err.line - 1,
+            column: match err.line {
+                1 => self.column + err.column - 1,
+                _ => err.column,
+            },
+            ..err
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{line_column, Position};
+
+    #[test]
+    fn first_char() {
+        let input = "{}";
+
+        let actual = line_column(input, 0);
+
+        assert_eq!(actual, (1, 1));
+    }
+
+    #[test]
+    fn later_line() {
+        let input = "{\n  \"a\": 1,\n  \"b\" 2\n}";
+        let offset = input.find('2').unwrap();
+
+        let actual = line_column(input, offset);
+
+        assert_eq!(actual, (3, 7));
+    }
+
+    #[test]
+    fn counts_chars_not_bytes() {
+        let input = "[\"é\", x]";
+        let offset = input.find('x').unwrap();
+
+        let actual = line_column(input, offset);
+
+        assert_eq!(actual, (1, 7));
+    }
+
+    #[test]
+    fn end_of_input() {
+        let input = "[1,\n";
+
+        let actual = line_column(input, input.len());
+
+        assert_eq!(actual, (2, 1));
+    }
+
+    #[test]
+    fn advances_position() {
+        let input = "[\"é\",\n  x]";
+        let (before, after) = input.split_at(input.find('x').unwrap());
+        let expected = (line_column(input, input.len() - 1), 9);
+
+        let mut position = Position::default();
+        position.advance(before.as_bytes());
+        let actual = (position.locate(after, 1), position.offset);
+
+        assert_eq!(actual, expected);
+    }
+}
@@ -0,0 +1,189 @@
+use crate::error::Error;
+use crate::reader::{Event, JsonReader};
+use crate::{JsonPointer, Value};
+use std::fmt;
+use std::io::BufRead;
+
+/// Called with the pointer and value of each match
+type Callback<'a> = Box<dyn FnMut(&JsonPointer, Value) + 'a>;
+
+/// A pattern and what to call with the values it matches
+struct Route<'a> {
+    pattern: String,
+    /// the pattern read as a pointer, `None` when it isn't one and so matches nothing
+    pointer: Option<JsonPointer>,
+    callback: Callback<'a>,
+}
+
+impl Route<'_> {
+    fn matches(&self, path: &JsonPointer) -> bool {
+        self.pointer
+            .as_ref()
+            .is_some_and(|pointer| pointer.tokens().len() == path.tokens().len())
+            && self.covers(path)
+    }
+
+    /// Whether each token of `path` is matched by the pattern, so values under it may match
+    fn covers(&self, path: &JsonPointer) -> bool {
+        self.pointer.as_ref().is_some_and(|pointer| {
+            let mut segments = pointer.tokens().iter();
+            path.tokens().iter().all(|token| {
+                segments
+                    .next()
+                    .is_some_and(|segment| segment == "*" || segment == token)
+            })
+        })
+    }
+}
+
+/// Pulls the values at chosen places out of a document as it streams past, materializing only
+/// those while everything else is skipped over. Places are JSON Pointers in which a `*` segment
+/// matches any key or index, e.g. `/records/*/payload`. The values inside one that matched
+/// aren't matched again.
+#[derive(Default)]
+pub struct Splitter<'a> {
+    routes: Vec<Route<'a>>,
+}
+
+impl fmt::Debug for Splitter<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let patterns: Vec<_> = self.routes.iter().map(|route| &route.pattern).collect();
+        f.debug_struct("Splitter")
+            .field("patterns", &patterns)
+            .finish()
+    }
+}
+
+impl<'a> Splitter<'a> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Calls `callback` with the pointer and value of each value matching `pattern`, the first
+    /// pattern added wins when several match
+    pub fn route(
+        &mut self,
+        pattern: &str,
+        callback: impl FnMut(&JsonPointer, Value) + 'a,
+    ) -> &mut Self {
+        self.routes.push(Route {
+            pattern: String::from(pattern),
+            pointer: JsonPointer::parse(pattern).ok(),
+            callback: Box::new(callback),
+        });
+        self
+    }
+
+    /// Reads the rest of the document from `reader`, handing each matching value to its route
+    pub fn split<R: BufRead>(&mut self, reader: &mut JsonReader<R>) -> Result<(), Error> {
+        while let Some(event) = reader.next_event()? {
+            if matches!(event, Event::Key(_) | Event::EndArray | Event::EndObject) {
+                continue;
+            }
+            let path = reader.current_path();
+            if let Some(route) = self.routes.iter_mut().find(|route| route.matches(&path)) {
+                let value = reader.value_from(event)?;
+                (route.callback)(&path, value);
+            } else if matches!(event, Event::StartArray | Event::StartObject)
+                && !self.routes.iter().any(|route| route.covers(&path))
+            {
+                reader.skip_container()?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Splitter;
+    use crate::{parse, ErrorCode, JsonReader, TokenParseError, Value};
+
+    #[test]
+    fn splits_matching_values() {
+        let input = r#"{
+            "records": [{"id": 1, "payload": {"x": [1]}}, {"id": 2, "payload": null}],
+            "meta": {"payload": 3, "skipped": [[["]"]]]}
+        }"#;
+        let expected_payloads = vec![
+            (
+                String::from("/records/0/payload"),
+                parse(String::from(r#"{"x": [1]}"#)).unwrap(),
+            ),
+            (
+                String::from("/records/1/payload"),
+                parse(String::from("null")).unwrap(),
+            ),
+        ];
+        let expected_meta = vec![(
+            String::from("/meta/payload"),
+            parse(String::from("3")).unwrap(),
+        )];
+
+        let mut payloads = Vec::new();
+        let mut meta = Vec::new();
+        let mut reader = JsonReader::new(input.as_bytes());
+        Splitter::new()
+            .route("/records/*/payload", |path, value| {
+                payloads.push((path.to_string(), value))
+            })
+            .route("/meta/payload", |path, value| {
+                meta.push((path.to_string(), value))
+            })
+            .split(&mut reader)
+            .unwrap();
+
+        assert_eq!(payloads, expected_payloads);
+        assert_eq!(meta, expected_meta);
+    }
+
+    #[test]
+    fn splits_whole_document() {
+        let input = "[1, [2]]";
+        let expected = vec![parse(String::from(input)).unwrap()];
+
+        let mut actual = Vec::new();
+        let mut elements = 0;
+        let mut reader = JsonReader::new(input.as_bytes());
+        Splitter::new()
+            .route("", |_, value| actual.push(value))
+            .route("/*", |_, _| elements += 1)
+            .split(&mut reader)
+            .unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(elements, 0);
+    }
+
+    #[test]
+    fn splits_on_escaped_keys() {
+        let input = r#"{"a/b": {"~": 1, "c": 2}, "a": {"b": {"~": 3}}}"#;
+        let expected = vec![(vec![String::from("a/b"), String::from("~")], Value::from(1))];
+
+        let mut actual = Vec::new();
+        let mut reader = JsonReader::new(input.as_bytes());
+        Splitter::new()
+            .route("/a~1b/~0", |path, value| {
+                actual.push((path.tokens().to_vec(), value))
+            })
+            .route("no slash", |_, _| panic!("matched an invalid pattern"))
+            .split(&mut reader)
+            .unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn split_reports_errors() {
+        let input = r#"[{"a": 1}, {"a": 2 "b": 3}]"#;
+        let expected = ErrorCode::ParseError(TokenParseError::ExpectedComma);
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let actual = Splitter::new()
+            .route("/*/a", |_, _| {})
+            .split(&mut reader)
+            .unwrap_err();
+
+        assert_eq!(actual.code, expected);
+    }
+}
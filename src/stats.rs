@@ -0,0 +1,162 @@
+use crate::path::Path;
+use crate::visit::{walk, Visitor};
+use crate::{Map, Value};
+
+/// The shape of a document, made by `Value::stats`, for logging payload metrics and enforcing
+/// limits on what a service accepts
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Stats {
+    pub nulls: usize,
+    pub booleans: usize,
+    pub numbers: usize,
+    pub strings: usize,
+    pub arrays: usize,
+    pub objects: usize,
+    /// the most arrays and objects nested inside each other, 0 for a lone scalar
+    pub max_depth: usize,
+    /// the UTF-8 length of every string value added up, keys not included
+    pub string_bytes: usize,
+    /// the UTF-8 length of every object key added up
+    pub key_bytes: usize,
+    /// the most elements any one array has
+    pub largest_array: usize,
+    /// the most members any one object has
+    pub largest_object: usize,
+}
+
+impl Stats {
+    /// How many values there are in all, containers included
+    pub fn nodes(&self) -> usize {
+        self.nulls + self.booleans + self.numbers + self.strings + self.arrays + self.objects
+    }
+}
+
+impl Visitor for Stats {
+    fn enter_object(&mut self, path: &Path, members: &Map<Value>) {
+        self.objects += 1;
+        self.max_depth = self.max_depth.max(path.0.len() + 1);
+        self.key_bytes += members.keys().map(String::len).sum::<usize>();
+        self.largest_object = self.largest_object.max(members.len());
+    }
+
+    fn enter_array(&mut self, path: &Path, elements: &[Value]) {
+        self.arrays += 1;
+        self.max_depth = self.max_depth.max(path.0.len() + 1);
+        self.largest_array = self.largest_array.max(elements.len());
+    }
+
+    fn scalar(&mut self, _path: &Path, value: &Value) {
+        match value {
+            Value::Null => self.nulls += 1,
+            Value::Boolean(_) => self.booleans += 1,
+            Value::Number(_) => self.numbers += 1,
+            Value::String(string) => {
+                self.strings += 1;
+                self.string_bytes += string.len();
+            }
+            Value::Array(_) | Value::Object(_) => {}
+        }
+    }
+}
+
+impl Value {
+    /// Counts what the document is made of
+    pub fn stats(&self) -> Stats {
+        let mut stats = Stats::default();
+        walk(self, &mut stats);
+        stats
+    }
+}
+
+impl Value {
+    /// Roughly how many bytes of heap the value holds on to: the capacity of every string and
+    /// array, and the entries and bookkeeping of every object's map, but not allocator
+    /// overhead or the Value itself, which lives wherever its owner put it. Meant for keeping
+    /// caches of parsed documents within a memory budget.
+    pub fn approximate_heap_size(&self) -> usize {
+        match self {
+            Value::Null | Value::Boolean(_) | Value::Number(_) => 0,
+            Value::String(string) => string.capacity(),
+            Value::Array(elements) => {
+                elements.capacity() * size_of::<Value>()
+                    + elements
+                        .iter()
+                        .map(Value::approximate_heap_size)
+                        .sum::<usize>()
+            }
+            Value::Object(members) => {
+                map_overhead(members)
+                    + members
+                        .iter()
+                        .map(|(key, member)| key.capacity() + member.approximate_heap_size())
+                        .sum::<usize>()
+            }
+        }
+    }
+}
+
+/// The bytes a map allocates for its entries, a `HashMap` keeping a control byte for each of
+/// its slots
+#[cfg(not(feature = "sorted_maps"))]
+fn map_overhead(members: &Map<Value>) -> usize {
+    members.capacity() * (size_of::<(String, Value)>() + 1)
+}
+
+/// The bytes a map allocates for its entries, a `BTreeMap` keeping them in nodes of up to 11
+/// with a parent pointer, lengths and, for inner nodes, child pointers
+#[cfg(feature = "sorted_maps")]
+fn map_overhead(members: &Map<Value>) -> usize {
+    const NODE_CAPACITY: usize = 11;
+    let nodes = members.len().div_ceil(NODE_CAPACITY);
+    let node = NODE_CAPACITY * size_of::<(String, Value)>() + 2 * size_of::<usize>();
+    nodes * node
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Stats;
+    use crate::{parse, Map, Value};
+
+    #[test]
+    fn counts_nodes() {
+        let input =
+            r#"{"name": "héllo", "tags": ["a", "b", null], "nested": {"deep": [[1, true]]}}"#;
+        let input = parse(String::from(input)).unwrap();
+        let expected = Stats {
+            nulls: 1,
+            booleans: 1,
+            numbers: 1,
+            strings: 3,
+            arrays: 3,
+            objects: 2,
+            max_depth: 4,
+            string_bytes: 8,
+            key_bytes: 18,
+            largest_array: 3,
+            largest_object: 3,
+        };
+
+        let actual = input.stats();
+
+        assert_eq!(actual, expected);
+        assert_eq!(actual.nodes(), 11);
+        assert_eq!(Value::Null.stats().max_depth, 0);
+    }
+
+    #[test]
+    fn estimates_heap_size() {
+        let string = Value::String(String::from("hello"));
+        let array = Value::Array(vec![Value::Null, string.clone()]);
+        let object = Value::Object(Map::from([(String::from("key"), array.clone())]));
+
+        let scalar = Value::from(1).approximate_heap_size();
+        let actual_string = string.approximate_heap_size();
+        let actual_array = array.approximate_heap_size();
+        let actual_object = object.approximate_heap_size();
+
+        assert_eq!(scalar, 0);
+        assert_eq!(actual_string, 5);
+        assert_eq!(actual_array, 2 * size_of::<Value>() + 5);
+        assert!(actual_object >= 3 + actual_array + size_of::<(String, Value)>());
+    }
+}
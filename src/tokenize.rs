@@ -1,128 +1,440 @@
-use crate::tokenize::TokenizeError::UnfinishedLiteralValue;
-use std::char::ParseCharError;
-use std::num::ParseFloatError;
-use std::str::Chars;
+use crate::options::{ParseOptions, SurrogatePolicy, CANCEL_CHECK_INTERVAL};
+use crate::simd::{next_structural, skip_whitespace};
+pub use crate::span::{Span, Spanned};
+use std::fmt;
 
 /// Takes in an input string and returns a Vector of Token
 pub fn tokenize(input: String) -> Result<Vec<Token>, TokenizeError> {
-    let chars: Vec<char> = input.chars().collect();
+    tokenize_with(input, &ParseOptions::default())
+}
+
+/// Same as `tokenize` but decodes strings according to the options
+pub fn tokenize_with(input: String, options: &ParseOptions) -> Result<Vec<Token>, TokenizeError> {
+    let tokens = tokenize_spanned(&input, options).map_err(|err| err.value)?;
+    Ok(tokens.into_iter().map(|token| token.value).collect())
+}
+
+/// Same as `tokenize_with` but keeps where each token, or the error, was found
+pub fn tokenize_spanned(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Vec<Spanned<Token>>, Spanned<TokenizeError>> {
+    let mut tokenized = tokenize_commented(input, options)?;
+    // the tokens of a single document are only wanted by the parser
+    if let Some(err) = tokenized.errors.pop() {
+        return Err(err);
+    }
+    Ok(tokenized.tokens)
+}
+
+/// Same as `tokenize_spanned` but also returns where each comment allowed by
+/// `ParseOptions::allow_comments` was skipped. With `ParseOptions::allow_trailing_data`
+/// an error may be left in `errors` alongside the tokens before it.
+pub(crate) fn tokenize_commented(
+    input: &str,
+    options: &ParseOptions,
+) -> Result<Tokenized, Spanned<TokenizeError>> {
+    let mut tokenized = tokenize_all(input, options, false);
+    // trailing data may be anything, so its error is kept for the parser to judge
+    // whether the document reached it
+    let trailing = options.allow_trailing_data && !tokenized.tokens.is_empty();
+    if !trailing {
+        if let Some(err) = tokenized.errors.pop() {
+            return Err(err);
+        }
+    }
+    // whitespace alone is not json
+    if tokenized.tokens.is_empty() && !input.is_empty() {
+        let end = input.len();
+        return Err(Spanned::new(
+            TokenizeError::UnexpectedEof,
+            Span::new(end, end),
+        ));
+    }
+
+    Ok(tokenized)
+}
+
+/// Same as `tokenize_spanned` but carries on past errors, standing in `null` for each bad token
+pub fn tokenize_recovering(
+    input: &str,
+    options: &ParseOptions,
+) -> (Vec<Spanned<Token>>, Vec<Spanned<TokenizeError>>) {
+    let tokenized = tokenize_all(input, options, true);
+    (tokenized.tokens, tokenized.errors)
+}
+
+/// Everything found in the input, errors are only collected past the first when recovering
+pub(crate) struct Tokenized {
+    pub tokens: Vec<Spanned<Token>>,
+    pub errors: Vec<Spanned<TokenizeError>>,
+    /// where each comment was skipped over
+    pub comments: Vec<Span>,
+}
+
+pub(crate) fn tokenize_all(input: &str, options: &ParseOptions, recover: bool) -> Tokenized {
+    let bytes = input.as_bytes();
     let mut index = 0;
+    // the offset last given to the progress callback
+    let mut reported = 0;
 
     let mut tokens = Vec::new();
-    while index < chars.len() {
-        let token = make_token(&chars, &mut index)?;
-        tokens.push(token);
-        index += 1;
+    let mut errors = Vec::new();
+    let mut comments = Vec::new();
+    // refuse oversized input before doing any work on it
+    if let Some(max) = options.max_input_len.filter(|max| bytes.len() > *max) {
+        errors.push(Spanned::new(
+            TokenizeError::InputTooLarge,
+            Span::new(max, bytes.len()),
+        ));
+        return Tokenized {
+            tokens,
+            errors,
+            comments,
+        };
+    }
+    for count in 0_usize.. {
+        if count.is_multiple_of(CANCEL_CHECK_INTERVAL) && options.is_cancelled() {
+            errors.push(Spanned::new(
+                TokenizeError::Cancelled,
+                Span::new(index, index),
+            ));
+            break;
+        }
+        let start = skip_whitespace(bytes, index);
+        match next_lexeme(input, &mut index, options) {
+            None => break,
+            Some(Ok(Lexeme::Token(token))) => tokens.push(token),
+            Some(Ok(Lexeme::Comment(span))) => comments.push(span),
+            Some(Err(err)) => {
+                // nothing after an unclosed comment can be told apart from the comment
+                let unclosed_comment = err.value == TokenizeError::UnclosedComment;
+                errors.push(err);
+                if !recover || unclosed_comment {
+                    break;
+                }
+                // a bad string ends at its closing quote, anything else at the next delimiter
+                index = if bytes[start] == b'"' {
+                    (index + 1).min(bytes.len())
+                } else {
+                    skip_to_delimiter(bytes, start + 1)
+                };
+                tokens.push(Spanned::new(Token::Null, Span::new(start, index)));
+            }
+        }
+        if let Some(progress) = &options.progress {
+            if index - reported >= progress.interval {
+                (progress.callback)(index);
+                reported = index;
+            }
+        }
+    }
+    if let Some(progress) = &options.progress {
+        let index = index.min(bytes.len());
+        if index > reported {
+            (progress.callback)(index);
+        }
     }
 
-    Ok(tokens)
+    Tokenized {
+        tokens,
+        errors,
+        comments,
+    }
 }
 
-fn make_token(chars: &[char], index: &mut usize) -> Result<Token, TokenizeError> {
-    let mut c = chars[*index];
+/// A token or a comment, whatever comes next in the input
+pub(crate) enum Lexeme {
+    Token(Spanned<Token>),
+    /// only produced when `ParseOptions::allow_comments` is set
+    Comment(Span),
+}
 
-    while c.is_ascii_whitespace() {
-        *index += 1;
-        if *index >= chars.len() {
-            return Err(TokenizeError::UnexpectedEof);
+/// Reads whatever comes after the whitespace at `index`, leaving `index` just past it.
+/// Returns `None` when only whitespace remains.
+pub(crate) fn next_lexeme(
+    input: &str,
+    index: &mut usize,
+    options: &ParseOptions,
+) -> Option<Result<Lexeme, Spanned<TokenizeError>>> {
+    let bytes = input.as_bytes();
+    // jump straight to the next structural character or value, trailing whitespace is fine
+    *index = skip_whitespace(bytes, *index);
+    if *index >= bytes.len() {
+        return None;
+    }
+    let start = *index;
+
+    // comments are skipped like whitespace, only remembering where they were
+    if options.allow_comments {
+        match skip_comment(bytes, start) {
+            Some(Ok(end)) => {
+                *index = end;
+                return Some(Ok(Lexeme::Comment(Span::new(start, end))));
+            }
+            Some(Err(err)) => {
+                *index = bytes.len();
+                return Some(Err(Spanned::new(err, Span::new(start, bytes.len()))));
+            }
+            None => {}
         }
-        c = chars[*index];
     }
-    let token = match c {
-        '[' => Token::LeftBracket,
-        ']' => Token::RightBracket,
-        '{' => Token::LeftBrace,
-        '}' => Token::RightBrace,
-        ',' => Token::Comma,
-        ':' => Token::Colon,
+    match make_token(input, index, options) {
+        Ok(token) => {
+            *index += 1;
+            Some(Ok(Lexeme::Token(Spanned::new(
+                token,
+                Span::new(start, *index),
+            ))))
+        }
+        Err(err) => {
+            let end = (*index).min(bytes.len());
+            Some(Err(Spanned::new(err, Span::new(start, end))))
+        }
+    }
+}
 
-        'n' => tokenize_literal(chars, index, String::from("null"), Token::Null)?,
-        't' => tokenize_literal(chars, index, String::from("true"), Token::True)?,
-        'f' => tokenize_literal(chars, index, String::from("false"), Token::False)?,
+/// Finds the end of the `//` or `/* */` comment at `index`, `None` when there isn't one there
+pub(crate) fn skip_comment(bytes: &[u8], index: usize) -> Option<Result<usize, TokenizeError>> {
+    match bytes.get(index..index + 2)? {
+        // a line comment runs up to, but not including, the end of the line
+        b"//" => Some(Ok(bytes[index..]
+            .iter()
+            .position(|&b| b == b'\n')
+            .map_or(bytes.len(), |newline| index + newline))),
+        b"/*" => Some(
+            bytes[index + 2..]
+                .windows(2)
+                .position(|pair| pair == b"*/")
+                .map(|close| index + 2 + close + 2)
+                .ok_or(TokenizeError::UnclosedComment),
+        ),
+        _ => None,
+    }
+}
 
-        c if c.is_ascii_digit() => tokenize_float(chars, index)?,
+/// Finds the next whitespace or punctuation, where a new token could start
+fn skip_to_delimiter(bytes: &[u8], mut index: usize) -> usize {
+    while index < bytes.len()
+        && !matches!(
+            bytes[index],
+            b' ' | b'\t' | b'\n' | b'\r' | b'[' | b']' | b'{' | b'}' | b',' | b':' | b'"'
+        )
+    {
+        index += 1;
+    }
+    index
+}
 
-        '"' => tokenize_string(chars, index)?,
-        c => return Err(TokenizeError::CharNotRecognized(c)),
+fn make_token(
+    input: &str,
+    index: &mut usize,
+    options: &ParseOptions,
+) -> Result<Token, TokenizeError> {
+    let bytes = input.as_bytes();
+
+    let token = match bytes[*index] {
+        b'[' => Token::LeftBracket,
+        b']' => Token::RightBracket,
+        b'{' => Token::LeftBrace,
+        b'}' => Token::RightBrace,
+        b',' => Token::Comma,
+        b':' => Token::Colon,
+
+        // capitalised spellings from other languages are always errors but get a suggestion
+        b'n' | b'N' => tokenize_literal(bytes, index, "null", Token::Null)?,
+        b't' | b'T' => tokenize_literal(bytes, index, "true", Token::True)?,
+        b'f' | b'F' => tokenize_literal(bytes, index, "false", Token::False)?,
+
+        c if c.is_ascii_digit() || c == b'-' => tokenize_number(input, index)?,
+
+        b'"' => tokenize_string(input, index, options)?,
+        _ => {
+            // the index always sits on a char boundary, so decode the full char for the error
+            let c = input[*index..].chars().next().unwrap_or_default();
+            return Err(TokenizeError::CharNotRecognized(c));
+        }
     };
 
     Ok(token)
 }
 
-fn tokenize_string(chars: &[char], current_index: &mut usize) -> Result<Token, TokenizeError> {
-    // New string buffer
-    let mut string = String::new();
-    let mut is_escaping = false;
+fn tokenize_string(
+    input: &str,
+    current_index: &mut usize,
+    options: &ParseOptions,
+) -> Result<Token, TokenizeError> {
+    let bytes = input.as_bytes();
+    // the token starts on the opening "
+    let start = *current_index;
+    *current_index += 1;
 
-    // Loop through from the current index to the end of the chars length
+    // jump from one structural byte to the next, only quotes and escapes matter in here
     loop {
-        *current_index += 1;
-        // if we get to the end of the buffer and there is no closing "
-        // it is deemed invalid json and we throw an error
-        if *current_index >= chars.len() {
-            return Err(TokenizeError::UnclosedQuotes);
+        *current_index = next_structural(bytes, *current_index);
+        match bytes.get(*current_index) {
+            // if we get to the end of the buffer and there is no closing "
+            // it is deemed invalid json and we throw an error
+            None => return Err(TokenizeError::UnclosedQuotes),
+            Some(b'"') => break,
+            // the escaped byte can't end the string, whatever it is
+            Some(b'\\') => *current_index += 2,
+            Some(_) => *current_index += 1,
         }
-        let ch = chars[*current_index];
-        match ch {
-            // if it is the end of a string and we are not escaping break
-            '"' if !is_escaping => break,
-            // toggle the escaping based on the forward slash
-            '\\' => is_escaping = !is_escaping,
-            // else stop escaping
-            _ => is_escaping = false,
+    }
+
+    // quotes are ascii so both ends are char boundaries
+    let span = Span::new(start, *current_index + 1);
+    let value = decode_string(&input[start + 1..*current_index], options)?;
+    Ok(Token::String(StringToken { span, value }))
+}
+
+/// Resolves the escapes in the raw text between a string's quotes
+pub(crate) fn decode_string(raw: &str, options: &ParseOptions) -> Result<String, TokenizeError> {
+    let mut output = String::with_capacity(raw.len());
+    let mut chars = raw.chars();
+
+    while let Some(ch) = chars.next() {
+        if ch != '\\' {
+            output.push(ch);
+            continue;
+        }
+        let escaped = chars.next().ok_or(TokenizeError::UnfinishedEscape)?;
+        match escaped {
+            '"' => output.push('"'),
+            '\\' => output.push('\\'),
+            '/' => output.push('/'),
+            'b' => output.push('\u{8}'),
+            'f' => output.push('\u{c}'),
+            'n' => output.push('\n'),
+            'r' => output.push('\r'),
+            't' => output.push('\t'),
+            'u' => decode_unicode_escape(&mut chars, &mut output, options)?,
+            _ => return Err(TokenizeError::UnfinishedEscape),
         }
-        string.push(ch);
     }
 
-    // return the string token
-    Ok(Token::String(string))
+    Ok(output)
 }
 
-fn tokenize_float(chars: &[char], curr_index: &mut usize) -> Result<Token, TokenizeError> {
-    // string to stored an unparsed number
-    let mut unparsed_num = String::new();
-    // flag to set if its a float or not
-    let mut has_decimal = false;
-    // flag if negative number
-    let mut has_negative = false;
-
-    // walks through the characters starting at the index
-    while *curr_index < chars.len() {
-        let ch = chars[*curr_index];
-
-        match ch {
-            // if the character is a digit we add it to our string
-            c if c.is_ascii_digit() => unparsed_num.push(c),
-            // if its a decimal we set the has_decimal flag and then add the decimal to the string
-            c if c == '.' && !has_decimal => {
-                unparsed_num.push('.');
-                has_decimal = true;
-            }
-            c if c == '-' && !has_decimal => {
-                unparsed_num.push('-');
-                has_negative = true;
+/// Decodes the code point after a `\u`, joining surrogate pairs written as two escapes
+fn decode_unicode_escape(
+    chars: &mut std::str::Chars,
+    output: &mut String,
+    options: &ParseOptions,
+) -> Result<(), TokenizeError> {
+    // remember the digits as written in case the escape has to be preserved
+    let written = chars.as_str().get(..4).unwrap_or_default();
+    let first = decode_hex(chars)?;
+
+    // a high surrogate should be followed by an escaped low surrogate
+    if (0xD800..=0xDBFF).contains(&first) {
+        // look ahead on a copy so a missing pair doesn't swallow the next escape
+        let mut lookahead = chars.clone();
+        if lookahead.next() == Some('\\') && lookahead.next() == Some('u') {
+            if let Ok(second @ 0xDC00..=0xDFFF) = decode_hex(&mut lookahead) {
+                *chars = lookahead;
+                let code_point = 0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00);
+                output.extend(char::from_u32(code_point));
+                return Ok(());
             }
-            // if we reach the end of the number we terminate, say a bracket or whitespace
-            _ => break,
         }
+    }
+
+    // anything that isn't a char on its own is an unpaired surrogate
+    match (char::from_u32(first), options.surrogates) {
+        (Some(c), _) => output.push(c),
+        (None, SurrogatePolicy::Error) => return Err(TokenizeError::InvalidCodePointValue),
+        (None, SurrogatePolicy::Replace) => output.push(char::REPLACEMENT_CHARACTER),
+        (None, SurrogatePolicy::Preserve) => {
+            output.push_str("\\u");
+            output.push_str(written);
+        }
+    }
+    Ok(())
+}
+
+fn decode_hex(chars: &mut std::str::Chars) -> Result<u32, TokenizeError> {
+    let mut value = 0;
+    for _ in 0..4 {
+        let digit = chars
+            .next()
+            .and_then(|c| c.to_digit(16))
+            .ok_or(TokenizeError::InvalidHexValue)?;
+        value = value * 16 + digit;
+    }
+    Ok(value)
+}
+
+fn tokenize_number(input: &str, curr_index: &mut usize) -> Result<Token, TokenizeError> {
+    let bytes = input.as_bytes();
+    let start = *curr_index;
+
+    // an optional minus sign in front of the integer part
+    if bytes[*curr_index] == b'-' {
+        *curr_index += 1;
+    }
+    // the integer part is either a single zero or digits without a leading zero
+    match bytes.get(*curr_index) {
+        Some(b'0') => *curr_index += 1,
+        Some(c) if c.is_ascii_digit() => {
+            consume_digits(bytes, curr_index);
+        }
+        _ => return Err(TokenizeError::InvalidNumber),
+    }
+    // an optional fraction which must have at least one digit
+    if bytes.get(*curr_index) == Some(&b'.') {
         *curr_index += 1;
+        if !consume_digits(bytes, curr_index) {
+            return Err(TokenizeError::InvalidNumber);
+        }
     }
+    // an optional exponent with an optional sign, again needing at least one digit
+    if matches!(bytes.get(*curr_index), Some(b'e' | b'E')) {
+        *curr_index += 1;
+        if matches!(bytes.get(*curr_index), Some(b'+' | b'-')) {
+            *curr_index += 1;
+        }
+        if !consume_digits(bytes, curr_index) {
+            return Err(TokenizeError::InvalidNumber);
+        }
+    }
+
+    // the number is only checked here, converting it is left to whoever consumes the token
+    let raw = String::from(&input[start..*curr_index]);
+    // step back onto the last digit so the next token isn't skipped
+    *curr_index -= 1;
+    Ok(Token::Number(raw))
+}
 
-    match unparsed_num.parse() {
-        Ok(f) => Ok(Token::Number(f)),
-        Err(err) => Err(TokenizeError::ParseNumberError(err)),
+/// Advances past a run of ascii digits, returning whether there were any
+fn consume_digits(bytes: &[u8], curr_index: &mut usize) -> bool {
+    let start = *curr_index;
+    while *curr_index < bytes.len() && bytes[*curr_index].is_ascii_digit() {
+        *curr_index += 1;
     }
+    *curr_index > start
 }
 
 fn tokenize_literal(
-    chars: &[char],
+    bytes: &[u8],
     index: &mut usize,
-    string_value: String,
+    string_value: &'static str,
     token: Token,
 ) -> Result<Token, TokenizeError> {
-    for expected_char in string_value.chars() {
-        if expected_char != chars[*index] {
-            return Err(TokenizeError::UnfinishedLiteralValue);
+    let start = *index;
+    for expected_char in string_value.bytes() {
+        if bytes.get(*index) != Some(&expected_char) {
+            // report the whole word as written, not just the part that matched
+            *index = start;
+            while *index < bytes.len() && bytes[*index].is_ascii_alphanumeric() {
+                *index += 1;
+            }
+            let found = String::from_utf8_lossy(&bytes[start..*index]).into_owned();
+            return Err(TokenizeError::UnfinishedLiteralValue {
+                found,
+                suggestion: string_value,
+            });
         }
         *index += 1;
     }
@@ -134,14 +446,71 @@ fn tokenize_literal(
 /// Possible errors from attempting to parse JSON
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub enum TokenizeError {
-    UnfinishedLiteralValue,
-    ParseNumberError(ParseFloatError),
+    /// a word that starts like `null`, `true` or `false` but isn't, such as `ture` or `nul`
+    UnfinishedLiteralValue {
+        found: String,
+        suggestion: &'static str,
+    },
+    InvalidNumber,
     UnclosedQuotes,
     UnexpectedEof,
     CharNotRecognized(char),
+    /// a backslash in a string not followed by a valid escape
+    UnfinishedEscape,
+    /// a `\u` escape without four hex digits
+    InvalidHexValue,
+    /// a `\u` escape that doesn't decode to a char, such as a lone surrogate
+    InvalidCodePointValue,
+    /// the input is longer than `ParseOptions::max_input_len`
+    InputTooLarge,
+    /// `ParseOptions::cancel` was set before the input was fully tokenized
+    Cancelled,
+    /// a `/*` comment without its closing `*/`
+    UnclosedComment,
+    /// bytes read from a reader that aren't valid UTF-8
+    InvalidUtf8,
+}
+
+impl fmt::Display for TokenizeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TokenizeError::UnfinishedLiteralValue { found, suggestion } => {
+                write!(f, "invalid literal `{found}`, did you mean `{suggestion}`?")
+            }
+            TokenizeError::InvalidNumber => write!(f, "invalid number"),
+            TokenizeError::UnclosedQuotes => write!(f, "unclosed quotes"),
+            TokenizeError::UnexpectedEof => write!(f, "unexpected end of input"),
+            TokenizeError::CharNotRecognized(c) => write!(f, "unrecognized character {c:?}"),
+            TokenizeError::UnfinishedEscape => write!(f, "invalid escape in string"),
+            TokenizeError::InvalidHexValue => write!(f, "invalid hex digits in `\\u` escape"),
+            TokenizeError::InvalidCodePointValue => write!(f, "invalid code point in `\\u` escape"),
+            TokenizeError::InputTooLarge => write!(f, "input exceeds the size limit"),
+            TokenizeError::Cancelled => write!(f, "cancelled"),
+            TokenizeError::UnclosedComment => write!(f, "unclosed comment"),
+            TokenizeError::InvalidUtf8 => write!(f, "invalid UTF-8"),
+        }
+    }
 }
 
-#[derive(Debug, PartialEq)]
+impl std::error::Error for TokenizeError {}
+
+/// A string as it appeared in the input along with its decoded value
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct StringToken {
+    /// where the string sits in the input, including both quotes
+    pub span: Span,
+    /// the string with every escape resolved
+    pub value: String,
+}
+
+impl StringToken {
+    /// The text between the quotes exactly as written, escapes included
+    pub fn raw<'a>(&self, input: &'a str) -> &'a str {
+        &input[self.span.start + 1..self.span.end - 1]
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Clone)]
 pub enum Token {
     /// `{`
     LeftBrace,
@@ -161,21 +530,64 @@ pub enum Token {
     False,
     /// `true`
     True,
-    /// Any number literal
-    Number(f64),
+    /// Any number literal, kept as the raw source text until it is converted
+    Number(String),
     /// Key of the key/value pair or string value
-    String(String),
+    String(StringToken),
+}
+impl fmt::Display for Token {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Token::LeftBrace => write!(f, "`{{`"),
+            Token::RightBrace => write!(f, "`}}`"),
+            Token::LeftBracket => write!(f, "`[`"),
+            Token::RightBracket => write!(f, "`]`"),
+            Token::Comma => write!(f, "`,`"),
+            Token::Colon => write!(f, "`:`"),
+            Token::Null => write!(f, "`null`"),
+            Token::False => write!(f, "`false`"),
+            Token::True => write!(f, "`true`"),
+            Token::Number(raw) => write!(f, "number `{raw}`"),
+            Token::String(string) => write!(f, "string {:?}", string.value),
+        }
+    }
 }
+
+#[cfg(test)]
+impl TokenizeError {
+    pub fn literal(found: &str, suggestion: &'static str) -> Self {
+        Self::UnfinishedLiteralValue {
+            found: String::from(found),
+            suggestion,
+        }
+    }
+}
+
 #[cfg(test)]
 impl Token {
-    fn string(input: &str) -> Self {
-        Self::String(String::from(input))
+    /// A string token without escapes whose opening quote is at `start`
+    pub fn string(input: &str, start: usize) -> Self {
+        Self::String(StringToken {
+            span: Span::new(start, start + input.len() + 2),
+            value: String::from(input),
+        })
+    }
+
+    pub fn number(input: &str) -> Self {
+        Self::Number(String::from(input))
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{tokenize, Token, TokenizeError};
+    use super::{
+        tokenize, tokenize_recovering, tokenize_spanned, tokenize_with, Span, Spanned, StringToken,
+        Token, TokenizeError,
+    };
+    use crate::options::{ParseOptions, Progress, SurrogatePolicy};
+    use crate::{parse_with, to_string, Value};
+    use std::sync::atomic::AtomicBool;
+    use std::sync::{Arc, Mutex};
 
     #[test]
     fn true_comma() {
@@ -249,7 +661,7 @@ mod tests {
     fn integer() {
         let input = String::from("100");
 
-        let expected = [Token::Number(100.0)];
+        let expected = [Token::number("100")];
 
         let actual = tokenize(input).unwrap();
 
@@ -260,7 +672,7 @@ mod tests {
     fn float() {
         let input = String::from("1.23");
 
-        let expected = [Token::Number(1.23)];
+        let expected = [Token::number("1.23")];
 
         let actual = tokenize(input).unwrap();
 
@@ -270,7 +682,7 @@ mod tests {
     #[test]
     fn simple_string() {
         let input = String::from("\"ken\"");
-        let expected = [Token::string("ken")];
+        let expected = [Token::string("ken", 0)];
 
         let actual = tokenize(input).unwrap();
 
@@ -290,13 +702,105 @@ mod tests {
     #[test]
     fn escaped_quote() {
         let input = String::from(r#""the \" is OK""#);
-        let expected = [Token::String(String::from(r#"the \" is OK"#))];
+        let expected = [Token::String(StringToken {
+            span: Span::new(0, 14),
+            value: String::from(r#"the " is OK"#),
+        })];
 
         let actual = tokenize(input).unwrap();
 
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn raw_string() {
+        let input = String::from(r#"["a\tb"]"#);
+        let expected = r"a\tb";
+
+        let tokens = tokenize(input.clone()).unwrap();
+        let Token::String(string) = &tokens[1] else {
+            panic!("expected a string token");
+        };
+        let actual = string.raw(&input);
+
+        assert_eq!(actual, expected);
+        assert_eq!(string.value, "a\tb");
+    }
+
+    #[test]
+    fn decodes_escapes() {
+        let input = String::from(r#""a\"b\\c\nd\u00e9""#);
+        let expected = "a\"b\\c\nd\u{e9}";
+
+        let actual = tokenize(input).unwrap();
+
+        assert!(matches!(&actual[0], Token::String(string) if string.value == expected));
+    }
+
+    #[test]
+    fn decodes_surrogate_pair() {
+        let input = String::from(r#""\ud83d\ude00""#);
+        let expected = "\u{1f600}";
+
+        let actual = tokenize(input).unwrap();
+
+        assert!(matches!(&actual[0], Token::String(string) if string.value == expected));
+    }
+
+    #[test]
+    fn lone_surrogate() {
+        let input = String::from(r#""\ud83d""#);
+        let expected = Err(TokenizeError::InvalidCodePointValue);
+
+        let actual = tokenize(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn replaces_lone_surrogate() {
+        let input = String::from(r#""a\uD800\u0041""#);
+        let options = ParseOptions {
+            surrogates: SurrogatePolicy::Replace,
+            ..ParseOptions::default()
+        };
+        let expected = "a\u{fffd}A";
+
+        let actual = tokenize_with(input, &options).unwrap();
+
+        assert!(matches!(&actual[0], Token::String(string) if string.value == expected));
+    }
+
+    #[test]
+    fn preserves_lone_surrogate() {
+        let input = String::from(r#""\uDC00!""#);
+        let options = ParseOptions {
+            surrogates: SurrogatePolicy::Preserve,
+            ..ParseOptions::default()
+        };
+        let expected = r"\uDC00!";
+
+        let actual = tokenize_with(input, &options).unwrap();
+
+        assert!(matches!(&actual[0], Token::String(string) if string.value == expected));
+    }
+
+    #[test]
+    fn preserved_surrogate_changes_on_round_trip() {
+        let input = String::from(r#""\uD800""#);
+        let options = ParseOptions {
+            surrogates: SurrogatePolicy::Preserve,
+            ..ParseOptions::default()
+        };
+        let expected = Value::from(r"\uD800");
+
+        let serialized = to_string(&parse_with(input, &options).unwrap()).unwrap();
+        let actual = parse_with(serialized.clone(), &options).unwrap();
+
+        assert_eq!(serialized, r#""\\uD800""#);
+        assert_eq!(actual, expected);
+    }
+
     #[test]
     fn white_space() {
         let input = String::from(" ");
@@ -312,9 +816,9 @@ mod tests {
         let input = String::from("{\"key\": \"value\"}");
         let expected = [
             Token::LeftBrace,
-            Token::string("key"),
+            Token::string("key", 1),
             Token::Colon,
-            Token::string("value"),
+            Token::string("value", 8),
             Token::RightBrace,
         ];
 
@@ -322,4 +826,238 @@ mod tests {
 
         assert_eq!(actual, expected);
     }
+
+    #[test]
+    fn long_whitespace_between_tokens() {
+        let input = format!("[{}true{}]", " ".repeat(50), "\n".repeat(20));
+        let expected = [Token::LeftBracket, Token::True, Token::RightBracket];
+
+        let actual = tokenize(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn negative_exponent() {
+        let input = String::from("-1.5e-3");
+        let expected = [Token::number("-1.5e-3")];
+
+        let actual = tokenize(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn numbers_in_array() {
+        let input = String::from("[1,20]");
+        let expected = [
+            Token::LeftBracket,
+            Token::number("1"),
+            Token::Comma,
+            Token::number("20"),
+            Token::RightBracket,
+        ];
+
+        let actual = tokenize(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn missing_fraction_digits() {
+        let input = String::from("1.");
+        let expected = Err(TokenizeError::InvalidNumber);
+
+        let actual = tokenize(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn trailing_white_space() {
+        let input = String::from("true \n");
+        let expected = [Token::True];
+
+        let actual = tokenize(input).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn token_spans() {
+        let input = "[ null,\n  -1.5 ]";
+        let expected = vec![
+            Spanned::new(Token::LeftBracket, Span::new(0, 1)),
+            Spanned::new(Token::Null, Span::new(2, 6)),
+            Spanned::new(Token::Comma, Span::new(6, 7)),
+            Spanned::new(Token::number("-1.5"), Span::new(10, 14)),
+            Spanned::new(Token::RightBracket, Span::new(15, 16)),
+        ];
+
+        let actual = tokenize_spanned(input, &ParseOptions::default()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn error_span() {
+        let input = "[1, @]";
+        let expected = Err(Spanned::new(
+            TokenizeError::CharNotRecognized('@'),
+            Span::new(4, 4),
+        ));
+
+        let actual = tokenize_spanned(input, &ParseOptions::default());
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn recovers_from_bad_tokens() {
+        let input = "[tru, @x, \"\\q\", 2]";
+        let expected_tokens = [
+            Token::LeftBracket,
+            Token::Null,
+            Token::Comma,
+            Token::Null,
+            Token::Comma,
+            Token::Null,
+            Token::Comma,
+            Token::number("2"),
+            Token::RightBracket,
+        ];
+        let expected_errors = [
+            Spanned::new(TokenizeError::literal("tru", "true"), Span::new(1, 4)),
+            Spanned::new(TokenizeError::CharNotRecognized('@'), Span::new(6, 6)),
+            Spanned::new(TokenizeError::UnfinishedEscape, Span::new(10, 13)),
+        ];
+
+        let (tokens, errors) = tokenize_recovering(input, &ParseOptions::default());
+
+        let tokens: Vec<_> = tokens.into_iter().map(|token| token.value).collect();
+        assert_eq!(tokens, expected_tokens);
+        assert_eq!(errors, expected_errors);
+    }
+
+    #[test]
+    fn misspelled_literals() {
+        let cases = [
+            ("nul", TokenizeError::literal("nul", "null")),
+            ("ture", TokenizeError::literal("ture", "true")),
+            ("[flase]", TokenizeError::literal("flase", "false")),
+            ("True", TokenizeError::literal("True", "true")),
+            ("None", TokenizeError::literal("None", "null")),
+        ];
+
+        for (input, expected) in cases {
+            let actual = tokenize(String::from(input));
+
+            assert_eq!(actual, Err(expected));
+        }
+    }
+
+    #[test]
+    fn literal_suggestion_message() {
+        let input = String::from("flase");
+        let expected = "invalid literal `flase`, did you mean `false`?";
+
+        let actual = tokenize(input).unwrap_err().to_string();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn input_too_large() {
+        let input = "[1, 2, 3]";
+        let options = ParseOptions {
+            max_input_len: Some(8),
+            ..ParseOptions::default()
+        };
+        let expected = Err(Spanned::new(TokenizeError::InputTooLarge, Span::new(8, 9)));
+
+        let actual = tokenize_spanned(input, &options);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn cancelled() {
+        let input = "[1, 2, 3]";
+        let options = ParseOptions {
+            cancel: Some(Arc::new(AtomicBool::new(true))),
+            ..ParseOptions::default()
+        };
+        let expected = (
+            vec![],
+            vec![Spanned::new(TokenizeError::Cancelled, Span::new(0, 0))],
+        );
+
+        let actual = tokenize_recovering(input, &options);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn reports_progress() {
+        let input = "[1, 2, 3, 4]";
+        let offsets = Arc::new(Mutex::new(Vec::new()));
+        let reported = Arc::clone(&offsets);
+        let options = ParseOptions {
+            progress: Some(Progress::new(4, move |offset| {
+                reported.lock().unwrap().push(offset)
+            })),
+            ..ParseOptions::default()
+        };
+
+        tokenize_spanned(input, &options).unwrap();
+
+        assert_eq!(*offsets.lock().unwrap(), [5, 9, 12]);
+    }
+
+    #[test]
+    fn skips_comments() {
+        let input = "// header\n[1, /* two */ 2]\n// end";
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let expected = vec![
+            Token::LeftBracket,
+            Token::number("1"),
+            Token::Comma,
+            Token::number("2"),
+            Token::RightBracket,
+        ];
+
+        let actual = tokenize_with(String::from(input), &options).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn comments_need_allowing() {
+        let input = String::from("[1] // done");
+        let expected = Err(TokenizeError::CharNotRecognized('/'));
+
+        let actual = tokenize(input);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn unclosed_comment() {
+        let input = "[1] /* done";
+        let options = ParseOptions {
+            allow_comments: true,
+            ..ParseOptions::default()
+        };
+        let expected = Err(Spanned::new(
+            TokenizeError::UnclosedComment,
+            Span::new(4, 11),
+        ));
+
+        let actual = tokenize_spanned(input, &options);
+
+        assert_eq!(actual, expected);
+    }
 }
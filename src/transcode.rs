@@ -0,0 +1,317 @@
+use crate::error::{kind_predicates, Error, ErrorKind};
+use crate::reader::{Event, JsonReader};
+use crate::serialize::LineEnding;
+use crate::writer::{write_string, JsonWriter, WriteError};
+use std::fmt;
+use std::io::{self, BufRead, Write};
+
+/// What `transcode` does with an event, as decided by its filter
+#[derive(Debug, Clone, PartialEq)]
+pub enum Transform {
+    /// write the event as it is
+    Keep,
+    /// write this event in its place, e.g. a `Key` with a new name
+    Replace(Event),
+    /// leave the event out along with everything it covers: a key with its value, an array or
+    /// object start with the rest of the container. An object member is skipped by its key, as
+    /// the key is already written when its value starts. End events can't be skipped.
+    Skip,
+}
+
+/// The whitespace `reformat` puts between tokens
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Style {
+    /// none at all
+    #[default]
+    Minified,
+    /// every element and member on its own line ended by `line_ending`, indented by `indent`
+    /// spaces per level
+    Pretty {
+        indent: usize,
+        line_ending: LineEnding,
+    },
+}
+
+/// Possible errors from `transcode`, from either end of the pipeline
+#[derive(Debug)]
+pub enum TranscodeError {
+    Read(Error),
+    Write(WriteError),
+}
+
+impl fmt::Display for TranscodeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TranscodeError::Read(err) => write!(f, "{err}"),
+            TranscodeError::Write(err) => write!(f, "{err}"),
+        }
+    }
+}
+
+impl TranscodeError {
+    /// The category of the error from whichever end failed
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            TranscodeError::Read(err) => err.kind(),
+            TranscodeError::Write(err) => err.kind(),
+        }
+    }
+}
+
+kind_predicates!(TranscodeError);
+
+impl std::error::Error for TranscodeError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TranscodeError::Read(err) => Some(err),
+            TranscodeError::Write(err) => Some(err),
+        }
+    }
+}
+
+impl From<Error> for TranscodeError {
+    fn from(err: Error) -> Self {
+        TranscodeError::Read(err)
+    }
+}
+
+impl From<io::Error> for TranscodeError {
+    fn from(err: io::Error) -> Self {
+        TranscodeError::Write(err.into())
+    }
+}
+
+impl From<WriteError> for TranscodeError {
+    fn from(err: WriteError) -> Self {
+        TranscodeError::Write(err)
+    }
+}
+
+/// Copies a document event by event from `reader` to `writer`, passing each through `filter`, so
+/// a document of any size can be transformed in a single pass, e.g. dropping or renaming fields.
+/// The writer checks the events still make a valid document.
+pub fn transcode<R: BufRead, W: Write>(
+    reader: &mut JsonReader<R>,
+    writer: &mut JsonWriter<W>,
+    mut filter: impl FnMut(&Event) -> Transform,
+) -> Result<(), TranscodeError> {
+    while let Some(event) = reader.next_event()? {
+        match filter(&event) {
+            Transform::Keep => writer.write_event(&event)?,
+            Transform::Replace(replacement) => writer.write_event(&replacement)?,
+            Transform::Skip => match event {
+                Event::Key(_) => reader.skip_value()?,
+                Event::StartArray | Event::StartObject => reader.skip_container()?,
+                Event::EndArray | Event::EndObject => writer.write_event(&event)?,
+                _ => {}
+            },
+        }
+    }
+    Ok(())
+}
+
+/// Copies a document from `reader` to `writer` with the whitespace of `style`, a token at a time
+/// rather than through a `Value`, so input of any size can be reformatted. Numbers keep the
+/// literal they were written with. Output goes out in many small writes, so `writer` should be
+/// buffered.
+pub fn reformat<R: BufRead, W: Write>(
+    reader: &mut JsonReader<R>,
+    writer: &mut W,
+    style: Style,
+) -> Result<(), TranscodeError> {
+    // whether the innermost container has nothing in it yet, and whether a key was just written
+    let mut empty = false;
+    let mut keyed = false;
+    while let Some(event) = reader.next_event()? {
+        let depth = reader.depth();
+        match event {
+            Event::EndArray | Event::EndObject => {
+                if !empty {
+                    newline(writer, style, depth)?;
+                }
+                let closer = match event {
+                    Event::EndArray => b"]",
+                    _ => b"}",
+                };
+                writer.write_all(closer)?;
+                empty = false;
+                continue;
+            }
+            // a value straight after its key stays on the key's line
+            _ if keyed => keyed = false,
+            // the depth counts a container the event starts
+            Event::StartArray | Event::StartObject => separate(writer, style, depth - 1, empty)?,
+            _ => separate(writer, style, depth, empty)?,
+        }
+        empty = false;
+        let written = match &event {
+            Event::StartArray => writer.write_all(b"["),
+            Event::StartObject => writer.write_all(b"{"),
+            Event::Key(key) => write_string(writer, key).and_then(|()| match style {
+                Style::Minified => writer.write_all(b":"),
+                Style::Pretty { .. } => writer.write_all(b": "),
+            }),
+            Event::String(string) => write_string(writer, string),
+            Event::Number(_) => writer.write_all(reader.raw_number().as_bytes()),
+            Event::Boolean(true) => writer.write_all(b"true"),
+            Event::Boolean(false) => writer.write_all(b"false"),
+            Event::Null => writer.write_all(b"null"),
+            Event::EndArray | Event::EndObject => unreachable!("ends are written above"),
+        };
+        written?;
+        match event {
+            Event::StartArray | Event::StartObject => empty = true,
+            Event::Key(_) => keyed = true,
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Writes what goes before an element or member at `depth`, a comma unless it is the first
+fn separate(writer: &mut impl Write, style: Style, depth: usize, first: bool) -> io::Result<()> {
+    if depth == 0 {
+        return Ok(());
+    }
+    if !first {
+        writer.write_all(b",")?;
+    }
+    newline(writer, style, depth)
+}
+
+/// Starts a new line indented for `depth` when pretty printing
+fn newline(writer: &mut impl Write, style: Style, depth: usize) -> io::Result<()> {
+    if let Style::Pretty {
+        indent,
+        line_ending,
+    } = style
+    {
+        writer.write_all(line_ending.as_str().as_bytes())?;
+        for _ in 0..indent * depth {
+            writer.write_all(b" ")?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{reformat, transcode, Style, TranscodeError, Transform};
+    use crate::serialize::LineEnding;
+    use crate::{Event, JsonReader, JsonWriter, WriteError};
+
+    fn run(input: &str, filter: impl FnMut(&Event) -> Transform) -> Result<String, TranscodeError> {
+        let mut reader = JsonReader::new(input.as_bytes());
+        let mut writer = JsonWriter::new(Vec::new());
+        transcode(&mut reader, &mut writer, filter)?;
+        Ok(String::from_utf8(writer.finish()?).unwrap())
+    }
+
+    #[test]
+    fn strips_and_renames() {
+        let input = r#"[{"id": 1, "password": {"hash": "]"}, "tags": ["a", "b"]}, {"id": 2}]"#;
+        let expected = r#"[{"key":1,"tags":[]},{"key":2}]"#;
+
+        let actual = run(input, |event| match event {
+            Event::Key(key) if key == "password" => Transform::Skip,
+            Event::Key(key) if key == "id" => Transform::Replace(Event::Key(String::from("key"))),
+            Event::String(_) => Transform::Skip,
+            _ => Transform::Keep,
+        });
+
+        assert_eq!(actual.unwrap(), expected);
+    }
+
+    #[test]
+    fn skips_containers() {
+        let input = r#"{"a": [1, [2, 3]], "b": [{"c": []}]}"#;
+        let expected = r#"{"a":[1],"b":[]}"#;
+
+        let mut depth = 0;
+        let actual = run(input, |event| {
+            match event {
+                Event::StartArray | Event::StartObject => depth += 1,
+                Event::EndArray | Event::EndObject => depth -= 1,
+                _ => {}
+            }
+            match depth {
+                // the filter doesn't see the end of a skipped container
+                3 => {
+                    depth -= 1;
+                    Transform::Skip
+                }
+                _ => Transform::Keep,
+            }
+        });
+
+        assert_eq!(actual.unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_invalid_output() {
+        let input = "[1]";
+
+        let actual = run(input, |event| match event {
+            Event::EndArray => Transform::Replace(Event::EndObject),
+            _ => Transform::Keep,
+        });
+
+        assert!(matches!(
+            actual,
+            Err(TranscodeError::Write(WriteError::MismatchedEnd))
+        ));
+    }
+
+    #[test]
+    fn reformats() {
+        let input = "{\"a\" : [1.50, 1e2, {}], \"b\":{\"c\":[ ]},\n\"d\": \"\\u0041\"}";
+        let cases = [
+            (Style::Minified, r#"{"a":[1.50,1e2,{}],"b":{"c":[]},"d":"A"}"#),
+            (
+                Style::Pretty {
+                    indent: 2,
+                    line_ending: LineEnding::Lf,
+                },
+                "{\n  \"a\": [\n    1.50,\n    1e2,\n    {}\n  ],\n  \"b\": {\n    \"c\": []\n  },\n  \"d\": \"A\"\n}",
+            ),
+        ];
+
+        for (style, expected) in cases {
+            let mut reader = JsonReader::new(input.as_bytes());
+            let mut actual = Vec::new();
+            reformat(&mut reader, &mut actual, style).unwrap();
+
+            assert_eq!(String::from_utf8(actual).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn reformats_scalar() {
+        let input = " -0.0 ";
+        let expected = "-0.0";
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let mut actual = Vec::new();
+        reformat(
+            &mut reader,
+            &mut actual,
+            Style::Pretty {
+                indent: 4,
+                line_ending: LineEnding::Lf,
+            },
+        )
+        .unwrap();
+
+        assert_eq!(String::from_utf8(actual).unwrap(), expected);
+    }
+
+    #[test]
+    fn reformat_stops_at_error() {
+        let input = "[1, 2 3]";
+
+        let mut reader = JsonReader::new(input.as_bytes());
+        let actual = reformat(&mut reader, &mut Vec::new(), Style::Minified);
+
+        assert!(matches!(actual, Err(TranscodeError::Read(_))));
+    }
+}
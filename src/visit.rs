@@ -0,0 +1,335 @@
+use crate::path::{Path, PathSegment};
+use crate::pointer::JsonPointer;
+use crate::{Map, Value};
+
+/// Callbacks for `walk`, which visits every node of a Value depth first, each with its path.
+/// Every callback does nothing by default, so a pass only implements the ones it needs.
+pub trait Visitor {
+    /// Called before the members of an object are visited
+    fn enter_object(&mut self, _path: &Path, _members: &Map<Value>) {}
+    /// Called after the members of an object are visited
+    fn leave_object(&mut self, _path: &Path, _members: &Map<Value>) {}
+    /// Called before the elements of an array are visited
+    fn enter_array(&mut self, _path: &Path, _elements: &[Value]) {}
+    /// Called after the elements of an array are visited
+    fn leave_array(&mut self, _path: &Path, _elements: &[Value]) {}
+    /// Called for each null, boolean, string and number
+    fn scalar(&mut self, _path: &Path, _value: &Value) {}
+}
+
+/// Callbacks for `walk_mut`, the same as `Visitor` but free to change the nodes. Whatever an
+/// `enter_` callback leaves in the container is what gets visited, and a scalar replaced with a
+/// container isn't visited any further.
+pub trait VisitorMut {
+    /// Called before the members of an object are visited
+    fn enter_object(&mut self, _path: &Path, _members: &mut Map<Value>) {}
+    /// Called after the members of an object are visited
+    fn leave_object(&mut self, _path: &Path, _members: &mut Map<Value>) {}
+    /// Called before the elements of an array are visited
+    fn enter_array(&mut self, _path: &Path, _elements: &mut Vec<Value>) {}
+    /// Called after the elements of an array are visited
+    fn leave_array(&mut self, _path: &Path, _elements: &mut Vec<Value>) {}
+    /// Called for each null, boolean, string and number
+    fn scalar(&mut self, _path: &Path, _value: &mut Value) {}
+}
+
+/// Visits every node of `value` depth first, parents before and after their children, and
+/// object members in map order
+pub fn walk(value: &Value, visitor: &mut impl Visitor) {
+    visit(value, &mut Path::default(), visitor);
+}
+
+/// Same as `walk` but the visitor can change the nodes as it goes
+pub fn walk_mut(value: &mut Value, visitor: &mut impl VisitorMut) {
+    visit_mut(value, &mut Path::default(), visitor);
+}
+
+impl Value {
+    /// The same tree with every null, boolean, string and number passed through `f`, e.g. to
+    /// round all numbers. Objects and arrays keep their shape.
+    pub fn map_values(self, mut f: impl FnMut(Value) -> Value) -> Value {
+        self.map_scalars(&mut f)
+    }
+
+    fn map_scalars(self, f: &mut impl FnMut(Value) -> Value) -> Value {
+        match self {
+            Value::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, member)| (key, member.map_scalars(f)))
+                    .collect(),
+            ),
+            Value::Array(elements) => Value::Array(
+                elements
+                    .into_iter()
+                    .map(|element| element.map_scalars(f))
+                    .collect(),
+            ),
+            scalar => f(scalar),
+        }
+    }
+
+    /// The same tree with every key of every object passed through `f`, e.g. to lowercase
+    /// them. When two keys of an object end up the same, the member later in map order wins.
+    pub fn map_keys(self, mut f: impl FnMut(String) -> String) -> Value {
+        self.rename(&mut f)
+    }
+
+    fn rename(self, f: &mut impl FnMut(String) -> String) -> Value {
+        match self {
+            Value::Object(members) => Value::Object(
+                members
+                    .into_iter()
+                    .map(|(key, member)| (f(key), member.rename(f)))
+                    .collect(),
+            ),
+            Value::Array(elements) => Value::Array(
+                elements
+                    .into_iter()
+                    .map(|element| element.rename(f))
+                    .collect(),
+            ),
+            scalar => scalar,
+        }
+    }
+
+    /// The same tree with only the object members whose key passes `keep`, at every depth
+    pub fn filter_keys(mut self, mut keep: impl FnMut(&str) -> bool) -> Value {
+        self.retain_members(&mut |key, _| key.is_none_or(&mut keep));
+        self
+    }
+
+    /// Drops every element and member, however deeply nested, that fails `keep`, e.g. to drop
+    /// nulls. A container is checked before what is inside it, so what it holds is only
+    /// checked when it's kept.
+    pub fn retain(&mut self, mut keep: impl FnMut(&Value) -> bool) {
+        self.retain_members(&mut |_, value| keep(value));
+    }
+
+    /// Drops the members and elements that fail `keep`, which elements are given no key for
+    fn retain_members(&mut self, keep: &mut impl FnMut(Option<&str>, &Value) -> bool) {
+        match self {
+            Value::Object(members) => {
+                members.retain(|key, member| keep(Some(key), member));
+                members
+                    .values_mut()
+                    .for_each(|member| member.retain_members(keep));
+            }
+            Value::Array(elements) => {
+                elements.retain(|element| keep(None, element));
+                elements
+                    .iter_mut()
+                    .for_each(|element| element.retain_members(keep));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// The scalars of a Value with their JSON Pointers, made by `Value::leaves`
+#[derive(Debug, Clone)]
+pub struct Leaves<'a> {
+    /// values still to look at, the next one last
+    pending: Vec<(JsonPointer, &'a Value)>,
+}
+
+impl<'a> Iterator for Leaves<'a> {
+    type Item = (JsonPointer, &'a Value);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some((path, value)) = self.pending.pop() {
+            let children: Vec<_> = match value {
+                Value::Object(members) => members
+                    .iter()
+                    .map(|(key, member)| (key.clone(), member))
+                    .collect(),
+                Value::Array(elements) => elements
+                    .iter()
+                    .enumerate()
+                    .map(|(index, element)| (index.to_string(), element))
+                    .collect(),
+                scalar => return Some((path, scalar)),
+            };
+            // reversed so the first child comes off the stack first
+            for (token, child) in children.into_iter().rev() {
+                let mut child_path = path.clone();
+                child_path.push(token);
+                self.pending.push((child_path, child));
+            }
+        }
+        None
+    }
+}
+
+impl Value {
+    /// Every null, boolean, string and number in the tree with its JSON Pointer, in document
+    /// order with object members in map order. Empty objects and arrays have no leaves.
+    pub fn leaves(&self) -> Leaves<'_> {
+        Leaves {
+            pending: vec![(JsonPointer::root(), self)],
+        }
+    }
+}
+
+fn visit(value: &Value, path: &mut Path, visitor: &mut impl Visitor) {
+    match value {
+        Value::Object(members) => {
+            visitor.enter_object(path, members);
+            for (key, member) in members {
+                path.0.push(PathSegment::Key(key.clone()));
+                visit(member, path, visitor);
+                path.0.pop();
+            }
+            visitor.leave_object(path, members);
+        }
+        Value::Array(elements) => {
+            visitor.enter_array(path, elements);
+            for (index, element) in elements.iter().enumerate() {
+                path.0.push(PathSegment::Index(index));
+                visit(element, path, visitor);
+                path.0.pop();
+            }
+            visitor.leave_array(path, elements);
+        }
+        scalar => visitor.scalar(path, scalar),
+    }
+}
+
+fn visit_mut(value: &mut Value, path: &mut Path, visitor: &mut impl VisitorMut) {
+    match value {
+        Value::Object(members) => {
+            visitor.enter_object(path, members);
+            for (key, member) in members.iter_mut() {
+                path.0.push(PathSegment::Key(key.clone()));
+                visit_mut(member, path, visitor);
+                path.0.pop();
+            }
+            visitor.leave_object(path, members);
+        }
+        Value::Array(elements) => {
+            visitor.enter_array(path, elements);
+            for (index, element) in elements.iter_mut().enumerate() {
+                path.0.push(PathSegment::Index(index));
+                visit_mut(element, path, visitor);
+                path.0.pop();
+            }
+            visitor.leave_array(path, elements);
+        }
+        scalar => visitor.scalar(path, scalar),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{walk, walk_mut, Visitor, VisitorMut};
+    use crate::{parse, JsonPointer, Map, Path, Value};
+
+    /// Writes down each callback with the path it was given
+    #[derive(Default)]
+    struct Trace(Vec<String>);
+
+    impl Visitor for Trace {
+        fn enter_object(&mut self, path: &Path, _members: &Map<Value>) {
+            self.0.push(format!("enter object {path}"));
+        }
+        fn leave_object(&mut self, path: &Path, _members: &Map<Value>) {
+            self.0.push(format!("leave object {path}"));
+        }
+        fn enter_array(&mut self, path: &Path, _elements: &[Value]) {
+            self.0.push(format!("enter array {path}"));
+        }
+        fn leave_array(&mut self, path: &Path, _elements: &[Value]) {
+            self.0.push(format!("leave array {path}"));
+        }
+        fn scalar(&mut self, path: &Path, value: &Value) {
+            self.0.push(format!("{path} = {value:?}"));
+        }
+    }
+
+    #[test]
+    fn walks_depth_first() {
+        let input = parse(String::from(r#"{"a": [1, {"b": null}]}"#)).unwrap();
+        let expected = vec![
+            "enter object $",
+            "enter array $.a",
+            "$.a[0] = Number(1.0)",
+            "enter object $.a[1]",
+            "$.a[1].b = Null",
+            "leave object $.a[1]",
+            "leave array $.a",
+            "leave object $",
+        ];
+        let mut trace = Trace::default();
+
+        walk(&input, &mut trace);
+
+        assert_eq!(trace.0, expected);
+    }
+
+    /// Drops nulls from arrays and doubles every number
+    struct Tidy;
+
+    impl VisitorMut for Tidy {
+        fn enter_array(&mut self, _path: &Path, elements: &mut Vec<Value>) {
+            elements.retain(|element| *element != Value::Null);
+        }
+        fn scalar(&mut self, _path: &Path, value: &mut Value) {
+            if let Value::Number(number) = value {
+                *number *= 2.0;
+            }
+        }
+    }
+
+    #[test]
+    fn transforms() {
+        let input =
+            r#"{"Name": "x", "Price": 1.26, "Tags": [null, "a", {"Id": null}], "Secret": 1}"#;
+        let input = parse(String::from(input)).unwrap();
+        let expected = parse(String::from(
+            r#"{"name": "x", "price": 1.3, "tags": ["a", {}]}"#,
+        ))
+        .unwrap();
+
+        let mut actual = input
+            .filter_keys(|key| key != "Secret")
+            .map_keys(|key| key.to_lowercase())
+            .map_values(|value| match value {
+                Value::Number(number) => Value::Number((number * 10.0).round() / 10.0),
+                value => value,
+            });
+        actual.retain(|value| *value != Value::Null);
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn walks_mutably() {
+        let mut input = parse(String::from(r#"{"a": [1, null, [2, null]], "b": 3}"#)).unwrap();
+        let expected = parse(String::from(r#"{"a": [2, [4]], "b": 6}"#)).unwrap();
+
+        walk_mut(&mut input, &mut Tidy);
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn iterates_leaves() {
+        let input = parse(String::from(r#"[{"a/b": 1}, [], [true, [null]], "x"]"#)).unwrap();
+        let scalar = Value::from(2);
+        let expected = vec![
+            (String::from("/0/a~1b"), Value::from(1)),
+            (String::from("/2/0"), Value::from(true)),
+            (String::from("/2/1/0"), Value::Null),
+            (String::from("/3"), Value::from("x")),
+        ];
+
+        let actual: Vec<_> = input
+            .leaves()
+            .map(|(pointer, value)| (pointer.to_string(), value.clone()))
+            .collect();
+        let root: Vec<_> = scalar.leaves().collect();
+
+        assert_eq!(actual, expected);
+        assert_eq!(root, vec![(JsonPointer::root(), &scalar)]);
+    }
+}
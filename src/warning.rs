@@ -0,0 +1,57 @@
+use crate::span::line_column;
+use std::fmt;
+
+/// Something that isn't strict json but was accepted because a lenient option allows it
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct Warning {
+    pub kind: WarningKind,
+    /// byte offset into the input
+    pub offset: usize,
+    /// 1-based line of the offset
+    pub line: usize,
+    /// 1-based column of the offset, counted in chars
+    pub column: usize,
+}
+
+/// The ways a document can deviate from RFC 8259 without failing to parse
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub enum WarningKind {
+    /// a comment, accepted by `ParseOptions::allow_comments`
+    Comment,
+    /// a comma before a closing `]` or `}`, accepted by `ParseOptions::allow_trailing_commas`
+    TrailingComma,
+    /// an object repeated this key, settled by `ParseOptions::duplicate_keys`
+    DuplicateKey(String),
+}
+
+impl Warning {
+    pub fn new(input: &str, offset: usize, kind: WarningKind) -> Self {
+        let (line, column) = line_column(input, offset);
+        Self {
+            kind,
+            offset,
+            line,
+            column,
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} at line {}, column {}",
+            self.kind, self.line, self.column
+        )
+    }
+}
+
+impl fmt::Display for WarningKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WarningKind::Comment => write!(f, "comment"),
+            WarningKind::TrailingComma => write!(f, "trailing comma"),
+            WarningKind::DuplicateKey(key) => write!(f, "duplicate key {key:?}"),
+        }
+    }
+}
@@ -0,0 +1,197 @@
+use crate::{Map, Value};
+use std::collections::HashSet;
+
+impl Value {
+    /// Sorts an array, typically of objects, by the value each element has at `pointer`,
+    /// e.g. `/user/age`, using the cross-kind order of `Value`. The sort is stable, and
+    /// elements without a value there come first. Does nothing to anything but an array.
+    pub fn sort_by_key_path(&mut self, pointer: &str) {
+        if let Value::Array(elements) = self {
+            elements.sort_by(|a, b| a.pointer(pointer).cmp(&b.pointer(pointer)));
+        }
+    }
+
+    /// Drops the elements of an array whose value at `pointer` an earlier element already
+    /// had, e.g. to keep one record per `/id`. Elements without a value there are all kept.
+    /// Does nothing to anything but an array.
+    pub fn dedup_by(&mut self, pointer: &str) {
+        if let Value::Array(elements) = self {
+            let mut seen = HashSet::new();
+            elements.retain(|element| match element.pointer(pointer) {
+                Some(key) => seen.insert(key.clone()),
+                None => true,
+            });
+        }
+    }
+
+    /// An object grouping the elements of an array by their value at `pointer`, each key
+    /// holding an array of the elements with that value in their original order. Values are
+    /// written as keys the way `as_string_lossy` does, so `1` and `"1"` share a group.
+    /// Elements without a scalar there are left out, and anything but an array gives an
+    /// empty object.
+    pub fn group_by(&self, pointer: &str) -> Value {
+        let mut groups: Map<Value> = Map::new();
+        if let Value::Array(elements) = self {
+            for element in elements {
+                let Some(key) = element.pointer(pointer).and_then(group_key) else {
+                    continue;
+                };
+                match groups
+                    .entry(key)
+                    .or_insert_with(|| Value::Array(Vec::new()))
+                {
+                    Value::Array(group) => group.push(element.clone()),
+                    _ => unreachable!("every group is an array"),
+                }
+            }
+        }
+        Value::Object(groups)
+    }
+
+    /// Moves the member under `from` to `to`, replacing any member already there, e.g. to
+    /// rename a legacy key. Returns whether there was a member to move; anything but an
+    /// object is left alone.
+    pub fn rename_key(&mut self, from: &str, to: &str) -> bool {
+        let Value::Object(members) = self else {
+            return false;
+        };
+        match members.remove(from) {
+            Some(member) => {
+                members.insert(String::from(to), member);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Drops every member of an object whose key isn't one of `keys`, to whitelist fields.
+    /// Anything but an object is left alone.
+    pub fn retain_keys(&mut self, keys: &[&str]) {
+        if let Value::Object(members) = self {
+            members.retain(|key, _| keys.contains(&key.as_str()));
+        }
+    }
+
+    /// A copy of an object with only the members under `keys`, or of an array with each
+    /// element projected that way, for shaping responses. Keys that aren't there are skipped,
+    /// and anything but an object or array is copied as it is.
+    pub fn project(&self, keys: &[&str]) -> Value {
+        match self {
+            Value::Object(members) => Value::Object(
+                keys.iter()
+                    .filter_map(|&key| Some((String::from(key), members.get(key)?.clone())))
+                    .collect(),
+            ),
+            Value::Array(elements) => Value::Array(
+                elements
+                    .iter()
+                    .map(|element| element.project(keys))
+                    .collect(),
+            ),
+            value => value.clone(),
+        }
+    }
+}
+
+/// The key of the group a value belongs in, `null` being grouped too
+fn group_key(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => Some(String::from("null")),
+        value => value.as_string_lossy().map(String::from),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{parse, Value};
+
+    fn value(input: &str) -> Value {
+        parse(String::from(input)).unwrap()
+    }
+
+    fn records() -> Value {
+        value(
+            r#"[
+                {"id": 3, "team": "b", "user": {"age": 40}},
+                {"id": 1, "team": "a", "user": {"age": 25}},
+                {"id": 3, "team": "a"},
+                {"id": 2, "team": null, "user": {"age": 25}}
+            ]"#,
+        )
+    }
+
+    #[test]
+    fn sorts_by_key_path() {
+        let mut input = records();
+        let expected = value(
+            r#"[
+                {"id": 3, "team": "a"},
+                {"id": 1, "team": "a", "user": {"age": 25}},
+                {"id": 2, "team": null, "user": {"age": 25}},
+                {"id": 3, "team": "b", "user": {"age": 40}}
+            ]"#,
+        );
+
+        input.sort_by_key_path("/user/age");
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn dedups_by_field() {
+        let mut input = records();
+        let expected = value(
+            r#"[
+                {"id": 3, "team": "b", "user": {"age": 40}},
+                {"id": 1, "team": "a", "user": {"age": 25}},
+                {"id": 2, "team": null, "user": {"age": 25}}
+            ]"#,
+        );
+
+        input.dedup_by("/id");
+
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn groups_by_field() {
+        let input = records();
+        let expected = value(
+            r#"{
+                "a": [{"id": 1, "team": "a", "user": {"age": 25}}, {"id": 3, "team": "a"}],
+                "b": [{"id": 3, "team": "b", "user": {"age": 40}}],
+                "null": [{"id": 2, "team": null, "user": {"age": 25}}]
+            }"#,
+        );
+
+        let actual = input.group_by("/team");
+
+        assert_eq!(actual, expected);
+        assert_eq!(input.group_by("/missing"), value("{}"));
+    }
+
+    #[test]
+    fn shapes_objects() {
+        let mut input = value(r#"{"userName": "a", "id": 1, "password": "x", "age": 2}"#);
+        let expected = value(r#"{"user_name": "a", "id": 1}"#);
+
+        let renamed = input.rename_key("userName", "user_name");
+        let missing = input.rename_key("email", "mail");
+        input.retain_keys(&["user_name", "id"]);
+
+        assert!(renamed);
+        assert!(!missing);
+        assert_eq!(input, expected);
+    }
+
+    #[test]
+    fn projects() {
+        let input = records();
+        let expected = value(r#"[{"id": 3}, {"id": 1}, {"id": 3}, {"id": 2}]"#);
+
+        let actual = input.project(&["id", "missing"]);
+
+        assert_eq!(actual, expected);
+        assert_eq!(Value::from(1).project(&["id"]), Value::from(1));
+    }
+}
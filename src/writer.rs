@@ -0,0 +1,747 @@
+use crate::error::kind_predicates;
+use crate::reader::Event;
+use crate::serialize::{write_document, Notation, SerializeOptions};
+use crate::{ErrorKind, Value};
+#[cfg(feature = "sorted_maps")]
+use std::collections::btree_map::Iter as MapIter;
+#[cfg(not(feature = "sorted_maps"))]
+use std::collections::hash_map::Iter as MapIter;
+use std::io::{self, Write};
+use std::{fmt, mem, slice};
+
+/// Possible errors from writing json
+#[derive(Debug)]
+pub enum WriteError {
+    /// the underlying writer failed
+    Io(io::Error),
+    /// a value was written in an object where a key belongs
+    ExpectedKey,
+    /// a key was written outside of an object or straight after another key
+    UnexpectedKey,
+    /// `end` was called with no array or object open, or straight after a key
+    NothingToEnd,
+    /// a value was written after the document was already complete
+    DocumentComplete,
+    /// the document was finished without a value or with containers still open
+    Unfinished,
+    /// NaN and the infinities have no json representation
+    NonFiniteNumber,
+    /// an `EndArray` or `EndObject` event doesn't match the innermost container
+    MismatchedEnd,
+}
+
+impl fmt::Display for WriteError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            WriteError::Io(err) => write!(f, "failed to write output: {err}"),
+            WriteError::ExpectedKey => write!(f, "expected a key before the value"),
+            WriteError::UnexpectedKey => {
+                write!(f, "a key can only be written where a member starts")
+            }
+            WriteError::NothingToEnd => write!(f, "no array or object to end"),
+            WriteError::DocumentComplete => write!(f, "the document already has a value"),
+            WriteError::Unfinished => write!(f, "the document is unfinished"),
+            WriteError::NonFiniteNumber => write!(f, "number is not finite"),
+            WriteError::MismatchedEnd => write!(f, "the end doesn't match the container"),
+        }
+    }
+}
+
+impl WriteError {
+    /// The category of the error: `Unfinished` is `Eof`, `NonFiniteNumber` is `Data` and
+    /// writing something in the wrong place is `Syntax`
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            WriteError::Io(_) => ErrorKind::Io,
+            WriteError::Unfinished => ErrorKind::Eof,
+            WriteError::NonFiniteNumber => ErrorKind::Data,
+            WriteError::ExpectedKey
+            | WriteError::UnexpectedKey
+            | WriteError::NothingToEnd
+            | WriteError::DocumentComplete
+            | WriteError::MismatchedEnd => ErrorKind::Syntax,
+        }
+    }
+}
+
+kind_predicates!(WriteError);
+
+impl std::error::Error for WriteError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            WriteError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for WriteError {
+    fn from(err: io::Error) -> Self {
+        WriteError::Io(err)
+    }
+}
+
+/// An array or object the writer is inside of
+#[derive(Debug)]
+struct Frame {
+    object: bool,
+    /// whether nothing has been written inside it yet
+    empty: bool,
+    /// whether a key has been written without its value
+    keyed: bool,
+}
+
+/// Writes a document a piece at a time, so large output can be produced without building a
+/// `Value` first. Each call is checked against what has been written so far, the output is
+/// always on its way to being valid json.
+#[derive(Debug)]
+pub struct JsonWriter<W> {
+    writer: W,
+    stack: Vec<Frame>,
+    /// whether the top level value has been started
+    started: bool,
+    /// the value being serialized, so one that can't be written leaves the output as it was
+    scratch: Vec<u8>,
+}
+
+impl<W: Write> JsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            stack: Vec::new(),
+            started: false,
+            scratch: Vec::new(),
+        }
+    }
+
+    pub fn begin_object(&mut self) -> Result<(), WriteError> {
+        self.begin_value()?;
+        self.writer.write_all(b"{")?;
+        self.open(true);
+        Ok(())
+    }
+
+    pub fn begin_array(&mut self) -> Result<(), WriteError> {
+        self.begin_value()?;
+        self.writer.write_all(b"[")?;
+        self.open(false);
+        Ok(())
+    }
+
+    /// Writes the key of the next object member
+    pub fn key(&mut self, key: &str) -> Result<(), WriteError> {
+        let Some(frame) = self.stack.last_mut() else {
+            return Err(WriteError::UnexpectedKey);
+        };
+        if !frame.object || frame.keyed {
+            return Err(WriteError::UnexpectedKey);
+        }
+        if !frame.empty {
+            self.writer.write_all(b",")?;
+        }
+        frame.empty = false;
+        frame.keyed = true;
+        write_string(&mut self.writer, key)?;
+        self.writer.write_all(b":")?;
+        Ok(())
+    }
+
+    /// Writes a whole value, arrays and objects included. A value that can't be written, such
+    /// as one holding NaN, leaves the output and what may come next unchanged.
+    pub fn value(&mut self, value: &Value) -> Result<(), WriteError> {
+        self.scratch.clear();
+        write_value(&mut self.scratch, value)?;
+        self.begin_value()?;
+        Ok(self.writer.write_all(&self.scratch)?)
+    }
+
+    /// Writes an event as read by `JsonReader`, an end event must match the innermost container
+    pub fn write_event(&mut self, event: &Event) -> Result<(), WriteError> {
+        match event {
+            Event::StartObject => self.begin_object(),
+            Event::StartArray => self.begin_array(),
+            Event::EndObject | Event::EndArray => {
+                let object = matches!(event, Event::EndObject);
+                match self.stack.last() {
+                    Some(frame) if frame.object != object => Err(WriteError::MismatchedEnd),
+                    _ => self.end(),
+                }
+            }
+            Event::Key(key) => self.key(key),
+            Event::String(string) => {
+                self.begin_value()?;
+                Ok(write_string(&mut self.writer, string)?)
+            }
+            Event::Number(number) => self.value(&Value::Number(*number)),
+            Event::Boolean(boolean) => self.value(&Value::Boolean(*boolean)),
+            Event::Null => self.value(&Value::Null),
+        }
+    }
+
+    /// Closes the innermost array or object
+    pub fn end(&mut self) -> Result<(), WriteError> {
+        match self.stack.last() {
+            Some(frame) if !frame.keyed => {
+                let closer: &[u8] = if frame.object { b"}" } else { b"]" };
+                self.stack.pop();
+                self.writer.write_all(closer)?;
+                Ok(())
+            }
+            _ => Err(WriteError::NothingToEnd),
+        }
+    }
+
+    /// Checks the document is complete and hands back the underlying writer
+    pub fn finish(mut self) -> Result<W, WriteError> {
+        if !self.started || !self.stack.is_empty() {
+            return Err(WriteError::Unfinished);
+        }
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+
+    /// Checks a value may go here and writes whatever separates it from the value before
+    fn begin_value(&mut self) -> Result<(), WriteError> {
+        match self.stack.last_mut() {
+            None if self.started => Err(WriteError::DocumentComplete),
+            None => {
+                self.started = true;
+                Ok(())
+            }
+            Some(frame) if frame.object => match frame.keyed {
+                true => {
+                    frame.keyed = false;
+                    Ok(())
+                }
+                false => Err(WriteError::ExpectedKey),
+            },
+            Some(frame) => {
+                if !frame.empty {
+                    self.writer.write_all(b",")?;
+                }
+                frame.empty = false;
+                Ok(())
+            }
+        }
+    }
+
+    fn open(&mut self, object: bool) {
+        self.stack.push(Frame {
+            object,
+            empty: true,
+            keyed: false,
+        });
+    }
+}
+
+/// When an `NdjsonWriter` flushes the writer underneath it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum FlushPolicy {
+    /// only when asked to or finished, for batch output
+    #[default]
+    Manual,
+    /// after every line, so a process tailing a live log file sees each record as it is written
+    EveryLine,
+    /// after every this many lines
+    EveryLines(usize),
+}
+
+/// Writes newline delimited json, one compact value per line. Strings escape their newlines,
+/// so a line break only ever ends a record.
+#[derive(Debug)]
+pub struct NdjsonWriter<W> {
+    writer: W,
+    flush: FlushPolicy,
+    /// lines written since the last flush
+    unflushed: usize,
+    /// the line being serialized, so a value that can't be written leaves no partial line
+    line: Vec<u8>,
+    /// written before each value
+    prefix: &'static [u8],
+}
+
+impl<W: Write> NdjsonWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self::with_flush(writer, FlushPolicy::default())
+    }
+
+    pub fn with_flush(writer: W, flush: FlushPolicy) -> Self {
+        Self::with_prefix(writer, flush, b"")
+    }
+
+    /// A writer starting each line with `prefix`, for formats built on newline delimited json
+    pub(crate) fn with_prefix(writer: W, flush: FlushPolicy, prefix: &'static [u8]) -> Self {
+        Self {
+            writer,
+            flush,
+            unflushed: 0,
+            line: Vec::new(),
+            prefix,
+        }
+    }
+
+    /// Writes `value` followed by a newline in a single write
+    pub fn write(&mut self, value: &Value) -> Result<(), WriteError> {
+        self.line.clear();
+        self.line.extend_from_slice(self.prefix);
+        write_value(&mut self.line, value)?;
+        self.line.push(b'\n');
+        self.writer.write_all(&self.line)?;
+        self.unflushed += 1;
+        let due = match self.flush {
+            FlushPolicy::Manual => false,
+            FlushPolicy::EveryLine => true,
+            FlushPolicy::EveryLines(lines) => self.unflushed >= lines,
+        };
+        if due {
+            self.flush()?;
+        }
+        Ok(())
+    }
+
+    /// Writes every value in turn, stopping at the first that fails
+    pub fn write_all<'a>(
+        &mut self,
+        values: impl IntoIterator<Item = &'a Value>,
+    ) -> Result<(), WriteError> {
+        for value in values {
+            self.write(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), WriteError> {
+        self.unflushed = 0;
+        self.writer.flush()?;
+        Ok(())
+    }
+
+    /// Flushes and hands back the underlying writer
+    pub fn finish(mut self) -> Result<W, WriteError> {
+        self.flush()?;
+        Ok(self.writer)
+    }
+}
+
+/// Serializes `value` as compact json in pieces of `chunk_size` bytes, the last one possibly
+/// shorter, so a huge value can be streamed out without one giant allocation
+pub fn to_chunks(value: &Value, chunk_size: usize) -> Chunks<'_> {
+    Chunks {
+        next: Some(value),
+        stack: Vec::new(),
+        pending: Vec::new(),
+        chunk_size: chunk_size.max(1),
+        failed: false,
+    }
+}
+
+/// An array or object being serialized by `Chunks`
+#[derive(Debug)]
+enum ChunkFrame<'a> {
+    Array(slice::Iter<'a, Value>, bool),
+    Object(MapIter<'a, String, Value>, bool),
+}
+
+/// The output of `to_chunks`. Iteration stops after the first error.
+#[derive(Debug)]
+pub struct Chunks<'a> {
+    /// the value to start serializing next
+    next: Option<&'a Value>,
+    /// the containers being serialized, each with whether it is still empty
+    stack: Vec<ChunkFrame<'a>>,
+    /// output not yet handed out
+    pending: Vec<u8>,
+    chunk_size: usize,
+    failed: bool,
+}
+
+impl Chunks<'_> {
+    /// Serializes a little more of the value, returning false once it is all done
+    fn step(&mut self) -> Result<bool, WriteError> {
+        if let Some(value) = self.next.take() {
+            match value {
+                Value::Array(elements) => {
+                    self.pending.push(b'[');
+                    self.stack.push(ChunkFrame::Array(elements.iter(), true));
+                }
+                Value::Object(members) => {
+                    self.pending.push(b'{');
+                    self.stack.push(ChunkFrame::Object(members.iter(), true));
+                }
+                scalar => write_value(&mut self.pending, scalar)?,
+            }
+            return Ok(true);
+        }
+        match self.stack.last_mut() {
+            None => return Ok(false),
+            Some(ChunkFrame::Array(elements, empty)) => match elements.next() {
+                Some(element) => {
+                    if !mem::replace(empty, false) {
+                        self.pending.push(b',');
+                    }
+                    self.next = Some(element);
+                }
+                None => {
+                    self.pending.push(b']');
+                    self.stack.pop();
+                }
+            },
+            Some(ChunkFrame::Object(members, empty)) => match members.next() {
+                Some((key, member)) => {
+                    if !mem::replace(empty, false) {
+                        self.pending.push(b',');
+                    }
+                    write_string(&mut self.pending, key)?;
+                    self.pending.push(b':');
+                    self.next = Some(member);
+                }
+                None => {
+                    self.pending.push(b'}');
+                    self.stack.pop();
+                }
+            },
+        }
+        Ok(true)
+    }
+}
+
+impl Iterator for Chunks<'_> {
+    type Item = Result<Vec<u8>, WriteError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.failed {
+            return None;
+        }
+        while self.pending.len() < self.chunk_size {
+            match self.step() {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(err) => {
+                    self.failed = true;
+                    return Some(Err(err));
+                }
+            }
+        }
+        if self.pending.is_empty() {
+            return None;
+        }
+        // a long string can overflow a chunk, the rest of it goes in the next one
+        let rest = self
+            .pending
+            .split_off(self.chunk_size.min(self.pending.len()));
+        Some(Ok(mem::replace(&mut self.pending, rest)))
+    }
+}
+
+/// Writes `value` as compact json, straight to `writer` with no buffering or flush
+pub(crate) fn write_value(writer: &mut impl Write, value: &Value) -> Result<(), WriteError> {
+    write_document(writer, value, &SerializeOptions::new())
+}
+
+/// Writes the shortest text that parses back to exactly `number`, in exponent form where
+/// `notation` says
+pub(crate) fn write_number(
+    writer: &mut impl Write,
+    number: f64,
+    notation: Notation,
+) -> Result<(), WriteError> {
+    if !number.is_finite() {
+        return Err(WriteError::NonFiniteNumber);
+    }
+    // both formats pick the fewest digits that round trip, `{:e}` also says where the point is
+    let scientific = format!("{number:e}");
+    let (digits, exponent) = scientific
+        .split_once('e')
+        .expect("`{:e}` always has an exponent");
+    let exponent: i32 = exponent.parse().expect("`{:e}` exponents are integers");
+    let scientific = match notation {
+        Notation::Never => false,
+        Notation::Always => true,
+        Notation::Outside { min, max } => !(min..max).contains(&exponent),
+    };
+    match scientific {
+        true => write!(writer, "{digits}e{exponent}")?,
+        false => write!(writer, "{number}")?,
+    }
+    Ok(())
+}
+
+/// Writes `string` in quotes, escaping only what json requires
+pub(crate) fn write_string(writer: &mut impl Write, string: &str) -> io::Result<()> {
+    write_string_escaping(writer, string, |_| false)
+}
+
+/// Writes `string` in quotes, escaping what json requires and as `\u` escapes whatever else
+/// `escape` picks out
+pub(crate) fn write_string_escaping(
+    writer: &mut impl Write,
+    string: &str,
+    escape: impl Fn(char) -> bool,
+) -> io::Result<()> {
+    writer.write_all(b"\"")?;
+    // copy runs of chars that need no escaping in one go
+    let mut start = 0;
+    for (i, c) in string.char_indices() {
+        let short = match c {
+            '"' => Some("\\\""),
+            '\\' => Some("\\\\"),
+            '\n' => Some("\\n"),
+            '\r' => Some("\\r"),
+            '\t' => Some("\\t"),
+            '\u{8}' => Some("\\b"),
+            '\u{c}' => Some("\\f"),
+            // the other control chars have no short escape
+            c if c < ' ' || escape(c) => None,
+            _ => continue,
+        };
+        writer.write_all(&string.as_bytes()[start..i])?;
+        match short {
+            Some(short) => writer.write_all(short.as_bytes())?,
+            // chars past the basic plane take a surrogate pair
+            None => {
+                for unit in c.encode_utf16(&mut [0; 2]) {
+                    write!(writer, "\\u{unit:04x}")?;
+                }
+            }
+        }
+        start = i + c.len_utf8();
+    }
+    writer.write_all(&string.as_bytes()[start..])?;
+    writer.write_all(b"\"")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        to_chunks, write_number, write_value, FlushPolicy, JsonWriter, NdjsonWriter, WriteError,
+    };
+    use crate::serialize::Notation;
+    use crate::{parse, parse_documents, Event, Map, Value};
+    use std::io::{self, Write};
+
+    #[test]
+    fn writes_document() {
+        let expected = r#"{"name":"a \"b\"\n","sizes":[3.5,true,null,[]],"empty":{}}"#;
+
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.begin_object().unwrap();
+        writer.key("name").unwrap();
+        writer
+            .value(&Value::String(String::from("a \"b\"\n")))
+            .unwrap();
+        writer.key("sizes").unwrap();
+        writer.begin_array().unwrap();
+        writer.value(&Value::Number(3.5)).unwrap();
+        writer.value(&Value::Boolean(true)).unwrap();
+        writer.value(&Value::Null).unwrap();
+        writer.value(&Value::Array(Vec::new())).unwrap();
+        writer.end().unwrap();
+        writer.key("empty").unwrap();
+        writer.begin_object().unwrap();
+        writer.end().unwrap();
+        writer.end().unwrap();
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+        assert!(parse(actual).is_ok());
+    }
+
+    #[test]
+    fn checks_structure() {
+        let mut writer = JsonWriter::new(Vec::new());
+        assert!(matches!(writer.key("a"), Err(WriteError::UnexpectedKey)));
+        writer.begin_object().unwrap();
+        assert!(matches!(
+            writer.value(&Value::Null),
+            Err(WriteError::ExpectedKey)
+        ));
+        writer.key("a").unwrap();
+        assert!(matches!(writer.key("b"), Err(WriteError::UnexpectedKey)));
+        writer.value(&Value::Null).unwrap();
+        writer.end().unwrap();
+        assert!(matches!(writer.end(), Err(WriteError::NothingToEnd)));
+        assert!(matches!(
+            writer.value(&Value::Null),
+            Err(WriteError::DocumentComplete)
+        ));
+        assert!(JsonWriter::new(Vec::new()).finish().is_err());
+    }
+
+    #[test]
+    fn failed_value_changes_nothing() {
+        let bad = Value::Array(vec![Value::from(2), Value::Number(f64::NAN)]);
+        let expected = "[1,3]";
+
+        let mut writer = JsonWriter::new(Vec::new());
+        writer.begin_array().unwrap();
+        writer.value(&Value::from(1)).unwrap();
+        let failed = writer.value(&bad);
+        let event = writer.write_event(&Event::Number(f64::INFINITY));
+        writer.value(&Value::from(3)).unwrap();
+        writer.end().unwrap();
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert!(matches!(failed, Err(WriteError::NonFiniteNumber)));
+        assert!(matches!(event, Err(WriteError::NonFiniteNumber)));
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn escapes_control_chars() {
+        let expected = "\"\\u0001\\t\u{e9}\"";
+
+        let mut writer = JsonWriter::new(Vec::new());
+        writer
+            .value(&Value::String(String::from("\u{1}\t\u{e9}")))
+            .unwrap();
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn writes_ndjson() {
+        let values = vec![
+            Value::String(String::from("two\nlines")),
+            Value::Array(vec![Value::Null, Value::Number(1.5)]),
+        ];
+        let expected = "\"two\\nlines\"\n[null,1.5]\n";
+
+        let mut writer = NdjsonWriter::new(Vec::new());
+        writer.write_all(&values).unwrap();
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+        assert_eq!(parse_documents(actual), Ok(values));
+    }
+
+    #[test]
+    fn ndjson_skips_bad_lines() {
+        let values = [Value::Null, Value::Number(f64::NAN), Value::Boolean(true)];
+        let expected = "null\ntrue\n";
+
+        let mut writer = NdjsonWriter::new(Vec::new());
+        for value in &values {
+            let _ = writer.write(value);
+        }
+        let actual = String::from_utf8(writer.finish().unwrap()).unwrap();
+
+        assert_eq!(actual, expected);
+    }
+
+    #[test]
+    fn ndjson_flushes() {
+        // counts flushes of the writer underneath
+        #[derive(Default)]
+        struct Flushes(usize);
+        impl Write for Flushes {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                Ok(buf.len())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                self.0 += 1;
+                Ok(())
+            }
+        }
+        let values = vec![Value::Null; 5];
+        let cases = [
+            (FlushPolicy::Manual, 1),
+            (FlushPolicy::EveryLine, 6),
+            (FlushPolicy::EveryLines(2), 3),
+        ];
+
+        for (flush, expected) in cases {
+            let mut writer = NdjsonWriter::with_flush(Flushes::default(), flush);
+            writer.write_all(&values).unwrap();
+            let actual = writer.finish().unwrap().0;
+
+            assert_eq!(actual, expected, "{flush:?}");
+        }
+    }
+
+    #[test]
+    fn serializes_in_chunks() {
+        let value = Value::Object(Map::from([(
+            String::from("list"),
+            Value::Array(vec![
+                Value::String(String::from("a long string")),
+                Value::Null,
+                Value::Array(Vec::new()),
+            ]),
+        )]));
+        let mut expected = Vec::new();
+        write_value(&mut expected, &value).unwrap();
+
+        let chunks: Vec<_> = to_chunks(&value, 4).map(Result::unwrap).collect();
+
+        let (last, full) = chunks.split_last().unwrap();
+
+        assert!(full.iter().all(|chunk| chunk.len() == 4));
+        assert!((1..=4).contains(&last.len()));
+        assert_eq!(chunks.concat(), expected);
+        assert_eq!(chunks.len(), expected.len().div_ceil(4));
+    }
+
+    #[test]
+    fn chunks_stop_at_error() {
+        let value = Value::Array(vec![Value::Null, Value::Number(f64::INFINITY), Value::Null]);
+
+        let mut chunks = to_chunks(&value, 1);
+        assert_eq!(chunks.next().unwrap().unwrap(), b"[");
+        let actual = chunks.find_map(Result::err);
+
+        assert!(matches!(actual, Some(WriteError::NonFiniteNumber)));
+        assert!(chunks.next().is_none());
+    }
+
+    #[test]
+    fn writes_shortest_numbers() {
+        let cases = [
+            (0.1, "0.1"),
+            (1.0 / 3.0, "0.3333333333333333"),
+            (100.0, "100"),
+            (-0.0, "-0"),
+            (1e20, "100000000000000000000"),
+            (1e21, "1e21"),
+            (-1.5e300, "-1.5e300"),
+            (0.000001, "0.000001"),
+            (1.25e-7, "1.25e-7"),
+            (5e-324, "5e-324"),
+            (f64::MAX, "1.7976931348623157e308"),
+        ];
+
+        for (number, expected) in cases {
+            let mut actual = Vec::new();
+            write_number(&mut actual, number, Notation::default()).unwrap();
+            let actual = String::from_utf8(actual).unwrap();
+
+            assert_eq!(actual, expected);
+            assert_eq!(actual.parse::<f64>().unwrap().to_bits(), number.to_bits());
+        }
+    }
+
+    #[test]
+    fn writes_notations() {
+        let numbers = [0.0, 1500.0, -0.025, 1e21];
+        let cases = [
+            (Notation::Never, "0,1500,-0.025,1000000000000000000000"),
+            (Notation::Always, "0e0,1.5e3,-2.5e-2,1e21"),
+            (
+                Notation::Outside { min: -1, max: 3 },
+                "0,1.5e3,-2.5e-2,1e21",
+            ),
+        ];
+
+        for (notation, expected) in cases {
+            let mut actual = Vec::new();
+            for (i, number) in numbers.into_iter().enumerate() {
+                if i > 0 {
+                    actual.push(b',');
+                }
+                write_number(&mut actual, number, notation).unwrap();
+            }
+
+            assert_eq!(String::from_utf8(actual).unwrap(), expected);
+        }
+    }
+}